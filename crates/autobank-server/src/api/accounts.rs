@@ -3,10 +3,10 @@
 use crate::AppState;
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     routing::get,
 };
-use sb1_api::models::{AccountData, TransactionResponse};
+use sb1_api::models::{AccountData, TransactionQuery, TransactionResponse};
 use serde::Serialize;
 
 /// Creates the accounts router.
@@ -54,13 +54,16 @@ pub async fn get_account(
 }
 
 /// Get transactions for an account.
+///
+/// Accepts `fromDate`, `toDate`, `rowLimit` and `cursor` query parameters.
 pub async fn get_transactions(
     State(state): State<AppState>,
     Path(key): Path<String>,
+    Query(query): Query<TransactionQuery>,
 ) -> Result<Json<TransactionResponse>, Json<ApiError>> {
     state
         .bank_client
-        .get_transactions(&key)
+        .get_transactions(&key, &query)
         .await
         .map(Json)
         .map_err(|e| Json(ApiError { error: e.to_string() }))
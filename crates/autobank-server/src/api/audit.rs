@@ -36,10 +36,9 @@ pub async fn list_audit_entries(
     Query(query): Query<ListAuditQuery>,
 ) -> Result<Json<Vec<AuditEntry>>, Json<ApiError>> {
     let limit = query.limit.unwrap_or(100);
-    // TODO: Add event_type filtering when needed
     state
         .db
-        .query_audit(limit)
+        .query_audit(limit, query.event_type.as_deref())
         .await
         .map(Json)
         .map_err(|e| Json(ApiError { error: e.to_string() }))
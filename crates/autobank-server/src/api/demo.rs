@@ -7,6 +7,8 @@ use axum::{
     http::StatusCode,
     routing::{get, post},
 };
+use sb1_api::Money;
+use sb1_api::models::AccountType;
 use serde::{Deserialize, Serialize};
 
 pub fn router() -> Router<AppState> {
@@ -39,7 +41,7 @@ pub async fn demo_status(State(state): State<AppState>) -> Json<DemoStatusRespon
 pub struct CreateTransactionRequest {
     pub account_key: String,
     pub description: String,
-    pub amount: f64,
+    pub amount: Money,
     #[serde(default = "default_true")]
     pub is_settled: bool,
 }
@@ -105,8 +107,8 @@ pub struct DemoAccount {
     pub key: String,
     pub name: String,
     pub account_number: String,
-    pub balance: f64,
-    pub account_type: String,
+    pub balance: Money,
+    pub account_type: AccountType,
 }
 
 #[derive(Serialize)]
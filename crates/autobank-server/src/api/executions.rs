@@ -5,7 +5,7 @@ use crate::rules::RuleExecution;
 use axum::{
     Json, Router,
     extract::{Path, Query, State},
-    routing::get,
+    routing::{get, post},
 };
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -15,6 +15,7 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list_executions))
         .route("/{id}", get(get_execution))
+        .route("/{id}/resend", post(resend_execution))
 }
 
 #[derive(Serialize)]
@@ -56,6 +57,19 @@ pub async fn get_execution(
         .ok_or_else(|| Json(ApiError { error: "Execution not found".to_string() }))
 }
 
+/// Send a transfer that was blocked, once the block has been lifted.
+pub async fn resend_execution(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<RuleExecution>, Json<ApiError>> {
+    state
+        .rule_engine
+        .resend_blocked(&id.to_string())
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError { error: e.to_string() }))
+}
+
 /// Get executions for a specific rule.
 pub async fn get_rule_executions(
     State(state): State<AppState>,
@@ -5,6 +5,7 @@ mod audit;
 mod demo;
 mod executions;
 mod health;
+mod proposals;
 mod rules;
 mod system;
 
@@ -27,6 +28,7 @@ pub fn create_router(state: AppState) -> Router {
         .nest("/api/rules", rules::router())
         .route("/api/rules/{rule_id}/executions", get(executions::get_rule_executions))
         .nest("/api/executions", executions::router())
+        .nest("/api/proposals", proposals::router())
         .nest("/api/audit", audit::router())
         .nest("/api/system", system::router())
         .nest("/api/demo", demo::router())
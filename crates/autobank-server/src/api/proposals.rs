@@ -0,0 +1,110 @@
+//! Transfer proposal API endpoints.
+
+use crate::AppState;
+use crate::rules::TransferProposal;
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Creates the proposals router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_proposals))
+        .route("/{id}", get(get_proposal))
+        .route("/{id}/approve", post(approve_proposal))
+        .route("/{id}/reject", post(reject_proposal))
+}
+
+#[derive(Serialize)]
+pub struct ApiError {
+    error: String,
+}
+
+#[derive(Deserialize)]
+pub struct ListProposalsQuery {
+    /// Maximum number of proposals to return (default: 100)
+    pub limit: Option<i64>,
+    /// Filter by status
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct DecisionRequest {
+    /// Who is approving or rejecting the proposal.
+    pub decided_by: String,
+}
+
+/// List transfer proposals, newest first.
+pub async fn list_proposals(
+    State(state): State<AppState>,
+    Query(query): Query<ListProposalsQuery>,
+) -> Result<Json<Vec<TransferProposal>>, Json<ApiError>> {
+    let limit = query.limit.unwrap_or(100);
+    state
+        .rule_engine
+        .expire_proposals()
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+    state
+        .db
+        .list_proposals(query.status.as_deref(), limit)
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError { error: e.to_string() }))
+}
+
+/// Get a single proposal by ID.
+pub async fn get_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<TransferProposal>, Json<ApiError>> {
+    state
+        .db
+        .get_proposal(&id.to_string())
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?
+        .map(Json)
+        .ok_or_else(|| Json(ApiError { error: "Proposal not found".to_string() }))
+}
+
+/// Approve a proposal and send its transfer.
+pub async fn approve_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<DecisionRequest>,
+) -> Result<Json<TransferProposal>, Json<ApiError>> {
+    let decided_by = decided_by(&req)?;
+    state
+        .rule_engine
+        .approve_proposal(&id.to_string(), decided_by)
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError { error: e.to_string() }))
+}
+
+/// Reject a proposal.
+pub async fn reject_proposal(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<DecisionRequest>,
+) -> Result<Json<TransferProposal>, Json<ApiError>> {
+    let decided_by = decided_by(&req)?;
+    state
+        .rule_engine
+        .reject_proposal(&id.to_string(), decided_by)
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError { error: e.to_string() }))
+}
+
+fn decided_by(req: &DecisionRequest) -> Result<&str, Json<ApiError>> {
+    let decided_by = req.decided_by.trim();
+    if decided_by.is_empty() {
+        return Err(Json(ApiError { error: "decided_by is required".to_string() }));
+    }
+    Ok(decided_by)
+}
@@ -1,12 +1,16 @@
 //! Rule management API endpoints.
 
 use crate::AppState;
-use crate::rules::Rule;
+use crate::rules::{
+    AccumulatorEntry, BacktestReport, BacktestSource, FireOn, Rule, RuleLimits, RuleTransactionLog, RuleTrigger,
+    ValidationError,
+};
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     routing::{get, post},
 };
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -14,21 +18,62 @@ use uuid::Uuid;
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list_rules).post(create_rule))
+        .route("/validate", post(validate_rule))
+        .route("/backtest", post(backtest_draft_rule))
         .route("/{id}", get(get_rule).put(update_rule).delete(delete_rule))
         .route("/{id}/enable", post(enable_rule))
         .route("/{id}/disable", post(disable_rule))
+        .route("/{id}/backfill", post(backfill_rule))
+        .route("/{id}/backtest", post(backtest_rule))
+        .route("/{id}/log", get(get_rule_log))
+        .route("/{id}/log/{transaction_id}", get(get_transaction_log))
+        .route("/{id}/accumulated", get(get_accumulated))
 }
 
 #[derive(Serialize)]
 pub struct ApiError {
     error: String,
+    /// Field-level problems when a rule is invalid.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<ValidationError>,
+}
+
+impl ApiError {
+    fn new(error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn invalid(errors: Vec<ValidationError>) -> Self {
+        Self {
+            error: "Rule is invalid".to_string(),
+            errors,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct CreateRuleRequest {
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
+    pub trigger: RuleTrigger,
     pub trigger_account_key: String,
+    #[serde(default)]
+    pub fire_on: FireOn,
+    #[serde(default)]
+    pub include_self_transfers: bool,
+    #[serde(default)]
+    pub limits: RuleLimits,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub stop_processing: bool,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub requires_approval: bool,
     pub conditions: Vec<crate::rules::Condition>,
     pub actions: Vec<crate::rules::Action>,
 }
@@ -37,11 +82,60 @@ pub struct CreateRuleRequest {
 pub struct UpdateRuleRequest {
     pub name: Option<String>,
     pub description: Option<String>,
+    pub trigger: Option<RuleTrigger>,
     pub trigger_account_key: Option<String>,
+    pub fire_on: Option<FireOn>,
+    pub include_self_transfers: Option<bool>,
+    pub limits: Option<RuleLimits>,
+    pub priority: Option<i32>,
+    pub stop_processing: Option<bool>,
+    /// New group; an empty string takes the rule out of its group.
+    pub group: Option<String>,
+    pub requires_approval: Option<bool>,
     pub conditions: Option<Vec<crate::rules::Condition>>,
     pub actions: Option<Vec<crate::rules::Action>>,
 }
 
+#[derive(Serialize)]
+pub struct ValidationResponse {
+    pub valid: bool,
+    pub errors: Vec<ValidationError>,
+}
+
+#[derive(Deserialize)]
+pub struct BackfillRequest {
+    /// Earliest transaction date to evaluate.
+    pub since: NaiveDate,
+}
+
+#[derive(Serialize)]
+pub struct BackfillResponse {
+    pub transactions_evaluated: usize,
+}
+
+#[derive(Deserialize)]
+pub struct BacktestRequest {
+    /// First transaction date to include.
+    pub from: NaiveDate,
+    /// Last transaction date to include (default: today in Europe/Oslo).
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub source: BacktestSource,
+}
+
+#[derive(Deserialize)]
+pub struct DraftBacktestRequest {
+    pub rule: CreateRuleRequest,
+    #[serde(flatten)]
+    pub window: BacktestRequest,
+}
+
+#[derive(Deserialize)]
+pub struct RuleLogQuery {
+    /// Maximum number of entries to return (default: 100)
+    pub limit: Option<i64>,
+}
+
 /// List all rules.
 pub async fn list_rules(
     State(state): State<AppState>,
@@ -51,7 +145,7 @@ pub async fn list_rules(
         .list_rules()
         .await
         .map(Json)
-        .map_err(|e| Json(ApiError { error: e.to_string() }))
+        .map_err(|e| Json(ApiError::new(e.to_string())))
 }
 
 /// Get a single rule by ID.
@@ -63,9 +157,60 @@ pub async fn get_rule(
         .db
         .get_rule(&id.to_string())
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?
+        .map_err(|e| Json(ApiError::new(e.to_string())))?
         .map(Json)
-        .ok_or_else(|| Json(ApiError { error: "Rule not found".to_string() }))
+        .ok_or_else(|| Json(ApiError::new("Rule not found".to_string())))
+}
+
+impl CreateRuleRequest {
+    /// Build a new, enabled rule from the request.
+    fn into_rule(self, now: i64) -> Rule {
+        Rule {
+            id: Uuid::new_v4().to_string(),
+            name: self.name,
+            description: self.description,
+            enabled: true,
+            trigger: self.trigger,
+            trigger_account_key: self.trigger_account_key,
+            fire_on: self.fire_on,
+            include_self_transfers: self.include_self_transfers,
+            limits: self.limits,
+            priority: self.priority,
+            stop_processing: self.stop_processing,
+            group: self.group.filter(|group| !group.trim().is_empty()),
+            requires_approval: self.requires_approval,
+            conditions: self.conditions,
+            actions: self.actions,
+            created_at: now,
+            updated_at: now,
+            activated_at: now,
+            next_run_at: None,
+        }
+    }
+}
+
+/// Validate a rule against the current accounts.
+async fn validation_errors(state: &AppState, rule: &Rule) -> Result<Vec<ValidationError>, Json<ApiError>> {
+    let accounts = state
+        .bank_client
+        .get_accounts()
+        .await
+        .map_err(|e| Json(ApiError::new(format!("Failed to fetch accounts for validation: {}", e))))?
+        .accounts;
+    Ok(rule.validate(&accounts))
+}
+
+/// Validate a rule without saving it.
+pub async fn validate_rule(
+    State(state): State<AppState>,
+    Json(req): Json<CreateRuleRequest>,
+) -> Result<Json<ValidationResponse>, Json<ApiError>> {
+    let rule = req.into_rule(chrono::Utc::now().timestamp());
+    let errors = validation_errors(&state, &rule).await?;
+    Ok(Json(ValidationResponse {
+        valid: errors.is_empty(),
+        errors,
+    }))
 }
 
 /// Create a new rule.
@@ -74,23 +219,22 @@ pub async fn create_rule(
     Json(req): Json<CreateRuleRequest>,
 ) -> Result<Json<Rule>, Json<ApiError>> {
     let now = chrono::Utc::now().timestamp();
-    let rule = Rule {
-        id: Uuid::new_v4().to_string(),
-        name: req.name,
-        description: req.description,
-        enabled: true,
-        trigger_account_key: req.trigger_account_key,
-        conditions: req.conditions,
-        actions: req.actions,
-        created_at: now,
-        updated_at: now,
-    };
+    let mut rule = req.into_rule(now);
+    let errors = validation_errors(&state, &rule).await?;
+    if !errors.is_empty() {
+        return Err(Json(ApiError::invalid(errors)));
+    }
+    rule.next_run_at = rule
+        .trigger
+        .next_run_after(now)
+        .map_err(|error| Json(ApiError::new(error)))?;
 
     state
         .db
         .create_rule(&rule)
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+    state.rule_engine.invalidate_rules();
 
     Ok(Json(rule))
 }
@@ -105,8 +249,8 @@ pub async fn update_rule(
         .db
         .get_rule(&id.to_string())
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?
-        .ok_or_else(|| Json(ApiError { error: "Rule not found".to_string() }))?;
+        .map_err(|e| Json(ApiError::new(e.to_string())))?
+        .ok_or_else(|| Json(ApiError::new("Rule not found".to_string())))?;
 
     if let Some(name) = req.name {
         rule.name = name;
@@ -114,27 +258,65 @@ pub async fn update_rule(
     if let Some(description) = req.description {
         rule.description = Some(description);
     }
+    let trigger_changed = req.trigger.is_some();
+    if let Some(trigger) = req.trigger {
+        rule.trigger = trigger;
+    }
     if let Some(trigger_account_key) = req.trigger_account_key {
         rule.trigger_account_key = trigger_account_key;
     }
+    if let Some(fire_on) = req.fire_on {
+        rule.fire_on = fire_on;
+    }
+    if let Some(include_self_transfers) = req.include_self_transfers {
+        rule.include_self_transfers = include_self_transfers;
+    }
+    if let Some(limits) = req.limits {
+        rule.limits = limits;
+    }
+    if let Some(priority) = req.priority {
+        rule.priority = priority;
+    }
+    if let Some(stop_processing) = req.stop_processing {
+        rule.stop_processing = stop_processing;
+    }
+    if let Some(group) = req.group {
+        rule.group = Some(group).filter(|group| !group.trim().is_empty());
+    }
+    if let Some(requires_approval) = req.requires_approval {
+        rule.requires_approval = requires_approval;
+    }
     if let Some(conditions) = req.conditions {
         rule.conditions = conditions;
     }
     if let Some(actions) = req.actions {
         rule.actions = actions;
     }
+
+    let errors = validation_errors(&state, &rule).await?;
+    if !errors.is_empty() {
+        return Err(Json(ApiError::invalid(errors)));
+    }
     rule.updated_at = chrono::Utc::now().timestamp();
+    if trigger_changed {
+        rule.next_run_at = rule
+            .trigger
+            .next_run_after(rule.updated_at)
+            .map_err(|error| Json(ApiError::new(error)))?;
+    }
 
     state
         .db
         .update_rule(&rule)
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+    state.rule_engine.invalidate_rules();
 
     Ok(Json(rule))
 }
 
-/// Delete a rule.
+/// Delete a rule. A rule that has run is disabled instead, keeping its
+/// history.
 pub async fn delete_rule(
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
@@ -143,8 +325,10 @@ pub async fn delete_rule(
         .db
         .delete_rule(&id.to_string())
         .await
-        .map(|_| Json(()))
-        .map_err(|e| Json(ApiError { error: e.to_string() }))
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+    state.rule_engine.invalidate_rules();
+
+    Ok(Json(()))
 }
 
 /// Enable a rule.
@@ -156,7 +340,8 @@ pub async fn enable_rule(
         .db
         .set_rule_enabled(&id.to_string(), true)
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+    state.rule_engine.invalidate_rules();
 
     get_rule(State(state), Path(id)).await
 }
@@ -170,7 +355,123 @@ pub async fn disable_rule(
         .db
         .set_rule_enabled(&id.to_string(), false)
         .await
-        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+    state.rule_engine.invalidate_rules();
 
     get_rule(State(state), Path(id)).await
 }
+
+/// Evaluate a rule against transactions from before its activation.
+pub async fn backfill_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<BackfillRequest>,
+) -> Result<Json<BackfillResponse>, Json<ApiError>> {
+    let rule = state
+        .db
+        .get_rule(&id.to_string())
+        .await
+        .map_err(|e| Json(ApiError::new(e.to_string())))?
+        .ok_or_else(|| Json(ApiError::new("Rule not found".to_string())))?;
+
+    if !rule.enabled {
+        return Err(Json(ApiError::new("Rule is disabled".to_string())));
+    }
+    if rule.trigger.is_scheduled() {
+        return Err(Json(ApiError::new("Scheduled rules cannot be backfilled".to_string())));
+    }
+    if req.since > chrono::Utc::now().date_naive() {
+        return Err(Json(ApiError::new("Backfill date is in the future".to_string())));
+    }
+
+    let transactions_evaluated = state
+        .rule_engine
+        .backfill(&rule, req.since)
+        .await
+        .map_err(|e| Json(ApiError::new(e.to_string())))?;
+
+    Ok(Json(BackfillResponse { transactions_evaluated }))
+}
+
+/// Show what a saved rule would have done with past transactions.
+pub async fn backtest_rule(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(req): Json<BacktestRequest>,
+) -> Result<Json<BacktestReport>, Json<ApiError>> {
+    let rule = state
+        .db
+        .get_rule(&id.to_string())
+        .await
+        .map_err(|e| Json(ApiError::new(e.to_string())))?
+        .ok_or_else(|| Json(ApiError::new("Rule not found".to_string())))?;
+
+    run_backtest(&state, &rule, req).await
+}
+
+/// Show what an unsaved rule would have done with past transactions.
+pub async fn backtest_draft_rule(
+    State(state): State<AppState>,
+    Json(req): Json<DraftBacktestRequest>,
+) -> Result<Json<BacktestReport>, Json<ApiError>> {
+    let rule = req.rule.into_rule(chrono::Utc::now().timestamp());
+    run_backtest(&state, &rule, req.window).await
+}
+
+async fn run_backtest(
+    state: &AppState,
+    rule: &Rule,
+    req: BacktestRequest,
+) -> Result<Json<BacktestReport>, Json<ApiError>> {
+    if rule.trigger.is_scheduled() {
+        return Err(Json(ApiError::new("Scheduled rules cannot be backtested")));
+    }
+
+    state
+        .rule_engine
+        .backtest(rule, req.from, req.to, req.source)
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError::new(e.to_string())))
+}
+
+/// Get a rule's processing log, showing why it did or did not fire.
+pub async fn get_rule_log(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<RuleLogQuery>,
+) -> Result<Json<Vec<RuleTransactionLog>>, Json<ApiError>> {
+    state
+        .db
+        .get_processing_log(&id.to_string(), query.limit.unwrap_or(100))
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError::new(e.to_string())))
+}
+
+/// Get a rule's processing log for one transaction, with how each condition
+/// evaluated.
+pub async fn get_transaction_log(
+    State(state): State<AppState>,
+    Path((id, transaction_id)): Path<(Uuid, String)>,
+) -> Result<Json<Vec<RuleTransactionLog>>, Json<ApiError>> {
+    state
+        .db
+        .get_transaction_processing_log(&id.to_string(), &transaction_id)
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError::new(e.to_string())))
+}
+
+/// Get the amounts a rule has collected that are waiting to be transferred.
+pub async fn get_accumulated(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<Vec<AccumulatorEntry>>, Json<ApiError>> {
+    state
+        .db
+        .get_pending_accumulator_entries(&id.to_string())
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError::new(e.to_string())))
+}
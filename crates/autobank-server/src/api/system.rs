@@ -1,6 +1,7 @@
 //! System API endpoints for scheduler control and status.
 
 use crate::AppState;
+use crate::rules::TransferLimits;
 use axum::{
     Json, Router,
     extract::State,
@@ -15,6 +16,8 @@ pub fn router() -> Router<AppState> {
         .route("/poll", post(trigger_poll))
         .route("/scheduler/enable", post(enable_scheduler))
         .route("/scheduler/disable", post(disable_scheduler))
+        .route("/emergency-stop", post(engage_emergency_stop).delete(release_emergency_stop))
+        .route("/limits", get(get_limits).put(update_limits))
 }
 
 #[derive(Serialize)]
@@ -25,6 +28,7 @@ pub struct ApiError {
 #[derive(Serialize)]
 pub struct SystemStatus {
     pub scheduler_enabled: bool,
+    pub emergency_stop: bool,
     pub rules_count: i64,
     pub executions_count: i64,
 }
@@ -40,9 +44,15 @@ pub async fn get_status(
 ) -> Result<Json<SystemStatus>, Json<ApiError>> {
     let rules = state.db.list_rules().await.map_err(|e| Json(ApiError { error: e.to_string() }))?;
     let executions = state.db.list_executions(1000).await.map_err(|e| Json(ApiError { error: e.to_string() }))?;
-    
+    let emergency_stop = state
+        .rule_engine
+        .emergency_stop_engaged()
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+
     Ok(Json(SystemStatus {
         scheduler_enabled: state.scheduler.is_enabled().await,
+        emergency_stop,
         rules_count: rules.len() as i64,
         executions_count: executions.len() as i64,
     }))
@@ -77,3 +87,56 @@ pub async fn disable_scheduler(
         message: "Scheduler disabled".to_string(),
     })
 }
+
+/// Engage the emergency stop, blocking all transfers until released.
+pub async fn engage_emergency_stop(
+    State(state): State<AppState>,
+) -> Result<Json<PollResponse>, Json<ApiError>> {
+    state
+        .rule_engine
+        .set_emergency_stop(true)
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+    Ok(Json(PollResponse {
+        message: "Emergency stop engaged".to_string(),
+    }))
+}
+
+/// Release the emergency stop.
+pub async fn release_emergency_stop(
+    State(state): State<AppState>,
+) -> Result<Json<PollResponse>, Json<ApiError>> {
+    state
+        .rule_engine
+        .set_emergency_stop(false)
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+    Ok(Json(PollResponse {
+        message: "Emergency stop released".to_string(),
+    }))
+}
+
+/// Get the transfer safety limits.
+pub async fn get_limits(
+    State(state): State<AppState>,
+) -> Result<Json<TransferLimits>, Json<ApiError>> {
+    state
+        .rule_engine
+        .transfer_limits()
+        .await
+        .map(Json)
+        .map_err(|e| Json(ApiError { error: e.to_string() }))
+}
+
+/// Replace the transfer safety limits.
+pub async fn update_limits(
+    State(state): State<AppState>,
+    Json(limits): Json<TransferLimits>,
+) -> Result<Json<TransferLimits>, Json<ApiError>> {
+    state
+        .rule_engine
+        .set_transfer_limits(&limits)
+        .await
+        .map_err(|e| Json(ApiError { error: e.to_string() }))?;
+    Ok(Json(limits))
+}
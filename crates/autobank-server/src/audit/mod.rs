@@ -27,6 +27,8 @@ pub enum AuditEventType {
     TransferInitiated,
     TransferSucceeded,
     TransferFailed,
+    TransferApproved,
+    TransferRejected,
 
     // Scheduler
     SchedulerStarted,
@@ -1,6 +1,9 @@
 //! Database migrations.
 
 /// All database migrations in order.
+///
+/// Applied migrations are tracked in `PRAGMA user_version`, so entries must
+/// never be reordered or edited once released; append new ones instead.
 pub const MIGRATIONS: &[&str] = &[
     // Migration 001: Initial schema
     r#"
@@ -73,5 +76,179 @@ CREATE INDEX IF NOT EXISTS idx_rule_transaction_log_rule ON rule_transaction_log
 CREATE INDEX IF NOT EXISTS idx_rule_executions_rule ON rule_executions(rule_id);
 CREATE INDEX IF NOT EXISTS idx_audit_log_timestamp ON audit_log(timestamp);
 CREATE INDEX IF NOT EXISTS idx_audit_log_event_type ON audit_log(event_type);
+"#,
+    // Migration 002: Store execution amounts as integer øre with currency
+    r#"
+CREATE TABLE rule_executions_new (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id),
+    transaction_id TEXT NOT NULL,
+    transfer_payment_id TEXT,
+    amount_ore INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'NOK',
+    from_account TEXT NOT NULL,
+    to_account TEXT NOT NULL,
+    status TEXT NOT NULL,
+    error_message TEXT,
+    executed_at INTEGER NOT NULL
+);
+
+INSERT INTO rule_executions_new (id, rule_id, transaction_id, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at)
+SELECT id, rule_id, transaction_id, transfer_payment_id, CAST(ROUND(amount * 100) AS INTEGER), 'NOK', from_account, to_account, status, error_message, executed_at
+FROM rule_executions;
+
+DROP TABLE rule_executions;
+ALTER TABLE rule_executions_new RENAME TO rule_executions;
+
+CREATE INDEX IF NOT EXISTS idx_rule_executions_rule ON rule_executions(rule_id);
+"#,
+    // Migration 003: Idempotency keys for two-phase transfer execution
+    r#"
+ALTER TABLE rule_executions ADD COLUMN idempotency_key TEXT;
+UPDATE rule_executions SET idempotency_key = id WHERE idempotency_key IS NULL;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_rule_executions_idempotency ON rule_executions(idempotency_key);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_status ON rule_executions(status);
+"#,
+    // Migration 004: Transaction lifecycle tracking and per-rule firing stage
+    r#"
+ALTER TABLE rules ADD COLUMN fire_on TEXT NOT NULL DEFAULT 'first_seen';
+
+ALTER TABLE tracked_transactions ADD COLUMN pending_seen_at INTEGER;
+ALTER TABLE tracked_transactions ADD COLUMN booked_at INTEGER;
+UPDATE tracked_transactions SET pending_seen_at = first_seen_at WHERE settled = 0;
+UPDATE tracked_transactions SET booked_at = last_updated_at WHERE settled = 1;
+
+CREATE INDEX IF NOT EXISTS idx_rule_transaction_log_rule_tx ON rule_transaction_log(rule_id, transaction_id);
+"#,
+    // Migration 005: Rule activation timestamp
+    r#"
+ALTER TABLE rules ADD COLUMN activated_at INTEGER NOT NULL DEFAULT 0;
+UPDATE rules SET activated_at = created_at;
+"#,
+    // Migration 006: Match bank transactions back to autobank's own transfers
+    r#"
+ALTER TABLE rules ADD COLUMN include_self_transfers INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE rule_executions ADD COLUMN debit_transaction_id TEXT;
+ALTER TABLE rule_executions ADD COLUMN credit_transaction_id TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_rule_executions_debit_tx ON rule_executions(debit_transaction_id);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_credit_tx ON rule_executions(credit_transaction_id);
+"#,
+    // Migration 007: Server-wide settings
+    r#"
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+"#,
+    // Migration 008: Per-rule limits
+    r#"
+ALTER TABLE rules ADD COLUMN limits TEXT NOT NULL DEFAULT '{}';
+
+CREATE INDEX IF NOT EXISTS idx_rule_executions_rule_time ON rule_executions(rule_id, executed_at);
+"#,
+    // Migration 009: Scheduled rules; executions without a transaction
+    r#"
+ALTER TABLE rules ADD COLUMN rule_trigger TEXT NOT NULL DEFAULT '{"type":"transaction"}';
+ALTER TABLE rules ADD COLUMN next_run_at INTEGER;
+
+CREATE TABLE rule_executions_new (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id),
+    transaction_id TEXT,
+    scheduled_for INTEGER,
+    idempotency_key TEXT NOT NULL,
+    transfer_payment_id TEXT,
+    amount_ore INTEGER NOT NULL,
+    currency TEXT NOT NULL DEFAULT 'NOK',
+    from_account TEXT NOT NULL,
+    to_account TEXT NOT NULL,
+    status TEXT NOT NULL,
+    error_message TEXT,
+    executed_at INTEGER NOT NULL,
+    debit_transaction_id TEXT,
+    credit_transaction_id TEXT
+);
+
+INSERT INTO rule_executions_new (id, rule_id, transaction_id, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id)
+SELECT id, rule_id, transaction_id, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id
+FROM rule_executions;
+
+DROP TABLE rule_executions;
+ALTER TABLE rule_executions_new RENAME TO rule_executions;
+
+CREATE INDEX IF NOT EXISTS idx_rule_executions_rule ON rule_executions(rule_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_rule_executions_idempotency ON rule_executions(idempotency_key);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_status ON rule_executions(status);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_debit_tx ON rule_executions(debit_transaction_id);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_credit_tx ON rule_executions(credit_transaction_id);
+CREATE INDEX IF NOT EXISTS idx_rule_executions_rule_time ON rule_executions(rule_id, executed_at);
+"#,
+    // Migration 010: Ledger for accumulating transfer actions
+    r#"
+CREATE TABLE IF NOT EXISTS accumulator_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    rule_id TEXT NOT NULL REFERENCES rules(id),
+    action_index INTEGER NOT NULL,
+    source_id TEXT NOT NULL,
+    amount_ore INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    flush_execution_id TEXT,
+    UNIQUE(rule_id, action_index, source_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_accumulator_entries_pending ON accumulator_entries(rule_id, action_index, flush_execution_id);
+"#,
+    // Migration 011: Look up stored transactions by date for aggregate conditions
+    r#"
+CREATE INDEX IF NOT EXISTS idx_tracked_transactions_account_date ON tracked_transactions(account_key, json_extract(raw_data, '$.date'));
+"#,
+    // Migration 012: Record how each condition evaluated
+    r#"
+ALTER TABLE rule_transaction_log ADD COLUMN condition_trace TEXT;
+"#,
+    // Migration 013: Rule evaluation order, stop-processing and first-match groups
+    r#"
+ALTER TABLE rules ADD COLUMN priority INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE rules ADD COLUMN stop_processing INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE rules ADD COLUMN rule_group TEXT;
+"#,
+    // Migration 014: Transfers waiting for manual approval
+    r#"
+ALTER TABLE rules ADD COLUMN requires_approval INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS transfer_proposals (
+    id TEXT PRIMARY KEY,
+    rule_id TEXT NOT NULL REFERENCES rules(id),
+    transaction_id TEXT,
+    scheduled_for INTEGER,
+    action_index INTEGER NOT NULL,
+    idempotency_key TEXT NOT NULL UNIQUE,
+    amount_ore INTEGER NOT NULL,
+    currency TEXT NOT NULL,
+    from_account TEXT NOT NULL,
+    to_account TEXT NOT NULL,
+    message TEXT,
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    decided_at INTEGER,
+    decided_by TEXT,
+    execution_status TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfer_proposals_status ON transfer_proposals(status, expires_at);
+"#,
+    // Migration 015: Retry queue for transfers that failed transiently
+    r#"
+ALTER TABLE rule_executions ADD COLUMN message TEXT;
+ALTER TABLE rule_executions ADD COLUMN attempts INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE rule_executions ADD COLUMN next_retry_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_rule_executions_retry ON rule_executions(status, next_retry_at);
 "#,
 ];
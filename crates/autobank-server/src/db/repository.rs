@@ -1,7 +1,13 @@
 //! Database repository implementation.
 
 use crate::audit::AuditEntry;
-use crate::rules::{Rule, RuleExecution, RuleTransactionLog, TrackedTransaction};
+use crate::rules::{
+    AccumulatorEntry, PendingAccumulation, Rule, RuleExecution, RuleHistory, RuleTransactionLog, TrackedTransaction,
+    TransferProposal,
+};
+use sb1_api::models::Transaction;
+use sb1_api::{Currency, Money};
+use serde::{Serialize, de::DeserializeOwned};
 use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
 use std::str::FromStr;
 use thiserror::Error;
@@ -36,10 +42,22 @@ impl Database {
     }
 
     /// Run all migrations.
+    ///
+    /// The number of applied migrations is kept in `PRAGMA user_version`; each
+    /// pending migration runs in its own transaction together with the version bump.
     pub async fn run_migrations(&self) -> Result<(), DbError> {
-        for (i, migration) in super::MIGRATIONS.iter().enumerate() {
+        let (applied,): (i64,) = sqlx::query_as("PRAGMA user_version")
+            .fetch_one(&self.pool)
+            .await?;
+
+        for (i, migration) in super::MIGRATIONS.iter().enumerate().skip(applied as usize) {
             info!("Running migration {}", i + 1);
-            sqlx::raw_sql(migration).execute(&self.pool).await?;
+            let mut tx = self.pool.begin().await?;
+            sqlx::raw_sql(migration).execute(&mut *tx).await?;
+            sqlx::raw_sql(&format!("PRAGMA user_version = {}", i + 1))
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
         }
         Ok(())
     }
@@ -49,7 +67,7 @@ impl Database {
     /// List all rules.
     pub async fn list_rules(&self) -> Result<Vec<Rule>, DbError> {
         let rows = sqlx::query_as::<_, RuleRow>(
-            "SELECT id, name, description, enabled, trigger_account_key, conditions, actions, created_at, updated_at FROM rules ORDER BY created_at DESC"
+            "SELECT id, name, description, enabled, rule_trigger, trigger_account_key, fire_on, include_self_transfers, limits, priority, stop_processing, rule_group, requires_approval, conditions, actions, created_at, updated_at, activated_at, next_run_at FROM rules ORDER BY created_at DESC"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -60,7 +78,7 @@ impl Database {
     /// Get a rule by ID.
     pub async fn get_rule(&self, id: &str) -> Result<Option<Rule>, DbError> {
         let row = sqlx::query_as::<_, RuleRow>(
-            "SELECT id, name, description, enabled, trigger_account_key, conditions, actions, created_at, updated_at FROM rules WHERE id = ?"
+            "SELECT id, name, description, enabled, rule_trigger, trigger_account_key, fire_on, include_self_transfers, limits, priority, stop_processing, rule_group, requires_approval, conditions, actions, created_at, updated_at, activated_at, next_run_at FROM rules WHERE id = ?"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -69,10 +87,31 @@ impl Database {
         row.map(|r| r.try_into()).transpose()
     }
 
-    /// Get all enabled rules grouped by trigger account.
+    /// Get enabled rules that run on a schedule.
+    pub async fn get_enabled_scheduled_rules(&self) -> Result<Vec<Rule>, DbError> {
+        let rules = sqlx::query_as::<_, RuleRow>(
+            "SELECT id, name, description, enabled, rule_trigger, trigger_account_key, fire_on, include_self_transfers, limits, priority, stop_processing, rule_group, requires_approval, conditions, actions, created_at, updated_at, activated_at, next_run_at FROM rules WHERE enabled = 1 AND json_extract(rule_trigger, '$.type') = 'schedule'"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rules.into_iter().map(|r| r.try_into()).collect()
+    }
+
+    /// Set when a scheduled rule next runs.
+    pub async fn set_next_run_at(&self, id: &str, next_run_at: Option<i64>) -> Result<(), DbError> {
+        sqlx::query("UPDATE rules SET next_run_at = ? WHERE id = ?")
+            .bind(next_run_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get all enabled transaction-triggered rules grouped by trigger account.
     pub async fn get_enabled_rules_by_account(&self) -> Result<std::collections::HashMap<String, Vec<Rule>>, DbError> {
         let rules = sqlx::query_as::<_, RuleRow>(
-            "SELECT id, name, description, enabled, trigger_account_key, conditions, actions, created_at, updated_at FROM rules WHERE enabled = 1"
+            "SELECT id, name, description, enabled, rule_trigger, trigger_account_key, fire_on, include_self_transfers, limits, priority, stop_processing, rule_group, requires_approval, conditions, actions, created_at, updated_at, activated_at, next_run_at FROM rules WHERE enabled = 1 ORDER BY priority DESC, created_at, id"
         )
         .fetch_all(&self.pool)
         .await?;
@@ -80,6 +119,9 @@ impl Database {
         let mut map: std::collections::HashMap<String, Vec<Rule>> = std::collections::HashMap::new();
         for row in rules {
             let rule: Rule = row.try_into()?;
+            if rule.trigger.is_scheduled() {
+                continue;
+            }
             map.entry(rule.trigger_account_key.clone())
                 .or_default()
                 .push(rule);
@@ -91,19 +133,31 @@ impl Database {
     pub async fn create_rule(&self, rule: &Rule) -> Result<(), DbError> {
         let conditions = serde_json::to_string(&rule.conditions)?;
         let actions = serde_json::to_string(&rule.actions)?;
+        let limits = serde_json::to_string(&rule.limits)?;
+        let trigger = serde_json::to_string(&rule.trigger)?;
 
         sqlx::query(
-            "INSERT INTO rules (id, name, description, enabled, trigger_account_key, conditions, actions, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            "INSERT INTO rules (id, name, description, enabled, rule_trigger, trigger_account_key, fire_on, include_self_transfers, limits, priority, stop_processing, rule_group, requires_approval, conditions, actions, created_at, updated_at, activated_at, next_run_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
         )
         .bind(&rule.id)
         .bind(&rule.name)
         .bind(&rule.description)
         .bind(rule.enabled)
+        .bind(&trigger)
         .bind(&rule.trigger_account_key)
+        .bind(rule.fire_on.as_str())
+        .bind(rule.include_self_transfers)
+        .bind(&limits)
+        .bind(rule.priority)
+        .bind(rule.stop_processing)
+        .bind(&rule.group)
+        .bind(rule.requires_approval)
         .bind(&conditions)
         .bind(&actions)
         .bind(rule.created_at)
         .bind(rule.updated_at)
+        .bind(rule.activated_at)
+        .bind(rule.next_run_at)
         .execute(&self.pool)
         .await?;
 
@@ -114,17 +168,28 @@ impl Database {
     pub async fn update_rule(&self, rule: &Rule) -> Result<(), DbError> {
         let conditions = serde_json::to_string(&rule.conditions)?;
         let actions = serde_json::to_string(&rule.actions)?;
+        let limits = serde_json::to_string(&rule.limits)?;
+        let trigger = serde_json::to_string(&rule.trigger)?;
 
         sqlx::query(
-            "UPDATE rules SET name = ?, description = ?, enabled = ?, trigger_account_key = ?, conditions = ?, actions = ?, updated_at = ? WHERE id = ?"
+            "UPDATE rules SET name = ?, description = ?, enabled = ?, rule_trigger = ?, trigger_account_key = ?, fire_on = ?, include_self_transfers = ?, limits = ?, priority = ?, stop_processing = ?, rule_group = ?, requires_approval = ?, conditions = ?, actions = ?, updated_at = ?, next_run_at = ? WHERE id = ?"
         )
         .bind(&rule.name)
         .bind(&rule.description)
         .bind(rule.enabled)
+        .bind(&trigger)
         .bind(&rule.trigger_account_key)
+        .bind(rule.fire_on.as_str())
+        .bind(rule.include_self_transfers)
+        .bind(&limits)
+        .bind(rule.priority)
+        .bind(rule.stop_processing)
+        .bind(&rule.group)
+        .bind(rule.requires_approval)
         .bind(&conditions)
         .bind(&actions)
         .bind(rule.updated_at)
+        .bind(rule.next_run_at)
         .bind(&rule.id)
         .execute(&self.pool)
         .await?;
@@ -132,23 +197,64 @@ impl Database {
         Ok(())
     }
 
-    /// Delete a rule.
-    pub async fn delete_rule(&self, id: &str) -> Result<(), DbError> {
-        sqlx::query("DELETE FROM rules WHERE id = ?")
+    /// Delete a rule, or disable it if it has run.
+    ///
+    /// Executions and the processing log keep referring to a rule that has
+    /// run, so such a rule is disabled instead of removed. Either way its
+    /// transfers waiting to be retried are dead-lettered, with the amounts of
+    /// flushes among them returned to the ledger, in the same transaction.
+    /// Returns true if the rule was removed.
+    pub async fn delete_rule(&self, id: &str) -> Result<bool, DbError> {
+        let now = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "UPDATE accumulator_entries SET flush_execution_id = NULL WHERE flush_execution_id IN (SELECT id FROM rule_executions WHERE rule_id = ? AND status = ?)"
+        )
+        .bind(id)
+        .bind(RuleExecution::RETRYING)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("UPDATE rule_executions SET status = ?, error_message = ?, next_retry_at = NULL WHERE rule_id = ? AND status = ?")
+            .bind(RuleExecution::DEAD_LETTER)
+            .bind("Rule was deleted before the transfer was retried")
             .bind(id)
-            .execute(&self.pool)
+            .bind(RuleExecution::RETRYING)
+            .execute(&mut *tx)
             .await?;
-        Ok(())
+
+        let removed = match sqlx::query("DELETE FROM rules WHERE id = ?").bind(id).execute(&mut *tx).await {
+            Ok(_) => true,
+            Err(sqlx::Error::Database(e)) if e.is_foreign_key_violation() => {
+                sqlx::query("UPDATE rules SET enabled = 0, updated_at = ? WHERE id = ?")
+                    .bind(now)
+                    .bind(id)
+                    .execute(&mut *tx)
+                    .await?;
+                false
+            }
+            Err(e) => return Err(e.into()),
+        };
+        tx.commit().await?;
+
+        Ok(removed)
     }
 
     /// Set rule enabled status.
+    ///
+    /// Enabling a disabled rule resets its activation timestamp, and its next
+    /// scheduled run so runs missed while disabled are not made up.
     pub async fn set_rule_enabled(&self, id: &str, enabled: bool) -> Result<(), DbError> {
-        sqlx::query("UPDATE rules SET enabled = ?, updated_at = ? WHERE id = ?")
-            .bind(enabled)
-            .bind(chrono::Utc::now().timestamp())
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query(
+            "UPDATE rules SET activated_at = CASE WHEN ?1 AND enabled = 0 THEN ?2 ELSE activated_at END, next_run_at = CASE WHEN ?1 AND enabled = 0 THEN NULL ELSE next_run_at END, enabled = ?1, updated_at = ?2 WHERE id = ?3"
+        )
+        .bind(enabled)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
         Ok(())
     }
 
@@ -157,7 +263,7 @@ impl Database {
     /// Get a tracked transaction by ID.
     pub async fn get_tracked_transaction(&self, id: &str) -> Result<Option<TrackedTransaction>, DbError> {
         let row = sqlx::query_as::<_, TrackedTransactionRow>(
-            "SELECT id, account_key, fingerprint, first_seen_at, last_updated_at, settled, raw_data FROM tracked_transactions WHERE id = ?"
+            "SELECT id, account_key, fingerprint, first_seen_at, last_updated_at, settled, pending_seen_at, booked_at, raw_data FROM tracked_transactions WHERE id = ?"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -167,11 +273,17 @@ impl Database {
     }
 
     /// Upsert a tracked transaction.
+    ///
+    /// Lifecycle timestamps that are already set are kept, so they record
+    /// when each stage was first seen.
     pub async fn upsert_tracked_transaction(&self, tx: &TrackedTransaction) -> Result<(), DbError> {
         sqlx::query(
-            "INSERT INTO tracked_transactions (id, account_key, fingerprint, first_seen_at, last_updated_at, settled, raw_data) 
-             VALUES (?, ?, ?, ?, ?, ?, ?)
-             ON CONFLICT(id) DO UPDATE SET fingerprint = excluded.fingerprint, last_updated_at = excluded.last_updated_at, settled = excluded.settled, raw_data = excluded.raw_data"
+            "INSERT INTO tracked_transactions (id, account_key, fingerprint, first_seen_at, last_updated_at, settled, pending_seen_at, booked_at, raw_data) 
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET fingerprint = excluded.fingerprint, last_updated_at = excluded.last_updated_at, settled = excluded.settled,
+                 pending_seen_at = COALESCE(tracked_transactions.pending_seen_at, excluded.pending_seen_at),
+                 booked_at = COALESCE(tracked_transactions.booked_at, excluded.booked_at),
+                 raw_data = excluded.raw_data"
         )
         .bind(&tx.id)
         .bind(&tx.account_key)
@@ -179,6 +291,8 @@ impl Database {
         .bind(tx.first_seen_at)
         .bind(tx.last_updated_at)
         .bind(tx.settled)
+        .bind(tx.pending_seen_at)
+        .bind(tx.booked_at)
         .bind(&tx.raw_data)
         .execute(&self.pool)
         .await?;
@@ -186,6 +300,28 @@ impl Database {
         Ok(())
     }
 
+    /// Stored transactions on an account dated in `start..=end` (Unix millis),
+    /// oldest first.
+    pub async fn get_tracked_transactions_between(
+        &self,
+        account_key: &str,
+        start: i64,
+        end: i64,
+    ) -> Result<Vec<Transaction>, DbError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "SELECT raw_data FROM tracked_transactions WHERE account_key = ? AND json_extract(raw_data, '$.date') BETWEEN ? AND ? ORDER BY json_extract(raw_data, '$.date')"
+        )
+        .bind(account_key)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(raw,)| serde_json::from_str(&raw).map_err(DbError::from))
+            .collect()
+    }
+
     // --- Rule Transaction Log ---
 
     /// Check if a rule+transaction+fingerprint has been processed.
@@ -202,10 +338,42 @@ impl Database {
         Ok(count.0 > 0)
     }
 
+    /// Check if a rule's conditions matched a transaction, in any version,
+    /// whether or not its limits let it fire.
+    pub async fn has_matched(&self, rule_id: &str, tx_id: &str) -> Result<bool, DbError> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM rule_transaction_log WHERE rule_id = ? AND transaction_id = ? AND (action_taken LIKE 'executed:%' OR action_taken LIKE 'limited:%')"
+        )
+        .bind(rule_id)
+        .bind(tx_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0 > 0)
+    }
+
+    /// Check if a rule has fired for a transaction, in any version.
+    ///
+    /// Any `executed:` outcome counts, including transfers that were `blocked`
+    /// by a safety limit or the emergency stop: a block stops the rule for that
+    /// transaction rather than deferring the transfer to a later poll. Blocked
+    /// transfers are sent on request with `RuleEngine::resend_blocked`.
+    pub async fn has_fired(&self, rule_id: &str, tx_id: &str) -> Result<bool, DbError> {
+        let count: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM rule_transaction_log WHERE rule_id = ? AND transaction_id = ? AND action_taken LIKE 'executed:%'"
+        )
+        .bind(rule_id)
+        .bind(tx_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count.0 > 0)
+    }
+
     /// Record a rule processing event.
     pub async fn record_processing(&self, log: &RuleTransactionLog) -> Result<(), DbError> {
         sqlx::query(
-            "INSERT INTO rule_transaction_log (id, rule_id, transaction_id, transaction_fingerprint, action_taken, processed_at) VALUES (?, ?, ?, ?, ?, ?)"
+            "INSERT INTO rule_transaction_log (id, rule_id, transaction_id, transaction_fingerprint, action_taken, processed_at, condition_trace) VALUES (?, ?, ?, ?, ?, ?, ?)"
         )
         .bind(&log.id)
         .bind(&log.rule_id)
@@ -213,39 +381,288 @@ impl Database {
         .bind(&log.transaction_fingerprint)
         .bind(&log.action_taken)
         .bind(log.processed_at)
+        .bind(log.condition_trace.as_ref().map(serde_json::to_string).transpose()?)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Get the most recent processing log entries for a rule.
+    pub async fn get_processing_log(&self, rule_id: &str, limit: i64) -> Result<Vec<RuleTransactionLog>, DbError> {
+        let rows = sqlx::query_as::<_, RuleTransactionLogRow>(
+            "SELECT id, rule_id, transaction_id, transaction_fingerprint, action_taken, processed_at, condition_trace FROM rule_transaction_log WHERE rule_id = ? ORDER BY processed_at DESC LIMIT ?"
+        )
+        .bind(rule_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(|r| r.try_into()).collect()
+    }
+
+    /// Get a rule's processing log entries for one transaction, newest first.
+    pub async fn get_transaction_processing_log(
+        &self,
+        rule_id: &str,
+        transaction_id: &str,
+    ) -> Result<Vec<RuleTransactionLog>, DbError> {
+        let rows = sqlx::query_as::<_, RuleTransactionLogRow>(
+            "SELECT id, rule_id, transaction_id, transaction_fingerprint, action_taken, processed_at, condition_trace FROM rule_transaction_log WHERE rule_id = ? AND transaction_id = ? ORDER BY processed_at DESC"
+        )
+        .bind(rule_id)
+        .bind(transaction_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter().map(|r| r.try_into()).collect()
+    }
+
     // --- Rule Executions ---
 
     /// Record a rule execution.
-    pub async fn record_execution(&self, exec: &RuleExecution) -> Result<(), DbError> {
+    ///
+    /// Returns false without writing anything if an execution with the same
+    /// idempotency key already exists.
+    pub async fn record_execution(&self, exec: &RuleExecution) -> Result<bool, DbError> {
+        insert_execution(&self.pool, exec).await
+    }
+
+    /// Finalize a pending execution with its outcome.
+    pub async fn complete_execution(
+        &self,
+        id: &str,
+        status: &str,
+        transfer_payment_id: Option<&str>,
+        error_message: Option<&str>,
+    ) -> Result<(), DbError> {
         sqlx::query(
-            "INSERT INTO rule_executions (id, rule_id, transaction_id, transfer_payment_id, amount, from_account, to_account, status, error_message, executed_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
-        )
-        .bind(&exec.id)
-        .bind(&exec.rule_id)
-        .bind(&exec.transaction_id)
-        .bind(&exec.transfer_payment_id)
-        .bind(exec.amount)
-        .bind(&exec.from_account)
-        .bind(&exec.to_account)
-        .bind(&exec.status)
-        .bind(&exec.error_message)
-        .bind(exec.executed_at)
+            "UPDATE rule_executions SET status = ?, transfer_payment_id = ?, error_message = ? WHERE id = ? AND status = ?"
+        )
+        .bind(status)
+        .bind(transfer_payment_id)
+        .bind(error_message)
+        .bind(id)
+        .bind(RuleExecution::PENDING)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Queue a pending execution whose transfer failed transiently to be sent
+    /// again at `next_retry_at`.
+    pub async fn schedule_retry(&self, id: &str, error_message: &str, next_retry_at: i64) -> Result<(), DbError> {
+        sqlx::query(
+            "UPDATE rule_executions SET status = ?, error_message = ?, next_retry_at = ? WHERE id = ? AND status = ?"
+        )
+        .bind(RuleExecution::RETRYING)
+        .bind(error_message)
+        .bind(next_retry_at)
+        .bind(id)
+        .bind(RuleExecution::PENDING)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get executions waiting to be retried whose next attempt is due at `now`.
+    /// Only executions of enabled rules are returned.
+    pub async fn list_due_retries(&self, now: i64) -> Result<Vec<RuleExecution>, DbError> {
+        let rows = sqlx::query_as::<_, RuleExecutionRow>(
+            "SELECT id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id, message, attempts, next_retry_at FROM rule_executions WHERE status = ? AND next_retry_at <= ? AND rule_id IN (SELECT id FROM rules WHERE enabled = 1) ORDER BY next_retry_at"
+        )
+        .bind(RuleExecution::RETRYING)
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into()).collect())
+    }
+
+    /// Dead-letter executions waiting to be retried whose rule has been
+    /// disabled. Returns their IDs. Deleting a rule dead-letters its retries
+    /// itself (see [`Database::delete_rule`]).
+    pub async fn dead_letter_inactive_retries(&self, error_message: &str) -> Result<Vec<String>, DbError> {
+        let rows: Vec<(String,)> = sqlx::query_as(
+            "UPDATE rule_executions SET status = ?, error_message = ?, next_retry_at = NULL WHERE status = ? AND rule_id NOT IN (SELECT id FROM rules WHERE enabled = 1) RETURNING id"
+        )
+        .bind(RuleExecution::DEAD_LETTER)
+        .bind(error_message)
+        .bind(RuleExecution::RETRYING)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Claim a retrying execution for its next attempt, moving it back to
+    /// pending. Returns false if it is no longer waiting to be retried.
+    pub async fn begin_retry(&self, id: &str) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            "UPDATE rule_executions SET status = ?, attempts = attempts + 1, next_retry_at = NULL WHERE id = ? AND status = ?"
+        )
+        .bind(RuleExecution::PENDING)
+        .bind(id)
+        .bind(RuleExecution::RETRYING)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Claim a blocked execution to be sent after all, moving it back to
+    /// pending as of `now`. Returns false if it is no longer blocked.
+    pub async fn begin_resend(&self, id: &str, now: i64) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            "UPDATE rule_executions SET status = ?, error_message = NULL, executed_at = ? WHERE id = ? AND status = ?"
+        )
+        .bind(RuleExecution::PENDING)
+        .bind(now)
+        .bind(id)
+        .bind(RuleExecution::BLOCKED)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get executions that were never finalized.
+    pub async fn list_pending_executions(&self) -> Result<Vec<RuleExecution>, DbError> {
+        let rows = sqlx::query_as::<_, RuleExecutionRow>(
+            "SELECT id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id, message, attempts, next_retry_at FROM rule_executions WHERE status = ? ORDER BY executed_at"
+        )
+        .bind(RuleExecution::PENDING)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into()).collect())
+    }
+
+    /// Find the execution whose transfer produced a bank transaction.
+    ///
+    /// A transaction already linked to an execution is returned directly.
+    /// Otherwise the oldest unlinked execution that moved the same amount to or
+    /// from `account_number` within `window_secs` before the transaction date
+    /// (or one day after it) is linked to the transaction and returned. With a
+    /// `marker`, only the execution whose ID starts with it is considered. Only
+    /// executions that reached the bank are considered.
+    pub async fn match_execution_transaction(
+        &self,
+        account_number: &str,
+        tx_id: &str,
+        amount: Money,
+        tx_date: i64,
+        window_secs: i64,
+        marker: Option<&str>,
+    ) -> Result<Option<String>, DbError> {
+        let linked: Option<(String,)> = sqlx::query_as(
+            "SELECT id FROM rule_executions WHERE debit_transaction_id = ?1 OR credit_transaction_id = ?1 LIMIT 1"
+        )
+        .bind(tx_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some((id,)) = linked {
+            return Ok(Some(id));
+        }
+
+        // Money leaving the account matches the debit side of a transfer from it,
+        // money arriving matches the credit side of a transfer to it.
+        let (account_column, link_column) = if amount.is_negative() {
+            ("from_account", "debit_transaction_id")
+        } else {
+            ("to_account", "credit_transaction_id")
+        };
+
+        let mut tx = self.pool.begin().await?;
+        let candidate: Option<(String,)> = sqlx::query_as(&format!(
+            "SELECT id FROM rule_executions WHERE {account_column} = ? AND {link_column} IS NULL AND amount_ore = ? AND currency = ? AND status IN (?, ?) AND executed_at BETWEEN ? AND ? AND (? IS NULL OR id LIKE ? || '%') ORDER BY executed_at LIMIT 1"
+        ))
+        .bind(account_number)
+        .bind(amount.abs().ore())
+        .bind(amount.currency().as_str())
+        .bind(RuleExecution::SUCCESS)
+        .bind(RuleExecution::UNKNOWN)
+        .bind(tx_date - window_secs)
+        .bind(tx_date + 86400)
+        .bind(marker)
+        .bind(marker)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some((id,)) = candidate else {
+            return Ok(None);
+        };
+
+        sqlx::query(&format!("UPDATE rule_executions SET {link_column} = ? WHERE id = ?"))
+            .bind(tx_id)
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(id))
+    }
+
+    /// Total amount of transfers sent (or possibly sent) since `since`, in the given currency.
+    ///
+    /// Transfers waiting to be retried are included, as they may still be sent.
+    pub async fn sum_transferred_since(&self, currency: Currency, since: i64) -> Result<Money, DbError> {
+        let (total,): (i64,) = sqlx::query_as(
+            "SELECT COALESCE(SUM(ABS(amount_ore)), 0) FROM rule_executions WHERE currency = ? AND status IN (?, ?, ?, ?) AND executed_at >= ?"
+        )
+        .bind(currency.as_str())
+        .bind(RuleExecution::PENDING)
+        .bind(RuleExecution::SUCCESS)
+        .bind(RuleExecution::UNKNOWN)
+        .bind(RuleExecution::RETRYING)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Money::new(total, currency))
+    }
+
+    /// Summarize a rule's transfers for checking its limits.
+    ///
+    /// Counts the same statuses as [`Self::sum_transferred_since`]; blocked,
+    /// failed and dead-lettered executions moved no money.
+    pub async fn rule_history(&self, rule_id: &str, currency: Currency, day_start: i64, month_start: i64) -> Result<RuleHistory, DbError> {
+        let (transfers_today, amount_today, amount_this_month, last_transfer_at): (i64, i64, i64, Option<i64>) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN executed_at >= ?1 THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN executed_at >= ?1 AND currency = ?3 THEN ABS(amount_ore) ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN executed_at >= ?2 AND currency = ?3 THEN ABS(amount_ore) ELSE 0 END), 0),
+                MAX(executed_at)
+            FROM rule_executions
+            WHERE rule_id = ?4 AND status IN (?5, ?6, ?7, ?8)
+            "#
+        )
+        .bind(day_start)
+        .bind(month_start)
+        .bind(currency.as_str())
+        .bind(rule_id)
+        .bind(RuleExecution::PENDING)
+        .bind(RuleExecution::SUCCESS)
+        .bind(RuleExecution::UNKNOWN)
+        .bind(RuleExecution::RETRYING)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(RuleHistory {
+            transfers_today: transfers_today as u32,
+            amount_today: Money::new(amount_today, currency),
+            amount_this_month: Money::new(amount_this_month, currency),
+            last_transfer_at,
+        })
+    }
+
     /// Get executions for a rule.
     pub async fn get_rule_executions(&self, rule_id: &str) -> Result<Vec<RuleExecution>, DbError> {
         let rows = sqlx::query_as::<_, RuleExecutionRow>(
-            "SELECT id, rule_id, transaction_id, transfer_payment_id, amount, from_account, to_account, status, error_message, executed_at FROM rule_executions WHERE rule_id = ? ORDER BY executed_at DESC"
+            "SELECT id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id, message, attempts, next_retry_at FROM rule_executions WHERE rule_id = ? ORDER BY executed_at DESC"
         )
         .bind(rule_id)
         .fetch_all(&self.pool)
@@ -257,7 +674,7 @@ impl Database {
     /// Get all recent executions.
     pub async fn list_executions(&self, limit: i64) -> Result<Vec<RuleExecution>, DbError> {
         let rows = sqlx::query_as::<_, RuleExecutionRow>(
-            "SELECT id, rule_id, transaction_id, transfer_payment_id, amount, from_account, to_account, status, error_message, executed_at FROM rule_executions ORDER BY executed_at DESC LIMIT ?"
+            "SELECT id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id, message, attempts, next_retry_at FROM rule_executions ORDER BY executed_at DESC LIMIT ?"
         )
         .bind(limit)
         .fetch_all(&self.pool)
@@ -269,7 +686,7 @@ impl Database {
     /// Get a single execution by ID.
     pub async fn get_execution(&self, id: &str) -> Result<Option<RuleExecution>, DbError> {
         let row = sqlx::query_as::<_, RuleExecutionRow>(
-            "SELECT id, rule_id, transaction_id, transfer_payment_id, amount, from_account, to_account, status, error_message, executed_at FROM rule_executions WHERE id = ?"
+            "SELECT id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, debit_transaction_id, credit_transaction_id, message, attempts, next_retry_at FROM rule_executions WHERE id = ?"
         )
         .bind(id)
         .fetch_optional(&self.pool)
@@ -278,6 +695,256 @@ impl Database {
         Ok(row.map(|r| r.into()))
     }
 
+    // --- Accumulator Ledger ---
+
+    /// Collect an amount for an accumulating action.
+    ///
+    /// Returns false without writing anything if an amount was already
+    /// collected for this action and source.
+    pub async fn add_accumulator_entry(
+        &self,
+        rule_id: &str,
+        action_index: usize,
+        source_id: &str,
+        amount: Money,
+    ) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            "INSERT INTO accumulator_entries (rule_id, action_index, source_id, amount_ore, currency, created_at) VALUES (?, ?, ?, ?, ?, ?) ON CONFLICT(rule_id, action_index, source_id) DO NOTHING"
+        )
+        .bind(rule_id)
+        .bind(action_index as i64)
+        .bind(source_id)
+        .bind(amount.ore())
+        .bind(amount.currency().as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Sum the amounts waiting to be flushed for an action.
+    ///
+    /// Only amounts in the currency of the oldest waiting amount are included.
+    pub async fn pending_accumulation(
+        &self,
+        rule_id: &str,
+        action_index: usize,
+    ) -> Result<Option<PendingAccumulation>, DbError> {
+        let oldest: Option<(String,)> = sqlx::query_as(
+            "SELECT currency FROM accumulator_entries WHERE rule_id = ? AND action_index = ? AND flush_execution_id IS NULL ORDER BY id LIMIT 1"
+        )
+        .bind(rule_id)
+        .bind(action_index as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some((currency,)) = oldest else {
+            return Ok(None);
+        };
+
+        let (total, up_to_id, oldest_at): (i64, i64, i64) = sqlx::query_as(
+            "SELECT SUM(amount_ore), MAX(id), MIN(created_at) FROM accumulator_entries WHERE rule_id = ? AND action_index = ? AND flush_execution_id IS NULL AND currency = ?"
+        )
+        .bind(rule_id)
+        .bind(action_index as i64)
+        .bind(&currency)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(Some(PendingAccumulation {
+            total: Money::new(total, Currency::parse(&currency).unwrap_or_default()),
+            up_to_id,
+            oldest_at,
+        }))
+    }
+
+    /// Record the execution that flushes an action's waiting amounts, and
+    /// assign those amounts to it, in one transaction.
+    ///
+    /// Claims the amounts up to `up_to_id` in the execution's currency. Returns
+    /// false without writing anything if they no longer add up to the
+    /// execution's amount, e.g. because another flush claimed them first.
+    pub async fn begin_flush(
+        &self,
+        execution: &RuleExecution,
+        action_index: usize,
+        up_to_id: i64,
+    ) -> Result<bool, DbError> {
+        let mut tx = self.pool.begin().await?;
+
+        let (total,): (Option<i64>,) = sqlx::query_as(
+            "SELECT SUM(amount_ore) FROM accumulator_entries WHERE rule_id = ? AND action_index = ? AND flush_execution_id IS NULL AND currency = ? AND id <= ?"
+        )
+        .bind(&execution.rule_id)
+        .bind(action_index as i64)
+        .bind(execution.amount.currency().as_str())
+        .bind(up_to_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        if total != Some(execution.amount.ore()) || !insert_execution(&mut *tx, execution).await? {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            "UPDATE accumulator_entries SET flush_execution_id = ? WHERE rule_id = ? AND action_index = ? AND flush_execution_id IS NULL AND currency = ? AND id <= ?"
+        )
+        .bind(&execution.id)
+        .bind(&execution.rule_id)
+        .bind(action_index as i64)
+        .bind(execution.amount.currency().as_str())
+        .bind(up_to_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    /// Return the amounts of a failed flush to the ledger, to be sent with the next flush.
+    pub async fn release_accumulator_entries(&self, execution_id: &str) -> Result<(), DbError> {
+        sqlx::query("UPDATE accumulator_entries SET flush_execution_id = NULL WHERE flush_execution_id = ?")
+            .bind(execution_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Get the amounts waiting to be flushed for a rule.
+    pub async fn get_pending_accumulator_entries(&self, rule_id: &str) -> Result<Vec<AccumulatorEntry>, DbError> {
+        let rows = sqlx::query_as::<_, AccumulatorEntryRow>(
+            "SELECT id, rule_id, action_index, source_id, amount_ore, currency, created_at, flush_execution_id FROM accumulator_entries WHERE rule_id = ? AND flush_execution_id IS NULL ORDER BY id"
+        )
+        .bind(rule_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into()).collect())
+    }
+
+    // --- Transfer Proposals ---
+
+    /// Record a transfer proposal.
+    ///
+    /// Returns false without writing anything if a proposal with the same
+    /// idempotency key already exists.
+    pub async fn create_proposal(&self, proposal: &TransferProposal) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            "INSERT INTO transfer_proposals (id, rule_id, transaction_id, scheduled_for, action_index, idempotency_key, amount_ore, currency, from_account, to_account, message, status, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT(idempotency_key) DO NOTHING"
+        )
+        .bind(&proposal.id)
+        .bind(&proposal.rule_id)
+        .bind(&proposal.transaction_id)
+        .bind(proposal.scheduled_for)
+        .bind(proposal.action_index)
+        .bind(&proposal.idempotency_key)
+        .bind(proposal.amount.ore())
+        .bind(proposal.amount.currency().as_str())
+        .bind(&proposal.from_account)
+        .bind(&proposal.to_account)
+        .bind(&proposal.message)
+        .bind(&proposal.status)
+        .bind(proposal.created_at)
+        .bind(proposal.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Get a transfer proposal by ID.
+    pub async fn get_proposal(&self, id: &str) -> Result<Option<TransferProposal>, DbError> {
+        let row = sqlx::query_as::<_, TransferProposalRow>(
+            "SELECT id, rule_id, transaction_id, scheduled_for, action_index, idempotency_key, amount_ore, currency, from_account, to_account, message, status, created_at, expires_at, decided_at, decided_by, execution_status FROM transfer_proposals WHERE id = ?"
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|r| r.into()))
+    }
+
+    /// List transfer proposals, newest first, optionally only those with a status.
+    pub async fn list_proposals(&self, status: Option<&str>, limit: i64) -> Result<Vec<TransferProposal>, DbError> {
+        let rows = sqlx::query_as::<_, TransferProposalRow>(
+            "SELECT id, rule_id, transaction_id, scheduled_for, action_index, idempotency_key, amount_ore, currency, from_account, to_account, message, status, created_at, expires_at, decided_at, decided_by, execution_status FROM transfer_proposals WHERE (?1 IS NULL OR status = ?1) ORDER BY created_at DESC LIMIT ?2"
+        )
+        .bind(status)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|r| r.into()).collect())
+    }
+
+    /// Approve or reject a pending proposal that has not expired.
+    ///
+    /// Returns false without changing anything if the proposal was already
+    /// decided or has expired.
+    pub async fn decide_proposal(&self, id: &str, status: &str, decided_by: &str, now: i64) -> Result<bool, DbError> {
+        let result = sqlx::query(
+            "UPDATE transfer_proposals SET status = ?, decided_at = ?, decided_by = ? WHERE id = ? AND status = ? AND expires_at > ?"
+        )
+        .bind(status)
+        .bind(now)
+        .bind(decided_by)
+        .bind(id)
+        .bind(TransferProposal::PENDING)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record the status of the execution made for an approved proposal.
+    pub async fn set_proposal_execution_status(&self, id: &str, execution_status: &str) -> Result<(), DbError> {
+        sqlx::query("UPDATE transfer_proposals SET execution_status = ? WHERE id = ?")
+            .bind(execution_status)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Mark pending proposals past their expiry as expired. Returns the
+    /// number expired.
+    pub async fn expire_proposals(&self, now: i64) -> Result<u64, DbError> {
+        let result = sqlx::query("UPDATE transfer_proposals SET status = ? WHERE status = ? AND expires_at <= ?")
+            .bind(TransferProposal::EXPIRED)
+            .bind(TransferProposal::PENDING)
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    // --- Settings ---
+
+    /// Get a setting, or `None` if it has never been set.
+    pub async fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, DbError> {
+        let row: Option<(String,)> = sqlx::query_as("SELECT value FROM settings WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|(value,)| serde_json::from_str(&value)).transpose().map_err(DbError::from)
+    }
+
+    /// Store a setting.
+    pub async fn set_setting<T: Serialize>(&self, key: &str, value: &T) -> Result<(), DbError> {
+        let value = serde_json::to_string(value)?;
+        sqlx::query(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at"
+        )
+        .bind(key)
+        .bind(&value)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     // --- Audit Log ---
 
     /// Log an audit entry.
@@ -302,11 +969,12 @@ impl Database {
         Ok(())
     }
 
-    /// Query audit log entries.
-    pub async fn query_audit(&self, limit: i64) -> Result<Vec<AuditEntry>, DbError> {
+    /// Query audit log entries, optionally filtered by event type.
+    pub async fn query_audit(&self, limit: i64, event_type: Option<&str>) -> Result<Vec<AuditEntry>, DbError> {
         let rows = sqlx::query_as::<_, AuditEntryRow>(
-            "SELECT id, timestamp, event_type, actor, resource_type, resource_id, details, ip_address, user_agent FROM audit_log ORDER BY timestamp DESC LIMIT ?"
+            "SELECT id, timestamp, event_type, actor, resource_type, resource_id, details, ip_address, user_agent FROM audit_log WHERE (?1 IS NULL OR event_type = ?1) ORDER BY timestamp DESC LIMIT ?2"
         )
+        .bind(event_type)
         .bind(limit)
         .fetch_all(&self.pool)
         .await?;
@@ -327,6 +995,35 @@ impl Database {
     }
 }
 
+/// Insert an execution unless one with the same idempotency key exists.
+async fn insert_execution<'e, E>(executor: E, exec: &RuleExecution) -> Result<bool, DbError>
+where
+    E: sqlx::Executor<'e, Database = sqlx::Sqlite>,
+{
+    let result = sqlx::query(
+        "INSERT INTO rule_executions (id, rule_id, transaction_id, scheduled_for, idempotency_key, transfer_payment_id, amount_ore, currency, from_account, to_account, status, error_message, executed_at, message, attempts) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT(idempotency_key) DO NOTHING"
+    )
+    .bind(&exec.id)
+    .bind(&exec.rule_id)
+    .bind(&exec.transaction_id)
+    .bind(exec.scheduled_for)
+    .bind(&exec.idempotency_key)
+    .bind(&exec.transfer_payment_id)
+    .bind(exec.amount.ore())
+    .bind(exec.amount.currency().as_str())
+    .bind(&exec.from_account)
+    .bind(&exec.to_account)
+    .bind(&exec.status)
+    .bind(&exec.error_message)
+    .bind(exec.executed_at)
+    .bind(&exec.message)
+    .bind(exec.attempts)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
 // --- Row types for SQLx ---
 
 #[derive(sqlx::FromRow)]
@@ -335,11 +1032,21 @@ struct RuleRow {
     name: String,
     description: Option<String>,
     enabled: bool,
+    rule_trigger: String,
     trigger_account_key: String,
+    fire_on: String,
+    include_self_transfers: bool,
+    limits: String,
+    priority: i32,
+    stop_processing: bool,
+    rule_group: Option<String>,
+    requires_approval: bool,
     conditions: String,
     actions: String,
     created_at: i64,
     updated_at: i64,
+    activated_at: i64,
+    next_run_at: Option<i64>,
 }
 
 impl TryFrom<RuleRow> for Rule {
@@ -351,11 +1058,48 @@ impl TryFrom<RuleRow> for Rule {
             name: row.name,
             description: row.description,
             enabled: row.enabled,
+            trigger: serde_json::from_str(&row.rule_trigger)?,
             trigger_account_key: row.trigger_account_key,
+            fire_on: serde_json::from_value(serde_json::Value::String(row.fire_on))?,
+            include_self_transfers: row.include_self_transfers,
+            limits: serde_json::from_str(&row.limits)?,
+            priority: row.priority,
+            stop_processing: row.stop_processing,
+            group: row.rule_group,
+            requires_approval: row.requires_approval,
             conditions: serde_json::from_str(&row.conditions)?,
             actions: serde_json::from_str(&row.actions)?,
             created_at: row.created_at,
             updated_at: row.updated_at,
+            activated_at: row.activated_at,
+            next_run_at: row.next_run_at,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct RuleTransactionLogRow {
+    id: String,
+    rule_id: String,
+    transaction_id: String,
+    transaction_fingerprint: String,
+    action_taken: String,
+    processed_at: i64,
+    condition_trace: Option<String>,
+}
+
+impl TryFrom<RuleTransactionLogRow> for RuleTransactionLog {
+    type Error = DbError;
+
+    fn try_from(row: RuleTransactionLogRow) -> Result<Self, Self::Error> {
+        Ok(RuleTransactionLog {
+            id: row.id,
+            rule_id: row.rule_id,
+            transaction_id: row.transaction_id,
+            transaction_fingerprint: row.transaction_fingerprint,
+            action_taken: row.action_taken,
+            processed_at: row.processed_at,
+            condition_trace: row.condition_trace.as_deref().map(serde_json::from_str).transpose()?,
         })
     }
 }
@@ -368,6 +1112,8 @@ struct TrackedTransactionRow {
     first_seen_at: i64,
     last_updated_at: i64,
     settled: bool,
+    pending_seen_at: Option<i64>,
+    booked_at: Option<i64>,
     raw_data: String,
 }
 
@@ -380,6 +1126,8 @@ impl From<TrackedTransactionRow> for TrackedTransaction {
             first_seen_at: row.first_seen_at,
             last_updated_at: row.last_updated_at,
             settled: row.settled,
+            pending_seen_at: row.pending_seen_at,
+            booked_at: row.booked_at,
             raw_data: row.raw_data,
         }
     }
@@ -389,14 +1137,22 @@ impl From<TrackedTransactionRow> for TrackedTransaction {
 struct RuleExecutionRow {
     id: String,
     rule_id: String,
-    transaction_id: String,
+    transaction_id: Option<String>,
+    scheduled_for: Option<i64>,
+    idempotency_key: String,
     transfer_payment_id: Option<String>,
-    amount: f64,
+    amount_ore: i64,
+    currency: String,
     from_account: String,
     to_account: String,
     status: String,
     error_message: Option<String>,
     executed_at: i64,
+    debit_transaction_id: Option<String>,
+    credit_transaction_id: Option<String>,
+    message: Option<String>,
+    attempts: i64,
+    next_retry_at: Option<i64>,
 }
 
 impl From<RuleExecutionRow> for RuleExecution {
@@ -405,13 +1161,20 @@ impl From<RuleExecutionRow> for RuleExecution {
             id: row.id,
             rule_id: row.rule_id,
             transaction_id: row.transaction_id,
+            scheduled_for: row.scheduled_for,
+            idempotency_key: row.idempotency_key,
             transfer_payment_id: row.transfer_payment_id,
-            amount: row.amount,
+            amount: Money::new(row.amount_ore, Currency::parse(&row.currency).unwrap_or_default()),
             from_account: row.from_account,
             to_account: row.to_account,
             status: row.status,
             error_message: row.error_message,
             executed_at: row.executed_at,
+            debit_transaction_id: row.debit_transaction_id,
+            credit_transaction_id: row.credit_transaction_id,
+            message: row.message,
+            attempts: row.attempts,
+            next_retry_at: row.next_retry_at,
         }
     }
 }
@@ -446,3 +1209,73 @@ impl TryFrom<AuditEntryRow> for AuditEntry {
         })
     }
 }
+
+#[derive(sqlx::FromRow)]
+struct TransferProposalRow {
+    id: String,
+    rule_id: String,
+    transaction_id: Option<String>,
+    scheduled_for: Option<i64>,
+    action_index: i64,
+    idempotency_key: String,
+    amount_ore: i64,
+    currency: String,
+    from_account: String,
+    to_account: String,
+    message: Option<String>,
+    status: String,
+    created_at: i64,
+    expires_at: i64,
+    decided_at: Option<i64>,
+    decided_by: Option<String>,
+    execution_status: Option<String>,
+}
+
+impl From<TransferProposalRow> for TransferProposal {
+    fn from(row: TransferProposalRow) -> Self {
+        TransferProposal {
+            id: row.id,
+            rule_id: row.rule_id,
+            transaction_id: row.transaction_id,
+            scheduled_for: row.scheduled_for,
+            action_index: row.action_index,
+            idempotency_key: row.idempotency_key,
+            amount: Money::new(row.amount_ore, Currency::parse(&row.currency).unwrap_or_default()),
+            from_account: row.from_account,
+            to_account: row.to_account,
+            message: row.message,
+            status: row.status,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+            decided_at: row.decided_at,
+            decided_by: row.decided_by,
+            execution_status: row.execution_status,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct AccumulatorEntryRow {
+    id: i64,
+    rule_id: String,
+    action_index: i64,
+    source_id: String,
+    amount_ore: i64,
+    currency: String,
+    created_at: i64,
+    flush_execution_id: Option<String>,
+}
+
+impl From<AccumulatorEntryRow> for AccumulatorEntry {
+    fn from(row: AccumulatorEntryRow) -> Self {
+        AccumulatorEntry {
+            id: row.id,
+            rule_id: row.rule_id,
+            action_index: row.action_index,
+            source_id: row.source_id,
+            amount: Money::new(row.amount_ore, Currency::parse(&row.currency).unwrap_or_default()),
+            created_at: row.created_at,
+            flush_execution_id: row.flush_execution_id,
+        }
+    }
+}
@@ -2,12 +2,12 @@
 
 use async_trait::async_trait;
 use sb1_api::models::{
-    Account, AccountData, AccountNumber, AccountProperties, ClassificationInput,
-    CreateTransferDTO, Owner, Transaction, TransactionResponse, TransferResponse,
-    TransferToCreditCardDTO,
+    Account, AccountData, AccountNumber, AccountProperties, AccountType, BookingStatus,
+    ClassificationInput, CreateTransferDTO, Owner, Transaction, TransactionQuery, TransactionResponse,
+    TransactionSource, TransactionType, TransferResponse, TransferToCreditCardDTO,
 };
-use sb1_api::BankApiClient;
 use sb1_api::error::ApiError;
+use sb1_api::{BankApiClient, Money};
 use std::sync::atomic::{AtomicI64, Ordering};
 use tokio::sync::RwLock;
 use tracing::info;
@@ -53,12 +53,12 @@ impl DemoBankClient {
                 iban: "NO9312345678901".to_string(),
                 name: "Checking Account".to_string(),
                 description: "Main checking account".to_string(),
-                balance: 15420.50,
-                available_balance: 15420.50,
+                balance: Money::nok(1542050),
+                available_balance: Money::nok(1542050),
                 currency_code: "NOK".to_string(),
                 owner: Some(owner.clone()),
                 product_type: "CURRENT".to_string(),
-                type_field: "ACCOUNT".to_string(),
+                type_field: AccountType::Account,
                 product_id: Some("current-account".to_string()),
                 description_code: None,
                 account_properties: AccountProperties {
@@ -92,12 +92,12 @@ impl DemoBankClient {
                 iban: "NO9312345678902".to_string(),
                 name: "Savings Account".to_string(),
                 description: "High-interest savings".to_string(),
-                balance: 52000.00,
-                available_balance: 52000.00,
+                balance: Money::nok(5200000),
+                available_balance: Money::nok(5200000),
                 currency_code: "NOK".to_string(),
                 owner: Some(owner.clone()),
                 product_type: "SAVINGS".to_string(),
-                type_field: "ACCOUNT".to_string(),
+                type_field: AccountType::Account,
                 product_id: Some("savings-account".to_string()),
                 description_code: None,
                 account_properties: AccountProperties {
@@ -131,16 +131,16 @@ impl DemoBankClient {
                 iban: "NO9312345678903".to_string(),
                 name: "Credit Card".to_string(),
                 description: "Visa Gold".to_string(),
-                balance: -2340.00,
-                available_balance: 47660.00,
+                balance: Money::nok(-234000),
+                available_balance: Money::nok(4766000),
                 currency_code: "NOK".to_string(),
                 owner: Some(owner),
                 product_type: "CREDITCARD".to_string(),
-                type_field: "CREDITCARD".to_string(),
+                type_field: AccountType::CreditCard,
                 product_id: Some("visa-gold".to_string()),
                 description_code: None,
                 account_properties: AccountProperties::default(),
-                credit_card_credit_limit: Some(50000.0),
+                credit_card_credit_limit: Some(Money::nok(5000000)),
                 credit_card_account_id: Some("cc-account-123".to_string()),
             },
         ]
@@ -164,23 +164,23 @@ impl DemoBankClient {
                     formatted: checking.account_number.clone(),
                     unformatted: checking.account_number.clone(),
                 },
-                amount: -179.0,
+                amount: Money::nok(-17900),
                 date: now - day_ms,
                 interest_date: Some(now - day_ms),
-                type_code: "PURCHASE".to_string(),
+                type_code: TransactionType::Purchase,
                 type_text: "Purchase".to_string(),
                 currency_code: "NOK".to_string(),
                 can_show_details: true,
-                source: "CARD".to_string(),
+                source: TransactionSource::Card,
                 is_confidential: false,
-                booking_status: "BOOKED".to_string(),
+                booking_status: BookingStatus::Booked,
                 account_name: checking.name.clone(),
                 account_key: checking.key.clone(),
                 account_currency: "NOK".to_string(),
                 is_from_currency_account: false,
                 classification_input: ClassificationInput {
                     id: "tx-001".to_string(),
-                    amount: -179.0,
+                    amount: Money::nok(-17900),
                     type_field: "PURCHASE".to_string(),
                     text: Some("Netflix".to_string()),
                     date: now - day_ms,
@@ -200,23 +200,23 @@ impl DemoBankClient {
                     formatted: checking.account_number.clone(),
                     unformatted: checking.account_number.clone(),
                 },
-                amount: -119.0,
+                amount: Money::nok(-11900),
                 date: now - 2 * day_ms,
                 interest_date: Some(now - 2 * day_ms),
-                type_code: "PURCHASE".to_string(),
+                type_code: TransactionType::Purchase,
                 type_text: "Purchase".to_string(),
                 currency_code: "NOK".to_string(),
                 can_show_details: true,
-                source: "CARD".to_string(),
+                source: TransactionSource::Card,
                 is_confidential: false,
-                booking_status: "BOOKED".to_string(),
+                booking_status: BookingStatus::Booked,
                 account_name: checking.name.clone(),
                 account_key: checking.key.clone(),
                 account_currency: "NOK".to_string(),
                 is_from_currency_account: false,
                 classification_input: ClassificationInput {
                     id: "tx-002".to_string(),
-                    amount: -119.0,
+                    amount: Money::nok(-11900),
                     type_field: "PURCHASE".to_string(),
                     text: Some("Spotify".to_string()),
                     date: now - 2 * day_ms,
@@ -236,23 +236,23 @@ impl DemoBankClient {
                     formatted: checking.account_number.clone(),
                     unformatted: checking.account_number.clone(),
                 },
-                amount: -342.50,
+                amount: Money::nok(-34250),
                 date: now - 3 * day_ms,
                 interest_date: Some(now - 3 * day_ms),
-                type_code: "PURCHASE".to_string(),
+                type_code: TransactionType::Purchase,
                 type_text: "Purchase".to_string(),
                 currency_code: "NOK".to_string(),
                 can_show_details: true,
-                source: "CARD".to_string(),
+                source: TransactionSource::Card,
                 is_confidential: false,
-                booking_status: "BOOKED".to_string(),
+                booking_status: BookingStatus::Booked,
                 account_name: checking.name.clone(),
                 account_key: checking.key.clone(),
                 account_currency: "NOK".to_string(),
                 is_from_currency_account: false,
                 classification_input: ClassificationInput {
                     id: "tx-003".to_string(),
-                    amount: -342.50,
+                    amount: Money::nok(-34250),
                     type_field: "PURCHASE".to_string(),
                     text: Some("Rema 1000".to_string()),
                     date: now - 3 * day_ms,
@@ -272,23 +272,23 @@ impl DemoBankClient {
                     formatted: checking.account_number.clone(),
                     unformatted: checking.account_number.clone(),
                 },
-                amount: 45000.0,
+                amount: Money::nok(4500000),
                 date: now - 5 * day_ms,
                 interest_date: Some(now - 5 * day_ms),
-                type_code: "SALARY".to_string(),
+                type_code: TransactionType::Salary,
                 type_text: "Salary".to_string(),
                 currency_code: "NOK".to_string(),
                 can_show_details: true,
-                source: "TRANSFER".to_string(),
+                source: TransactionSource::Transfer,
                 is_confidential: false,
-                booking_status: "BOOKED".to_string(),
+                booking_status: BookingStatus::Booked,
                 account_name: checking.name.clone(),
                 account_key: checking.key.clone(),
                 account_currency: "NOK".to_string(),
                 is_from_currency_account: false,
                 classification_input: ClassificationInput {
                     id: "tx-004".to_string(),
-                    amount: 45000.0,
+                    amount: Money::nok(4500000),
                     type_field: "SALARY".to_string(),
                     text: Some("Salary".to_string()),
                     date: now - 5 * day_ms,
@@ -308,23 +308,23 @@ impl DemoBankClient {
                     formatted: checking.account_number.clone(),
                     unformatted: checking.account_number.clone(),
                 },
-                amount: -599.0,
+                amount: Money::nok(-59900),
                 date: now,
                 interest_date: None,
-                type_code: "PURCHASE".to_string(),
+                type_code: TransactionType::Purchase,
                 type_text: "Purchase".to_string(),
                 currency_code: "NOK".to_string(),
                 can_show_details: true,
-                source: "CARD".to_string(),
+                source: TransactionSource::Card,
                 is_confidential: false,
-                booking_status: "PENDING".to_string(),
+                booking_status: BookingStatus::Pending,
                 account_name: checking.name.clone(),
                 account_key: checking.key.clone(),
                 account_currency: "NOK".to_string(),
                 is_from_currency_account: false,
                 classification_input: ClassificationInput {
                     id: "tx-005".to_string(),
-                    amount: -599.0,
+                    amount: Money::nok(-59900),
                     type_field: "PURCHASE".to_string(),
                     text: Some("Amazon".to_string()),
                     date: now,
@@ -347,7 +347,7 @@ impl DemoBankClient {
         &self,
         account_key: &str,
         description: &str,
-        amount: f64,
+        amount: Money,
         is_settled: bool,
     ) -> Option<Transaction> {
         let account = self.accounts.iter().find(|a| a.key == account_key)?;
@@ -367,13 +367,13 @@ impl DemoBankClient {
             amount,
             date: now,
             interest_date: if is_settled { Some(now) } else { None },
-            type_code: if amount >= 0.0 { "TRANSFER".to_string() } else { "PURCHASE".to_string() },
-            type_text: if amount >= 0.0 { "Transfer".to_string() } else { "Purchase".to_string() },
+            type_code: if !amount.is_negative() { TransactionType::Transfer } else { TransactionType::Purchase },
+            type_text: if !amount.is_negative() { "Transfer".to_string() } else { "Purchase".to_string() },
             currency_code: "NOK".to_string(),
             can_show_details: true,
-            source: "CARD".to_string(),
+            source: TransactionSource::Card,
             is_confidential: false,
-            booking_status: if is_settled { "BOOKED".to_string() } else { "PENDING".to_string() },
+            booking_status: if is_settled { BookingStatus::Booked } else { BookingStatus::Pending },
             account_name: account.name.clone(),
             account_key: account.key.clone(),
             account_currency: "NOK".to_string(),
@@ -381,7 +381,7 @@ impl DemoBankClient {
             classification_input: ClassificationInput {
                 id: format!("tx-{}", tx_id),
                 amount,
-                type_field: if amount >= 0.0 { "TRANSFER".to_string() } else { "PURCHASE".to_string() },
+                type_field: if !amount.is_negative() { "TRANSFER".to_string() } else { "PURCHASE".to_string() },
                 text: Some(description.to_string()),
                 date: now,
             },
@@ -412,18 +412,18 @@ impl BankApiClient for DemoBankClient {
         })
     }
 
-    async fn get_transactions(&self, account_key: &str) -> Result<TransactionResponse, ApiError> {
+    async fn get_transactions(
+        &self,
+        account_key: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionResponse, ApiError> {
         let transactions = self.transactions.read().await;
-        let filtered: Vec<Transaction> = transactions
+        let filtered = transactions
             .iter()
             .filter(|tx| tx.account_key == account_key)
-            .cloned()
-            .collect();
-        
-        Ok(TransactionResponse {
-            transactions: filtered,
-            errors: vec![],
-        })
+            .cloned();
+
+        Ok(query.apply(filtered))
     }
 
     async fn create_transfer(&self, transfer: CreateTransferDTO) -> Result<TransferResponse, ApiError> {
@@ -6,7 +6,7 @@
 use clap::Parser;
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use tracing::info;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 mod api;
@@ -46,6 +46,7 @@ struct Args {
 pub struct AppState {
     pub db: Database,
     pub bank_client: Arc<dyn sb1_api::BankApiClient>,
+    pub rule_engine: Arc<RuleEngine>,
     pub scheduler: Arc<Scheduler>,
     pub shutdown_tx: broadcast::Sender<()>,
     pub demo_mode: bool,
@@ -99,9 +100,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create rule engine
     let rule_engine = Arc::new(RuleEngine::new(db.clone(), bank_client.clone()));
 
+    let reconciled = rule_engine
+        .reconcile_pending_executions()
+        .await
+        .map_err(|e| e as Box<dyn std::error::Error>)?;
+    if reconciled > 0 {
+        warn!("Reconciled {} interrupted transfer executions", reconciled);
+    }
+
     // Create scheduler
     let scheduler_config = SchedulerConfig::default();
-    let scheduler = Arc::new(Scheduler::new(scheduler_config, rule_engine));
+    let scheduler = Arc::new(Scheduler::new(scheduler_config, rule_engine.clone()));
 
     // Create shutdown channel
     let (shutdown_tx, shutdown_rx) = broadcast::channel(1);
@@ -110,6 +119,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let state = AppState {
         db,
         bank_client,
+        rule_engine,
         scheduler: scheduler.clone(),
         shutdown_tx: shutdown_tx.clone(),
         demo_mode: args.demo,
@@ -0,0 +1,255 @@
+//! Aggregate conditions over stored transaction history.
+
+use super::context::EvalContext;
+use super::schedule::{SCHEDULE_TIMEZONE, local_day_start};
+use super::types::{
+    Aggregate, AggregateFunction, AggregateValue, AggregateWindow, CalendarPeriod, Comparison, Condition,
+};
+use chrono::{DateTime, Datelike, Duration};
+use sb1_api::Money;
+use std::cmp::Ordering;
+use std::fmt;
+
+impl Aggregate {
+    /// Evaluate the aggregate over `ctx.history`. An amount to compare to is
+    /// taken to be in the transaction's currency.
+    pub fn evaluate(&self, ctx: &EvalContext) -> bool {
+        let value = match self.function.value() {
+            AggregateValue::Amount(amount) => AggregateValue::Amount(ctx.bind(amount)),
+            count => count,
+        };
+        self.measure(ctx)
+            .and_then(|measured| measured.partial_cmp(&value))
+            .is_some_and(|ordering| match self.comparison {
+                Comparison::GreaterThan => ordering.is_gt(),
+                Comparison::LessThan => ordering.is_lt(),
+            })
+    }
+
+    /// The aggregated value, or `None` if there is none (an average of no
+    /// transactions). Amounts in another currency than the transaction's are
+    /// left out of sums and averages.
+    pub fn measure(&self, ctx: &EvalContext) -> Option<AggregateValue> {
+        let end = ctx.reference_time_millis();
+        let start = self.window.start(end)?;
+
+        let mut count = 0u32;
+        let mut summed = 0i64;
+        let mut total = ctx.zero();
+        for tx in ctx.history {
+            if tx.account_key != ctx.trigger_account_key || tx.date < start || tx.date > end {
+                continue;
+            }
+            let tx_ctx = EvalContext { tx: Some(tx), history: &[], ..*ctx };
+            if self.filter.as_ref().is_some_and(|f| !f.evaluate(&tx_ctx)) {
+                continue;
+            }
+            count = count.saturating_add(1);
+            if let Some(sum) = total.checked_add(tx.amount.abs()) {
+                total = sum;
+                summed += 1;
+            }
+        }
+
+        match self.function {
+            AggregateFunction::Count { .. } => Some(AggregateValue::Count(count)),
+            AggregateFunction::Sum { .. } => Some(AggregateValue::Amount(total)),
+            AggregateFunction::Average { .. } if summed == 0 => None,
+            AggregateFunction::Average { .. } => {
+                Some(AggregateValue::Amount(Money::new(total.ore() / summed, total.currency())))
+            }
+        }
+    }
+}
+
+impl AggregateFunction {
+    /// The value the aggregate is compared to.
+    pub fn value(&self) -> AggregateValue {
+        match *self {
+            AggregateFunction::Count { value } => AggregateValue::Count(value),
+            AggregateFunction::Sum { value } | AggregateFunction::Average { value } => AggregateValue::Amount(value),
+        }
+    }
+}
+
+impl PartialOrd for AggregateValue {
+    /// Counts and amounts, or amounts in different currencies, are not
+    /// comparable.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (AggregateValue::Count(a), AggregateValue::Count(b)) => a.partial_cmp(b),
+            (AggregateValue::Amount(a), AggregateValue::Amount(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AggregateValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AggregateValue::Count(count) => write!(f, "{}", count),
+            AggregateValue::Amount(amount) => write!(f, "{}", amount),
+        }
+    }
+}
+
+impl AggregateWindow {
+    /// Start of the window ending at `end` (Unix millis).
+    pub fn start(&self, end: i64) -> Option<i64> {
+        match self {
+            AggregateWindow::Rolling { days } => end.checked_sub(Duration::days(i64::from(*days)).num_milliseconds()),
+            AggregateWindow::Calendar { period } => {
+                let date = DateTime::from_timestamp_millis(end)?
+                    .with_timezone(&SCHEDULE_TIMEZONE)
+                    .date_naive();
+                let first = match period {
+                    CalendarPeriod::Day => date,
+                    CalendarPeriod::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+                    CalendarPeriod::Month => date.with_day(1)?,
+                };
+                local_day_start(first)
+            }
+        }
+    }
+}
+
+impl Condition {
+    /// Earliest transaction date (Unix millis) an aggregate in this condition
+    /// needs for a window ending at `end`, or `None` if it has no aggregates.
+    pub fn history_start(&self, end: i64) -> Option<i64> {
+        match self {
+            Condition::Aggregate(aggregate) => aggregate.window.start(end),
+            Condition::And { conditions } | Condition::Or { conditions } => {
+                conditions.iter().filter_map(|c| c.history_start(end)).min()
+            }
+            Condition::Not { condition } => condition.history_start(end),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use sb1_api::models::{Transaction, TransactionSource};
+
+    fn oslo_millis(y: i32, m: u32, d: u32, h: u32) -> i64 {
+        SCHEDULE_TIMEZONE.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap().timestamp_millis()
+    }
+
+    fn tx(id: &str, ore: i64, date: i64, source: TransactionSource) -> Transaction {
+        Transaction {
+            id: id.to_string(),
+            amount: Money::nok(ore),
+            date,
+            source,
+            account_key: "acc-1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn aggregate(function: AggregateFunction, window: AggregateWindow, comparison: Comparison) -> Aggregate {
+        Aggregate {
+            function,
+            window,
+            filter: None,
+            comparison,
+        }
+    }
+
+    #[test]
+    fn test_calendar_window_start() {
+        let end = oslo_millis(2026, 3, 19, 15);
+        let month = AggregateWindow::Calendar { period: CalendarPeriod::Month };
+        assert_eq!(month.start(end), Some(oslo_millis(2026, 3, 1, 0)));
+        // 19 March 2026 is a Thursday
+        let week = AggregateWindow::Calendar { period: CalendarPeriod::Week };
+        assert_eq!(week.start(end), Some(oslo_millis(2026, 3, 16, 0)));
+        let day = AggregateWindow::Calendar { period: CalendarPeriod::Day };
+        assert_eq!(day.start(end), Some(oslo_millis(2026, 3, 19, 0)));
+    }
+
+    #[test]
+    fn test_monthly_spending_and_daily_count() {
+        let history = vec![
+            tx("old", -500000, oslo_millis(2026, 2, 27, 12), TransactionSource::Card),
+            tx("a", -250000, oslo_millis(2026, 3, 2, 12), TransactionSource::Card),
+            tx("b", -250000, oslo_millis(2026, 3, 18, 12), TransactionSource::Card),
+            tx("c", -10000, oslo_millis(2026, 3, 19, 9), TransactionSource::Online),
+            tx("d", -10000, oslo_millis(2026, 3, 19, 10), TransactionSource::Online),
+            tx("later", -900000, oslo_millis(2026, 3, 20, 10), TransactionSource::Card),
+        ];
+        let current = &history[4];
+        let ctx = EvalContext::new(current, &[], "acc-1").with_history(&history);
+        let this_month = AggregateWindow::Calendar { period: CalendarPeriod::Month };
+
+        // 2500 + 2500 + 100 + 100 this month; earlier and later transactions excluded
+        let sum = |value| AggregateFunction::Sum { value };
+        let spending = aggregate(sum(Money::nok(500000)), this_month, Comparison::GreaterThan);
+        assert_eq!(spending.measure(&ctx), Some(AggregateValue::Amount(Money::nok(520000))));
+        assert!(spending.evaluate(&ctx));
+        let spending = aggregate(sum(Money::nok(520000)), this_month, Comparison::GreaterThan);
+        assert!(!spending.evaluate(&ctx));
+
+        let mut online_today = aggregate(
+            AggregateFunction::Count { value: 1 },
+            AggregateWindow::Calendar { period: CalendarPeriod::Day },
+            Comparison::GreaterThan,
+        );
+        online_today.filter = Some(Box::new(Condition::TransactionSource { source: TransactionSource::Online }));
+        assert!(online_today.evaluate(&ctx));
+        online_today.function = AggregateFunction::Count { value: 2 };
+        assert!(!online_today.evaluate(&ctx));
+
+        // (2500 + 100 + 100) / 3 in the last week
+        let average = |value| AggregateFunction::Average { value };
+        let weekly = aggregate(average(Money::nok(90000)), AggregateWindow::Rolling { days: 7 }, Comparison::LessThan);
+        assert_eq!(weekly.measure(&ctx), Some(AggregateValue::Amount(Money::nok(90000))));
+        assert!(!weekly.evaluate(&ctx));
+
+        // Nothing in the window
+        let empty = aggregate(average(Money::nok(100000)), this_month, Comparison::LessThan);
+        assert!(!empty.evaluate(&EvalContext::new(current, &[], "acc-1")));
+    }
+
+    #[test]
+    fn test_value_follows_function() {
+        let condition: Condition = serde_json::from_str(
+            r#"{"type": "aggregate", "function": "sum", "value": 5000.5, "window": {"type": "rolling", "days": 30}, "comparison": "greater_than"}"#,
+        )
+        .unwrap();
+        let Condition::Aggregate(aggregate) = &condition else { panic!("not an aggregate") };
+        assert_eq!(aggregate.function, AggregateFunction::Sum { value: Money::nok(500050) });
+        assert_eq!(serde_json::to_value(&condition).unwrap()["value"], 5000.5);
+
+        let count = r#"{"type": "aggregate", "function": "count", "value": 2, "window": {"type": "rolling", "days": 30}, "comparison": "greater_than"}"#;
+        assert!(serde_json::from_str::<Condition>(count).is_ok());
+        let fractional = count.replace("\"value\": 2", "\"value\": 2.5");
+        assert!(serde_json::from_str::<Condition>(&fractional).is_err());
+    }
+
+    #[test]
+    fn test_history_start() {
+        let end = oslo_millis(2026, 3, 19, 15);
+        let condition = Condition::Not {
+            condition: Box::new(Condition::Or {
+                conditions: vec![
+                    Condition::IsSettled,
+                    Condition::Aggregate(aggregate(
+                        AggregateFunction::Count { value: 1 },
+                        AggregateWindow::Rolling { days: 30 },
+                        Comparison::GreaterThan,
+                    )),
+                    Condition::Aggregate(aggregate(
+                        AggregateFunction::Count { value: 1 },
+                        AggregateWindow::Calendar { period: CalendarPeriod::Month },
+                        Comparison::GreaterThan,
+                    )),
+                ],
+            }),
+        };
+        assert_eq!(condition.history_start(end), Some(end - Duration::days(30).num_milliseconds()));
+        assert_eq!(Condition::IsSettled.history_start(end), None);
+    }
+}
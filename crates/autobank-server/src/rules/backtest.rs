@@ -0,0 +1,328 @@
+//! Dry runs of a rule over past transactions.
+
+use super::compiled::CompiledRule;
+use super::context::EvalContext;
+use super::engine::period_starts_at;
+use super::limits::{RuleHistory, RuleLimits};
+use super::types::Action;
+use chrono::DateTime;
+use sb1_api::Money;
+use sb1_api::models::{Account, Transaction};
+use serde::{Deserialize, Serialize};
+
+/// Where a backtest reads past transactions from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BacktestSource {
+    /// Transactions stored while polling.
+    #[default]
+    Stored,
+    /// Transactions fetched from the bank.
+    Bank,
+}
+
+/// What a rule would have done with past transactions.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub transactions_evaluated: usize,
+    pub matches: Vec<BacktestMatch>,
+    /// Transfers the rule would have made, accumulated amounts included.
+    pub transfer_count: usize,
+    /// Total the rule would have moved.
+    pub total_amount: Money,
+}
+
+/// A transaction the rule's conditions matched.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestMatch {
+    pub transaction_id: String,
+    pub date: i64,
+    pub description: Option<String>,
+    pub amount: Money,
+    /// The rule limit that would have stopped the rule, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limited: Option<String>,
+    pub transfers: Vec<BacktestTransfer>,
+}
+
+/// A transfer a matched transaction would have led to.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestTransfer {
+    pub action_index: usize,
+    pub from_account: Option<BacktestAccount>,
+    pub to_account: Option<BacktestAccount>,
+    pub amount: Money,
+    /// `transfer`, `accumulate`, `skipped` (nothing to transfer) or
+    /// `unresolved` (an account was not found).
+    pub outcome: &'static str,
+}
+
+/// An account a transfer resolved to.
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestAccount {
+    pub key: String,
+    pub name: String,
+    pub account_number: String,
+}
+
+impl From<&Account> for BacktestAccount {
+    fn from(account: &Account) -> Self {
+        Self {
+            key: account.key.clone(),
+            name: account.name.clone(),
+            account_number: account.account_number.clone(),
+        }
+    }
+}
+
+/// Run `rule` over the transactions on its account dated in `start..=end`
+/// (Unix millis), oldest first.
+///
+/// All of `transactions` serve as history for aggregate conditions, so they
+/// may begin before `start`. Balances are read from `accounts` as they are
+/// now. Per-rule limits are checked against the transfers made earlier in
+/// the backtest; server-wide limits are not checked.
+pub fn simulate(rule: &CompiledRule, transactions: &[Transaction], accounts: &[Account], start: i64, end: i64) -> BacktestReport {
+    let mut report = BacktestReport {
+        transactions_evaluated: 0,
+        matches: Vec::new(),
+        transfer_count: 0,
+        total_amount: EvalContext::without_trigger(accounts, &rule.trigger_account_key).zero(),
+    };
+    // (Unix seconds, amount) of each simulated transfer
+    let mut made: Vec<(i64, Money)> = Vec::new();
+
+    let in_window = transactions.iter().filter(|tx| {
+        tx.account_key == rule.trigger_account_key
+            && (start..=end).contains(&tx.date)
+            && rule.fire_on.applies_to(&tx.booking_status)
+    });
+
+    for tx in in_window {
+        report.transactions_evaluated += 1;
+        let ctx = EvalContext::new(tx, accounts, &rule.trigger_account_key)
+            .with_history(transactions)
+            .with_patterns(rule.patterns());
+        if !rule.conditions.iter().all(|c| c.evaluate(&ctx)) {
+            continue;
+        }
+
+        let at = tx.date / 1000;
+        let limited = if rule.limits == RuleLimits::default() {
+            None
+        } else {
+            let history = simulated_history(&made, at, ctx.zero());
+            rule.limits.check(rule.transfer_total(&ctx), &history, at).map(|(_, reason)| reason)
+        };
+
+        let transfers: Vec<BacktestTransfer> = rule
+            .actions
+            .iter()
+            .enumerate()
+            .map(|(action_index, action)| simulate_action(&ctx, action_index, action))
+            .collect();
+
+        if limited.is_none() {
+            for transfer in transfers.iter().filter(|t| matches!(t.outcome, "transfer" | "accumulate")) {
+                made.push((at, transfer.amount));
+                report.transfer_count += 1;
+                report.total_amount = report.total_amount.checked_add(transfer.amount).unwrap_or(report.total_amount);
+            }
+        }
+
+        report.matches.push(BacktestMatch {
+            transaction_id: tx.id.clone(),
+            date: tx.date,
+            description: tx.cleaned_description.clone().or_else(|| tx.description.clone()),
+            amount: tx.amount,
+            limited,
+            transfers,
+        });
+    }
+
+    report
+}
+
+fn simulate_action(ctx: &EvalContext, action_index: usize, action: &Action) -> BacktestTransfer {
+    match action {
+        Action::Transfer {
+            from_account,
+            to_account,
+            amount,
+            accumulate,
+            ..
+        } => {
+            let from = ctx.account(from_account);
+            let to = ctx.account(to_account);
+            let amount = amount.calculate(ctx);
+            let outcome = if from.is_none() || to.is_none() {
+                "unresolved"
+            } else if amount.ore() <= 0 {
+                "skipped"
+            } else if accumulate.is_some() {
+                "accumulate"
+            } else {
+                "transfer"
+            };
+            BacktestTransfer {
+                action_index,
+                from_account: from.map(BacktestAccount::from),
+                to_account: to.map(BacktestAccount::from),
+                amount,
+                outcome,
+            }
+        }
+    }
+}
+
+/// Rule history at `at` (Unix seconds) from simulated transfers, with days
+/// and months starting at local midnight like the engine's.
+fn simulated_history(made: &[(i64, Money)], at: i64, zero: Money) -> RuleHistory {
+    let (start_of_day, start_of_month) = DateTime::from_timestamp(at, 0).map_or((at, at), period_starts_at);
+    let add = |total: Money, amount: Money| total.checked_add(amount).unwrap_or(total);
+
+    let mut history = RuleHistory {
+        transfers_today: 0,
+        amount_today: zero,
+        amount_this_month: zero,
+        last_transfer_at: made.iter().map(|(ts, _)| *ts).max(),
+    };
+    for (ts, amount) in made {
+        if *ts >= start_of_month {
+            history.amount_this_month = add(history.amount_this_month, *amount);
+        }
+        if *ts >= start_of_day {
+            history.transfers_today += 1;
+            history.amount_today = add(history.amount_today, *amount);
+        }
+    }
+    history
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{AccountRef, AmountSpec, Condition, FireOn, Rule, RuleTrigger};
+    use chrono::Duration;
+    use sb1_api::models::BookingStatus;
+
+    const DAY_MILLIS: i64 = 24 * 60 * 60 * 1000;
+
+    fn account(key: &str) -> Account {
+        Account {
+            key: key.to_string(),
+            account_number: format!("{}-number", key),
+            name: key.to_string(),
+            ..Account::default()
+        }
+    }
+
+    fn tx(id: &str, ore: i64, date: i64) -> Transaction {
+        Transaction {
+            id: id.to_string(),
+            amount: Money::nok(ore),
+            date,
+            booking_status: BookingStatus::Booked,
+            account_key: "checking".to_string(),
+            ..Transaction::default()
+        }
+    }
+
+    fn rule(to: &str) -> Rule {
+        Rule {
+            id: "rule-1".to_string(),
+            name: "Round up".to_string(),
+            description: None,
+            enabled: false,
+            trigger: RuleTrigger::Transaction,
+            trigger_account_key: "checking".to_string(),
+            fire_on: FireOn::FirstSeen,
+            include_self_transfers: false,
+            limits: RuleLimits::default(),
+            priority: 0,
+            stop_processing: false,
+            group: None,
+            requires_approval: false,
+            conditions: vec![Condition::AmountLessThan { value: Money::nok(0) }],
+            actions: vec![Action::Transfer {
+                from_account: AccountRef::TriggerAccount,
+                to_account: AccountRef::ByKey { key: to.to_string() },
+                amount: AmountSpec::RoundUp { to: Money::nok(1000) },
+                message: None,
+                accumulate: None,
+            }],
+            created_at: 0,
+            updated_at: 0,
+            activated_at: 0,
+            next_run_at: None,
+        }
+    }
+
+    fn compiled(rule: Rule) -> CompiledRule {
+        CompiledRule::compile(rule).unwrap()
+    }
+
+    #[test]
+    fn test_simulate_reports_matches_and_totals() {
+        let accounts = [account("checking"), account("savings")];
+        let day = 1_767_225_600_000; // 2026-01-01 UTC
+        let transactions = vec![
+            tx("before", -14900, day - DAY_MILLIS),
+            tx("a", -14900, day),
+            tx("income", 500000, day + 1000),
+            tx("b", -2200, day + 2000),
+            tx("round", -3000, day + 3000),
+        ];
+
+        let report = simulate(&compiled(rule("savings")), &transactions, &accounts, day, day + DAY_MILLIS);
+        assert_eq!(report.transactions_evaluated, 4);
+        let ids: Vec<_> = report.matches.iter().map(|m| m.transaction_id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "round"]);
+        let outcomes: Vec<_> = report.matches.iter().map(|m| m.transfers[0].outcome).collect();
+        assert_eq!(outcomes, vec!["transfer", "transfer", "skipped"]);
+        assert_eq!(report.transfer_count, 2);
+        assert_eq!(report.total_amount, Money::nok(100 + 800));
+        assert_eq!(report.matches[0].transfers[0].to_account.as_ref().unwrap().key, "savings");
+
+        let report = simulate(&compiled(rule("missing")), &transactions, &accounts, day, day + DAY_MILLIS);
+        assert_eq!(report.matches[0].transfers[0].outcome, "unresolved");
+        assert_eq!(report.transfer_count, 0);
+    }
+
+    #[test]
+    fn test_simulate_applies_rule_limits() {
+        let accounts = [account("checking"), account("savings")];
+        let day = 1_767_225_600_000;
+        let transactions: Vec<_> = (0..4)
+            .map(|i| tx(&format!("tx-{}", i), -14900, day + Duration::hours(i).num_milliseconds()))
+            .collect();
+        let mut rule = rule("savings");
+        rule.limits.max_transfers_per_day = Some(2);
+        let rule = compiled(rule);
+
+        let report = simulate(&rule, &transactions, &accounts, day, day + DAY_MILLIS);
+        assert_eq!(report.matches.len(), 4);
+        assert_eq!(report.transfer_count, 2);
+        assert!(report.matches[2].limited.is_some());
+    }
+
+    #[test]
+    fn test_simulated_days_start_at_local_midnight() {
+        let accounts = [account("checking"), account("savings")];
+        let day = 1_767_225_600_000;
+        let hour = Duration::hours(1).num_milliseconds();
+        // 23:30 on 1 January in Oslo, then 00:30 and 01:30 on 2 January
+        // (all but the last still 1 January in UTC)
+        let transactions: Vec<_> = [22, 23, 24]
+            .iter()
+            .map(|h| tx(&format!("tx-{}", h), -14900, day + h * hour + hour / 2))
+            .collect();
+        let mut rule = rule("savings");
+        rule.limits.max_transfers_per_day = Some(1);
+        let rule = compiled(rule);
+
+        let report = simulate(&rule, &transactions, &accounts, day, day + 2 * DAY_MILLIS);
+        let limited: Vec<_> = report.matches.iter().map(|m| m.limited.is_some()).collect();
+        assert_eq!(limited, vec![false, false, true]);
+    }
+}
@@ -0,0 +1,150 @@
+//! Rules prepared for evaluation.
+
+use super::types::{Condition, Rule};
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// Compiled regex patterns, looked up by pattern and case sensitivity.
+#[derive(Debug, Clone, Default)]
+pub struct Patterns {
+    case_sensitive: HashMap<String, Regex>,
+    case_insensitive: HashMap<String, Regex>,
+}
+
+impl Patterns {
+    /// The compiled regex for `pattern`, if it has been compiled.
+    pub fn get(&self, pattern: &str, case_insensitive: bool) -> Option<&Regex> {
+        if case_insensitive {
+            self.case_insensitive.get(pattern)
+        } else {
+            self.case_sensitive.get(pattern)
+        }
+    }
+
+    /// Compile `pattern` unless it already is.
+    pub fn compile(&mut self, pattern: &str, case_insensitive: bool) -> Result<(), regex::Error> {
+        let map = if case_insensitive {
+            &mut self.case_insensitive
+        } else {
+            &mut self.case_sensitive
+        };
+        if !map.contains_key(pattern) {
+            let source = if case_insensitive {
+                format!("(?i){}", pattern)
+            } else {
+                pattern.to_string()
+            };
+            map.insert(pattern.to_string(), Regex::new(&source)?);
+        }
+        Ok(())
+    }
+}
+
+/// A rule with its regex patterns compiled. Dereferences to the rule.
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    rule: Rule,
+    patterns: Patterns,
+}
+
+impl CompiledRule {
+    /// Compile a rule's patterns. Fails on the first invalid pattern.
+    pub fn compile(rule: Rule) -> Result<Self, String> {
+        let mut patterns = Patterns::default();
+        for condition in &rule.conditions {
+            collect_patterns(condition, &mut patterns)
+                .map_err(|e| format!("Rule '{}' has an invalid pattern: {}", rule.name, e))?;
+        }
+        Ok(Self { rule, patterns })
+    }
+
+    pub fn patterns(&self) -> &Patterns {
+        &self.patterns
+    }
+}
+
+impl Deref for CompiledRule {
+    type Target = Rule;
+
+    fn deref(&self) -> &Rule {
+        &self.rule
+    }
+}
+
+fn collect_patterns(condition: &Condition, patterns: &mut Patterns) -> Result<(), regex::Error> {
+    match condition {
+        Condition::DescriptionMatches { pattern, case_insensitive }
+        | Condition::RemoteNameMatches { pattern, case_insensitive }
+        | Condition::KidOrMessageMatches { pattern, case_insensitive } => patterns.compile(pattern, *case_insensitive),
+        Condition::Aggregate(aggregate) => match &aggregate.filter {
+            Some(filter) => collect_patterns(filter, patterns),
+            None => Ok(()),
+        },
+        Condition::And { conditions } | Condition::Or { conditions } => {
+            conditions.iter().try_for_each(|c| collect_patterns(c, patterns))
+        }
+        Condition::Not { condition } => collect_patterns(condition, patterns),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{FireOn, RuleLimits, RuleTrigger};
+
+    fn rule(conditions: Vec<Condition>) -> Rule {
+        Rule {
+            id: "rule-1".to_string(),
+            name: "Groceries".to_string(),
+            description: None,
+            enabled: true,
+            trigger: RuleTrigger::Transaction,
+            trigger_account_key: "checking".to_string(),
+            fire_on: FireOn::default(),
+            include_self_transfers: false,
+            limits: RuleLimits::default(),
+            priority: 0,
+            stop_processing: false,
+            group: None,
+            requires_approval: false,
+            conditions,
+            actions: vec![],
+            created_at: 0,
+            updated_at: 0,
+            activated_at: 0,
+            next_run_at: None,
+        }
+    }
+
+    #[test]
+    fn test_compile_collects_nested_patterns() {
+        let compiled = CompiledRule::compile(rule(vec![Condition::Or {
+            conditions: vec![
+                Condition::DescriptionMatches { pattern: "rema".to_string(), case_insensitive: true },
+                Condition::Not {
+                    condition: Box::new(Condition::RemoteNameMatches {
+                        pattern: "^KIWI".to_string(),
+                        case_insensitive: false,
+                    }),
+                },
+            ],
+        }]))
+        .unwrap();
+
+        assert!(compiled.patterns().get("rema", true).unwrap().is_match("REMA 1000"));
+        assert!(compiled.patterns().get("rema", false).is_none());
+        assert!(compiled.patterns().get("^KIWI", false).is_some());
+        assert_eq!(compiled.name, "Groceries");
+    }
+
+    #[test]
+    fn test_compile_error_is_reported() {
+        let result = CompiledRule::compile(rule(vec![Condition::DescriptionMatches {
+            pattern: "(unclosed".to_string(),
+            case_insensitive: false,
+        }]));
+        assert!(result.unwrap_err().contains("invalid pattern"));
+    }
+}
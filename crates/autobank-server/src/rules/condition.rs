@@ -1,12 +1,45 @@
 //! Condition evaluation logic.
 
-use super::types::{AmountSpec, Condition};
+use super::context::EvalContext;
+use super::schedule::SCHEDULE_TIMEZONE;
+use super::types::{Action, AmountSpec, BalanceKind, Condition, Rule};
+use chrono::{DateTime, Datelike, Months, NaiveDate};
+use chrono_tz::Tz;
 use regex::Regex;
+use sb1_api::Money;
 use sb1_api::models::Transaction;
+use std::cmp::Ordering;
 
 impl Condition {
-    /// Evaluate this condition against a transaction.
-    pub fn evaluate(&self, tx: &Transaction) -> bool {
+    /// Evaluate this condition in `ctx`.
+    ///
+    /// Conditions on the transaction never match on scheduled runs.
+    pub fn evaluate(&self, ctx: &EvalContext) -> bool {
+        match self {
+            Condition::AccountBalanceBelow { account, value, balance } => ctx
+                .balance(account, *balance)
+                .is_some_and(|b| b < value.with_currency(b.currency())),
+
+            Condition::AccountBalanceAbove { account, value, balance } => ctx
+                .balance(account, *balance)
+                .is_some_and(|b| b > value.with_currency(b.currency())),
+
+            Condition::Aggregate(aggregate) => aggregate.evaluate(ctx),
+
+            Condition::And { conditions } => conditions.iter().all(|c| c.evaluate(ctx)),
+
+            Condition::Or { conditions } => conditions.iter().any(|c| c.evaluate(ctx)),
+
+            Condition::Not { condition } => !condition.evaluate(ctx),
+
+            _ => ctx.tx.is_some_and(|tx| self.matches_transaction(tx, ctx)),
+        }
+    }
+
+    /// Evaluate a condition on the transaction itself. Amounts are compared
+    /// in the transaction's currency.
+    fn matches_transaction(&self, tx: &Transaction, ctx: &EvalContext) -> bool {
+        let bind = |amount: &Money| amount.with_currency(tx.amount.currency());
         match self {
             Condition::DescriptionMatches { pattern, case_insensitive } => {
                 let description = tx
@@ -15,61 +48,205 @@ impl Condition {
                     .or(tx.description.as_deref())
                     .unwrap_or("");
 
-                let regex_pattern = if *case_insensitive {
-                    format!("(?i){}", pattern)
-                } else {
-                    pattern.clone()
-                };
-
-                Regex::new(&regex_pattern)
-                    .map(|re| re.is_match(description))
-                    .unwrap_or(false)
+                ctx.regex_matches(pattern, *case_insensitive, description)
             }
 
-            Condition::AmountGreaterThan { value } => tx.amount > *value,
+            Condition::AmountGreaterThan { value } => tx.amount > bind(value),
 
-            Condition::AmountLessThan { value } => tx.amount < *value,
+            Condition::AmountLessThan { value } => tx.amount < bind(value),
 
-            Condition::AmountBetween { min, max } => tx.amount >= *min && tx.amount <= *max,
+            Condition::AmountBetween { min, max } => tx.amount >= bind(min) && tx.amount <= bind(max),
 
-            Condition::AmountEquals { value, tolerance } => (tx.amount - value).abs() <= *tolerance,
+            Condition::AmountEquals { value, tolerance } => tx
+                .amount
+                .checked_sub(bind(value))
+                .is_some_and(|diff| diff.abs().ore() <= tolerance.ore()),
 
             Condition::TransactionType { type_code } => tx.type_code == *type_code,
 
-            Condition::IsSettled => tx.booking_status == "BOOKED",
+            Condition::TransactionSource { source } => tx.source == *source,
+
+            Condition::Currency { currency } => tx.amount.currency() == *currency,
+
+            Condition::RemoteAccountNumber { number } => tx
+                .remote_account_number
+                .as_deref()
+                .is_some_and(|remote| account_digits(remote) == account_digits(number)),
+
+            Condition::RemoteNameMatches { pattern, case_insensitive } => tx
+                .remote_account_name
+                .as_deref()
+                .is_some_and(|name| ctx.regex_matches(pattern, *case_insensitive, name)),
 
-            Condition::And { conditions } => conditions.iter().all(|c| c.evaluate(tx)),
+            Condition::KidOrMessageMatches { pattern, case_insensitive } => tx
+                .kid_or_message
+                .as_deref()
+                .is_some_and(|message| ctx.regex_matches(pattern, *case_insensitive, message)),
+
+            Condition::KidOrMessageEquals { value } => tx
+                .kid_or_message
+                .as_deref()
+                .is_some_and(|message| message.trim() == value.trim()),
+
+            Condition::IsSettled => tx.booking_status.is_booked(),
+
+            Condition::Weekday { days, timezone } => {
+                local_date(tx, timezone.as_deref()).is_some_and(|t| days.contains(&t.weekday()))
+            }
 
-            Condition::Or { conditions } => conditions.iter().any(|c| c.evaluate(tx)),
+            Condition::DayOfMonthBetween { from, to, timezone } => local_date(tx, timezone.as_deref())
+                .is_some_and(|t| {
+                    let day = t.day();
+                    if from <= to {
+                        (*from..=*to).contains(&day)
+                    } else {
+                        day >= *from || day <= *to
+                    }
+                }),
+
+            Condition::TimeOfDayBetween { from, to, timezone } => local_date(tx, timezone.as_deref())
+                .is_some_and(|t| {
+                    let time = t.time();
+                    if from <= to {
+                        time >= *from && time < *to
+                    } else {
+                        time >= *from || time < *to
+                    }
+                }),
+
+            Condition::NearMonthEnd { days, timezone } => local_date(tx, timezone.as_deref())
+                .and_then(|t| days_until_month_end(t.date_naive()))
+                .is_some_and(|remaining| remaining <= *days),
+
+            _ => false,
+        }
+    }
+}
 
-            Condition::Not { condition } => !condition.evaluate(tx),
+impl EvalContext<'_> {
+    /// Whether `text` matches `pattern`, using the rule's compiled pattern
+    /// if there is one. Invalid patterns never match.
+    fn regex_matches(&self, pattern: &str, case_insensitive: bool, text: &str) -> bool {
+        if let Some(re) = self.patterns.and_then(|p| p.get(pattern, case_insensitive)) {
+            return re.is_match(text);
         }
+
+        let regex_pattern = if case_insensitive {
+            format!("(?i){}", pattern)
+        } else {
+            pattern.to_string()
+        };
+
+        Regex::new(&regex_pattern)
+            .map(|re| re.is_match(text))
+            .unwrap_or(false)
+    }
+}
+
+/// The digits of an account number, without formatting.
+fn account_digits(number: &str) -> String {
+    number.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// The transaction date in `timezone`, or the schedule time zone if `None`.
+/// `None` if the time zone is unknown.
+pub(super) fn local_date(tx: &Transaction, timezone: Option<&str>) -> Option<DateTime<Tz>> {
+    let tz = match timezone {
+        Some(name) => name.parse::<Tz>().ok()?,
+        None => SCHEDULE_TIMEZONE,
+    };
+    DateTime::from_timestamp_millis(tx.date).map(|t| t.with_timezone(&tz))
+}
+
+/// Days from `date` to the last day of its month.
+pub(super) fn days_until_month_end(date: NaiveDate) -> Option<u32> {
+    let first = date.with_day(1)?;
+    let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+    Some(last.day() - date.day())
+}
+
+impl Rule {
+    /// Total amount the rule's actions would move in `ctx`. Actions with
+    /// nothing to transfer, or in another currency, are not counted.
+    pub fn transfer_total(&self, ctx: &EvalContext) -> Money {
+        self.actions
+            .iter()
+            .map(|action| match action {
+                Action::Transfer { amount, .. } => amount.calculate(ctx),
+            })
+            .filter(|amount| amount.ore() > 0)
+            .fold(ctx.zero(), |total, amount| total.checked_add(amount).unwrap_or(total))
     }
 }
 
 impl AmountSpec {
-    /// Calculate the amount for a transfer based on the transaction.
-    pub fn calculate(&self, tx: &Transaction) -> f64 {
+    /// Calculate the amount for a transfer in `ctx`.
+    ///
+    /// Amounts in the spec take the currency of the transaction, or of the
+    /// balance they are compared to. Amounts based on the transaction are
+    /// zero on scheduled runs. Amounts comparing different currencies (in
+    /// `Min`/`Max`) are treated as equal. The result may be zero or
+    /// negative, in which case nothing is transferred.
+    pub fn calculate(&self, ctx: &EvalContext) -> Money {
+        let zero = ctx.zero();
         match self {
-            AmountSpec::Fixed { value } => *value,
+            AmountSpec::Fixed { value } => ctx.bind(*value),
 
-            AmountSpec::TransactionAmount => tx.amount,
+            AmountSpec::TransactionAmount => ctx.tx.map_or(zero, |tx| tx.amount),
 
-            AmountSpec::TransactionAmountAbs => tx.amount.abs(),
+            AmountSpec::TransactionAmountAbs => ctx.tx.map_or(zero, |tx| tx.amount.abs()),
 
-            AmountSpec::Percentage { of_transaction } => tx.amount.abs() * (of_transaction / 100.0),
+            AmountSpec::Percentage { of_transaction } => {
+                ctx.tx.map_or(zero, |tx| tx.amount.abs().percentage(*of_transaction))
+            }
 
             AmountSpec::Min { specs } => specs
                 .iter()
-                .map(|s| s.calculate(tx))
-                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .unwrap_or(0.0),
+                .map(|s| s.calculate(ctx))
+                .min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .unwrap_or(zero),
 
             AmountSpec::Max { specs } => specs
                 .iter()
-                .map(|s| s.calculate(tx))
-                .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .unwrap_or(0.0),
+                .map(|s| s.calculate(ctx))
+                .max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .unwrap_or(zero),
+
+            AmountSpec::RoundUp { to } => ctx.tx.map_or(zero, |tx| {
+                let amount = tx.amount.abs();
+                if to.ore() <= 0 {
+                    return zero;
+                }
+                match amount.ore() % to.ore() {
+                    0 => zero,
+                    remainder => Money::new(to.ore() - remainder, amount.currency()),
+                }
+            }),
+
+            AmountSpec::AccountBalance { account, balance } => ctx.balance(account, *balance).unwrap_or(zero),
+
+            AmountSpec::ExcessAbove { account, threshold } => ctx
+                .balance(account, BalanceKind::Available)
+                .and_then(|balance| balance.checked_sub(threshold.with_currency(balance.currency())))
+                .unwrap_or(zero),
+
+            AmountSpec::TopUpTo { account, target } => ctx
+                .balance(account, BalanceKind::Available)
+                .and_then(|balance| target.with_currency(balance.currency()).checked_sub(balance))
+                .unwrap_or(zero),
+
+            AmountSpec::Clamp { spec, min, max } => {
+                let amount = spec.calculate(ctx);
+                let bind = |limit: &Money| limit.with_currency(amount.currency());
+                let amount = match min {
+                    Some(min) if amount < bind(min) => bind(min),
+                    _ => amount,
+                };
+                match max {
+                    Some(max) if amount > bind(max) => bind(max),
+                    _ => amount,
+                }
+            }
         }
     }
 }
@@ -77,9 +254,14 @@ impl AmountSpec {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sb1_api::models::{AccountNumber, ClassificationInput};
+    use crate::rules::AccountRef;
+    use sb1_api::models::{Account, AccountNumber, BookingStatus, ClassificationInput, TransactionSource, TransactionType};
+
+    fn ctx(tx: &Transaction) -> EvalContext<'_> {
+        EvalContext::new(tx, &[], "acc-1")
+    }
 
-    fn create_test_transaction(amount: f64, description: &str, booking_status: &str) -> Transaction {
+    fn create_test_transaction(amount: Money, description: &str, booking_status: BookingStatus) -> Transaction {
         Transaction {
             id: "tx-1".to_string(),
             non_unique_id: "tx-nu-1".to_string(),
@@ -93,13 +275,13 @@ mod tests {
             amount,
             date: 1707753600000,
             interest_date: None,
-            type_code: "VISA".to_string(),
+            type_code: TransactionType::Visa,
             type_text: "Card payment".to_string(),
             currency_code: "NOK".to_string(),
             can_show_details: true,
-            source: "VISA".to_string(),
+            source: TransactionSource::Visa,
             is_confidential: false,
-            booking_status: booking_status.to_string(),
+            booking_status,
             account_name: "Checking".to_string(),
             account_key: "acc-1".to_string(),
             account_currency: "NOK".to_string(),
@@ -119,73 +301,266 @@ mod tests {
 
     #[test]
     fn test_description_matches() {
-        let tx = create_test_transaction(-149.0, "NETFLIX.COM payment", "BOOKED");
+        let tx = create_test_transaction(Money::nok(-14900), "NETFLIX.COM payment", BookingStatus::Booked);
 
         let condition = Condition::DescriptionMatches {
             pattern: "netflix".to_string(),
             case_insensitive: true,
         };
-        assert!(condition.evaluate(&tx));
+        assert!(condition.evaluate(&ctx(&tx)));
 
         let condition_case_sensitive = Condition::DescriptionMatches {
             pattern: "netflix".to_string(),
             case_insensitive: false,
         };
-        assert!(!condition_case_sensitive.evaluate(&tx));
+        assert!(!condition_case_sensitive.evaluate(&ctx(&tx)));
     }
 
     #[test]
     fn test_amount_conditions() {
-        let tx = create_test_transaction(-149.0, "Test", "BOOKED");
+        let tx = create_test_transaction(Money::nok(-14900), "Test", BookingStatus::Booked);
 
-        assert!(Condition::AmountLessThan { value: 0.0 }.evaluate(&tx));
-        assert!(Condition::AmountGreaterThan { value: -200.0 }.evaluate(&tx));
-        assert!(Condition::AmountBetween { min: -200.0, max: -100.0 }.evaluate(&tx));
-        assert!(Condition::AmountEquals { value: -149.0, tolerance: 0.01 }.evaluate(&tx));
+        assert!(Condition::AmountLessThan { value: Money::nok(0) }.evaluate(&ctx(&tx)));
+        assert!(Condition::AmountGreaterThan { value: Money::nok(-20000) }.evaluate(&ctx(&tx)));
+        assert!(Condition::AmountBetween { min: Money::nok(-20000), max: Money::nok(-10000) }.evaluate(&ctx(&tx)));
+        assert!(Condition::AmountEquals { value: Money::nok(-14900), tolerance: Money::nok(1) }.evaluate(&ctx(&tx)));
+        assert!(!Condition::AmountEquals { value: Money::nok(-14902), tolerance: Money::nok(1) }.evaluate(&ctx(&tx)));
     }
 
     #[test]
     fn test_is_settled() {
-        let booked_tx = create_test_transaction(-100.0, "Test", "BOOKED");
-        let pending_tx = create_test_transaction(-100.0, "Test", "PENDING");
+        let booked_tx = create_test_transaction(Money::nok(-10000), "Test", BookingStatus::Booked);
+        let pending_tx = create_test_transaction(Money::nok(-10000), "Test", BookingStatus::Pending);
 
-        assert!(Condition::IsSettled.evaluate(&booked_tx));
-        assert!(!Condition::IsSettled.evaluate(&pending_tx));
+        assert!(Condition::IsSettled.evaluate(&ctx(&booked_tx)));
+        assert!(!Condition::IsSettled.evaluate(&ctx(&pending_tx)));
     }
 
     #[test]
     fn test_logical_operators() {
-        let tx = create_test_transaction(-149.0, "Netflix", "BOOKED");
+        let tx = create_test_transaction(Money::nok(-14900), "Netflix", BookingStatus::Booked);
 
         let and_condition = Condition::And {
             conditions: vec![
-                Condition::AmountLessThan { value: 0.0 },
+                Condition::AmountLessThan { value: Money::nok(0) },
                 Condition::IsSettled,
             ],
         };
-        assert!(and_condition.evaluate(&tx));
+        assert!(and_condition.evaluate(&ctx(&tx)));
 
         let or_condition = Condition::Or {
             conditions: vec![
-                Condition::AmountGreaterThan { value: 1000.0 },
+                Condition::AmountGreaterThan { value: Money::nok(100000) },
                 Condition::IsSettled,
             ],
         };
-        assert!(or_condition.evaluate(&tx));
+        assert!(or_condition.evaluate(&ctx(&tx)));
 
         let not_condition = Condition::Not {
-            condition: Box::new(Condition::AmountGreaterThan { value: 0.0 }),
+            condition: Box::new(Condition::AmountGreaterThan { value: Money::nok(0) }),
         };
-        assert!(not_condition.evaluate(&tx));
+        assert!(not_condition.evaluate(&ctx(&tx)));
     }
 
     #[test]
     fn test_amount_spec_calculation() {
-        let tx = create_test_transaction(-149.0, "Test", "BOOKED");
+        let tx = create_test_transaction(Money::nok(-14900), "Test", BookingStatus::Booked);
+
+        assert_eq!(AmountSpec::Fixed { value: Money::nok(10000) }.calculate(&ctx(&tx)), Money::nok(10000));
+        assert_eq!(AmountSpec::TransactionAmount.calculate(&ctx(&tx)), Money::nok(-14900));
+        assert_eq!(AmountSpec::TransactionAmountAbs.calculate(&ctx(&tx)), Money::nok(14900));
+        assert_eq!(AmountSpec::Percentage { of_transaction: 10.0 }.calculate(&ctx(&tx)), Money::nok(1490));
+        assert_eq!(AmountSpec::RoundUp { to: Money::nok(1000) }.calculate(&ctx(&tx)), Money::nok(100));
+        assert_eq!(AmountSpec::RoundUp { to: Money::nok(100) }.calculate(&ctx(&tx)), Money::nok(0));
+    }
+
+    fn savings_account(balance: Money, available_balance: Money) -> Account {
+        Account {
+            key: "savings".to_string(),
+            account_number: "12345678902".to_string(),
+            balance,
+            available_balance,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn test_account_balance_conditions() {
+        let tx = create_test_transaction(Money::nok(-14900), "Test", BookingStatus::Booked);
+        let accounts = [savings_account(Money::nok(600000), Money::nok(400000))];
+        let ctx = EvalContext::new(&tx, &accounts, "acc-1");
+        let savings = AccountRef::ByKey { key: "savings".to_string() };
+
+        let above = |value, balance| Condition::AccountBalanceAbove { account: savings.clone(), value, balance };
+        assert!(above(Money::nok(500000), BalanceKind::Booked).evaluate(&ctx));
+        assert!(!above(Money::nok(500000), BalanceKind::Available).evaluate(&ctx));
+
+        let below = Condition::AccountBalanceBelow {
+            account: savings.clone(),
+            value: Money::nok(500000),
+            balance: BalanceKind::Available,
+        };
+        assert!(below.evaluate(&ctx));
+
+        let missing = Condition::AccountBalanceBelow {
+            account: AccountRef::TriggerAccount,
+            value: Money::nok(100000000),
+            balance: BalanceKind::Available,
+        };
+        assert!(!missing.evaluate(&ctx));
+    }
+
+    #[test]
+    fn test_amount_spec_account_balance() {
+        let tx = create_test_transaction(Money::nok(-14900), "Test", BookingStatus::Booked);
+        let accounts = [savings_account(Money::nok(600000), Money::nok(10000))];
+        let ctx = EvalContext::new(&tx, &accounts, "acc-1");
+
+        let covered = AmountSpec::Min {
+            specs: vec![
+                AmountSpec::TransactionAmountAbs,
+                AmountSpec::AccountBalance {
+                    account: AccountRef::ByKey { key: "savings".to_string() },
+                    balance: BalanceKind::Available,
+                },
+            ],
+        };
+        assert_eq!(covered.calculate(&ctx), Money::nok(10000));
+
+        let missing = AmountSpec::AccountBalance { account: AccountRef::TriggerAccount, balance: BalanceKind::Booked };
+        assert_eq!(missing.calculate(&ctx), Money::nok(0));
+    }
+
+    #[test]
+    fn test_scheduled_run_has_no_transaction() {
+        let accounts = [savings_account(Money::nok(600000), Money::nok(400000))];
+        let ctx = EvalContext::scheduled(0, &accounts, "savings");
+
+        assert!(!Condition::IsSettled.evaluate(&ctx));
+        assert!(Condition::AccountBalanceAbove {
+            account: AccountRef::TriggerAccount,
+            value: Money::nok(100000),
+            balance: BalanceKind::Available,
+        }
+        .evaluate(&ctx));
+        assert_eq!(AmountSpec::TransactionAmountAbs.calculate(&ctx), Money::nok(0));
+    }
+
+    #[test]
+    fn test_balance_relative_amount_specs() {
+        let tx = create_test_transaction(Money::nok(-14900), "Test", BookingStatus::Booked);
+        let accounts = [savings_account(Money::nok(1500000), Money::nok(1200000))];
+        let ctx = EvalContext::new(&tx, &accounts, "acc-1");
+        let savings = AccountRef::ByKey { key: "savings".to_string() };
+
+        let excess = AmountSpec::ExcessAbove { account: savings.clone(), threshold: Money::nok(1000000) };
+        assert_eq!(excess.calculate(&ctx), Money::nok(200000));
+
+        let top_up = AmountSpec::TopUpTo { account: savings.clone(), target: Money::nok(1000000) };
+        assert_eq!(top_up.calculate(&ctx), Money::nok(-200000));
+
+        let clamped = AmountSpec::Clamp {
+            spec: Box::new(excess.clone()),
+            min: None,
+            max: Some(Money::nok(50000)),
+        };
+        assert_eq!(clamped.calculate(&ctx), Money::nok(50000));
 
-        assert_eq!(AmountSpec::Fixed { value: 100.0 }.calculate(&tx), 100.0);
-        assert_eq!(AmountSpec::TransactionAmount.calculate(&tx), -149.0);
-        assert_eq!(AmountSpec::TransactionAmountAbs.calculate(&tx), 149.0);
-        assert!((AmountSpec::Percentage { of_transaction: 10.0 }.calculate(&tx) - 14.9).abs() < 0.01);
+        let raised = AmountSpec::Clamp {
+            spec: Box::new(AmountSpec::TransactionAmountAbs),
+            min: Some(Money::nok(20000)),
+            max: None,
+        };
+        assert_eq!(raised.calculate(&ctx), Money::nok(20000));
+
+        let missing = AmountSpec::ExcessAbove { account: AccountRef::TriggerAccount, threshold: Money::nok(0) };
+        assert_eq!(missing.calculate(&ctx), Money::nok(0));
+    }
+
+    #[test]
+    fn test_date_conditions_use_local_time() {
+        use chrono::{NaiveTime, TimeZone, Weekday};
+
+        // 00:30 on Saturday 31 January in Oslo, still Friday 30 January in UTC
+        let mut tx = create_test_transaction(Money::nok(-14900), "Kiosk", BookingStatus::Booked);
+        tx.date = SCHEDULE_TIMEZONE
+            .with_ymd_and_hms(2026, 1, 31, 0, 30, 0)
+            .unwrap()
+            .timestamp_millis();
+        let ctx = ctx(&tx);
+
+        let saturday = Condition::Weekday { days: vec![Weekday::Sat], timezone: None };
+        assert!(saturday.evaluate(&ctx));
+        let saturday_utc = Condition::Weekday { days: vec![Weekday::Sat], timezone: Some("UTC".to_string()) };
+        assert!(!saturday_utc.evaluate(&ctx));
+        let unknown_zone = Condition::Weekday { days: vec![Weekday::Sat], timezone: Some("Mars/Olympus".to_string()) };
+        assert!(!unknown_zone.evaluate(&ctx));
+
+        let around_month_start = Condition::DayOfMonthBetween { from: 25, to: 5, timezone: None };
+        assert!(around_month_start.evaluate(&ctx));
+        let mid_month = Condition::DayOfMonthBetween { from: 10, to: 20, timezone: None };
+        assert!(!mid_month.evaluate(&ctx));
+
+        let night = Condition::TimeOfDayBetween {
+            from: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+            to: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            timezone: None,
+        };
+        assert!(night.evaluate(&ctx));
+        let office_hours = Condition::TimeOfDayBetween {
+            from: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            to: NaiveTime::from_hms_opt(16, 0, 0).unwrap(),
+            timezone: None,
+        };
+        assert!(!office_hours.evaluate(&ctx));
+
+        assert!(Condition::NearMonthEnd { days: 0, timezone: None }.evaluate(&ctx));
+        assert!(!Condition::NearMonthEnd { days: 0, timezone: Some("UTC".to_string()) }.evaluate(&ctx));
+        assert!(Condition::NearMonthEnd { days: 1, timezone: Some("UTC".to_string()) }.evaluate(&ctx));
+    }
+
+    #[test]
+    fn test_date_condition_serde() {
+        let json = r#"{"type":"time_of_day_between","from":"22:00","to":"06:00"}"#;
+        let condition: Condition = serde_json::from_str(json).unwrap();
+        assert!(matches!(condition, Condition::TimeOfDayBetween { timezone: None, .. }));
+
+        let json = r#"{"type":"weekday","days":["mon","Friday"],"timezone":"UTC"}"#;
+        let condition: Condition = serde_json::from_str(json).unwrap();
+        assert!(matches!(condition, Condition::Weekday { ref days, .. } if days.len() == 2));
+    }
+
+    #[test]
+    fn test_counterparty_conditions() {
+        let mut tx = create_test_transaction(Money::nok(850000), "Lønn", BookingStatus::Booked);
+        tx.source = TransactionSource::Transfer;
+        tx.remote_account_number = Some("12345678903".to_string());
+        tx.remote_account_name = Some("ACME AS".to_string());
+        tx.kid_or_message = Some(" Rent ".to_string());
+        let salary_ctx = ctx(&tx);
+
+        let employer = Condition::RemoteAccountNumber { number: "1234.56.78903".to_string() };
+        assert!(employer.evaluate(&salary_ctx));
+        let other = Condition::RemoteAccountNumber { number: "1234.56.78904".to_string() };
+        assert!(!other.evaluate(&salary_ctx));
+
+        let name = Condition::RemoteNameMatches { pattern: "^acme".to_string(), case_insensitive: true };
+        assert!(name.evaluate(&salary_ctx));
+        let name = Condition::RemoteNameMatches { pattern: "^acme".to_string(), case_insensitive: false };
+        assert!(!name.evaluate(&salary_ctx));
+
+        let message = Condition::KidOrMessageMatches { pattern: "(?i)rent|husleie".to_string(), case_insensitive: false };
+        assert!(message.evaluate(&salary_ctx));
+        assert!(Condition::KidOrMessageEquals { value: "Rent".to_string() }.evaluate(&salary_ctx));
+        assert!(!Condition::KidOrMessageEquals { value: "rent".to_string() }.evaluate(&salary_ctx));
+
+        assert!(Condition::TransactionSource { source: TransactionSource::Transfer }.evaluate(&salary_ctx));
+        assert!(!Condition::TransactionSource { source: TransactionSource::Card }.evaluate(&salary_ctx));
+        assert!(Condition::Currency { currency: sb1_api::Currency::NOK }.evaluate(&salary_ctx));
+
+        // Missing counterparty details never match
+        let tx = create_test_transaction(Money::nok(-14900), "Kiosk", BookingStatus::Booked);
+        assert!(!employer.evaluate(&ctx(&tx)));
+        assert!(!Condition::KidOrMessageMatches { pattern: ".*".to_string(), case_insensitive: false }.evaluate(&ctx(&tx)));
     }
 }
@@ -0,0 +1,143 @@
+//! Data available to conditions and amount specs during evaluation.
+
+use super::compiled::Patterns;
+use super::types::{AccountRef, BalanceKind};
+use sb1_api::{Currency, Money};
+use sb1_api::models::{Account, Transaction};
+
+/// What a rule is being run for, together with the accounts fetched for
+/// the current poll.
+///
+/// Balances are a snapshot from the start of the poll; transfers made while
+/// evaluating are not reflected until the next poll.
+#[derive(Debug, Clone, Copy)]
+pub struct EvalContext<'a> {
+    /// The triggering transaction; `None` on scheduled runs.
+    pub tx: Option<&'a Transaction>,
+    /// The scheduled run being made (Unix seconds).
+    pub scheduled_for: Option<i64>,
+    pub accounts: &'a [Account],
+    /// Key of the account the rule is watching.
+    pub trigger_account_key: &'a str,
+    /// Stored transactions for aggregate conditions; empty unless set with
+    /// [`EvalContext::with_history`].
+    pub history: &'a [Transaction],
+    /// Compiled regex patterns of the rule being evaluated; patterns not
+    /// found here are compiled when matched.
+    pub patterns: Option<&'a Patterns>,
+}
+
+impl<'a> EvalContext<'a> {
+    /// Context for a rule triggered by a transaction.
+    pub fn new(tx: &'a Transaction, accounts: &'a [Account], trigger_account_key: &'a str) -> Self {
+        Self {
+            tx: Some(tx),
+            scheduled_for: None,
+            accounts,
+            trigger_account_key,
+            history: &[],
+            patterns: None,
+        }
+    }
+
+    /// Context for the run of a scheduled rule due at `scheduled_for`.
+    pub fn scheduled(scheduled_for: i64, accounts: &'a [Account], trigger_account_key: &'a str) -> Self {
+        Self {
+            tx: None,
+            scheduled_for: Some(scheduled_for),
+            accounts,
+            trigger_account_key,
+            history: &[],
+            patterns: None,
+        }
+    }
+
+    /// Context for work not tied to a transaction or scheduled run, such as
+    /// flushing accumulated amounts.
+    pub fn without_trigger(accounts: &'a [Account], trigger_account_key: &'a str) -> Self {
+        Self {
+            tx: None,
+            scheduled_for: None,
+            accounts,
+            trigger_account_key,
+            history: &[],
+            patterns: None,
+        }
+    }
+
+    /// This context with stored transactions for aggregate conditions.
+    pub fn with_history(self, history: &'a [Transaction]) -> Self {
+        Self { history, ..self }
+    }
+
+    /// This context with a compiled rule's regex patterns.
+    pub fn with_patterns(self, patterns: &'a Patterns) -> Self {
+        Self {
+            patterns: Some(patterns),
+            ..self
+        }
+    }
+
+    /// The time the rule is evaluated as of (Unix millis): the transaction
+    /// date, the scheduled run, or now.
+    pub fn reference_time_millis(&self) -> i64 {
+        match (self.tx, self.scheduled_for) {
+            (Some(tx), _) => tx.date,
+            (None, Some(at)) => at * 1000,
+            (None, None) => chrono::Utc::now().timestamp_millis(),
+        }
+    }
+
+    /// Identifies what the rule runs for: the transaction id, or
+    /// `schedule:<timestamp>` for scheduled runs.
+    pub fn source_id(&self) -> String {
+        match (self.tx, self.scheduled_for) {
+            (Some(tx), _) => tx.id.clone(),
+            (None, Some(at)) => format!("schedule:{}", at),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Zero in the currency amounts are calculated in: the transaction's, or
+    /// the trigger account's on scheduled runs.
+    pub fn zero(&self) -> Money {
+        let currency = match self.tx {
+            Some(tx) => tx.amount.currency(),
+            None => self
+                .account(&AccountRef::TriggerAccount)
+                .map_or(Currency::NOK, |a| a.available_balance.currency()),
+        };
+        Money::zero(currency)
+    }
+
+    /// An amount from the rule definition in the currency amounts are
+    /// calculated in. Rule amounts are written without a currency and
+    /// deserialize as NOK.
+    pub fn bind(&self, amount: Money) -> Money {
+        amount.with_currency(self.zero().currency())
+    }
+
+    /// Find the account an account reference points to.
+    pub fn account(&self, account_ref: &AccountRef) -> Option<&'a Account> {
+        match account_ref {
+            AccountRef::TriggerAccount => self.accounts.iter().find(|a| a.key == self.trigger_account_key),
+            AccountRef::ByKey { key } => self.accounts.iter().find(|a| a.key == *key),
+            AccountRef::ByNumber { number } => self.accounts.iter().find(|a| a.account_number == *number),
+        }
+    }
+
+    /// The balance of a referenced account, or `None` if it was not found.
+    pub fn balance(&self, account_ref: &AccountRef, kind: BalanceKind) -> Option<Money> {
+        self.account(account_ref).map(|account| kind.of(account))
+    }
+}
+
+impl BalanceKind {
+    /// Read this balance from an account.
+    pub fn of(&self, account: &Account) -> Money {
+        match self {
+            BalanceKind::Booked => account.balance,
+            BalanceKind::Available => account.available_balance,
+        }
+    }
+}
@@ -1,14 +1,56 @@
 //! Rule engine for evaluating and executing rules.
 
-use super::types::{AccountRef, Action, AmountSpec, ProcessingDecision, Rule, RuleExecution, RuleTransactionLog, TrackedTransaction};
+use super::backtest::{self, BacktestReport, BacktestSource};
+use super::compiled::CompiledRule;
+use super::context::EvalContext;
+use super::limits::{RuleLimits, TransferLimits, TransferTotals};
+use super::schedule::{SCHEDULE_GRACE_SECS, SCHEDULE_TIMEZONE, Schedule, local_day_start};
+use super::types::{
+    AccountRef, Accumulate, Action, AmountSpec, FireOn, PendingAccumulation, ProcessingDecision, Rule, RuleExecution,
+    RuleTransactionLog, RuleTrigger, TrackedTransaction, TransferProposal,
+};
+use crate::audit::{AuditEntry, AuditEventType};
 use crate::db::Database;
-use sb1_api::models::{Account, CreateTransferDTO, Transaction};
-use sb1_api::BankApiClient;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use sb1_api::models::{Account, CreateTransferDTO, Transaction, TransactionQuery, TransactionType};
+use sb1_api::{BankApiClient, Money, RetryPolicy};
 use sha2::{Digest, Sha256};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// How far back to fetch transactions on each poll.
+///
+/// Long enough to see pending transactions settle; older history is only
+/// fetched on demand.
+const TRANSACTION_LOOKBACK_DAYS: i64 = 30;
+
+/// How long after a transfer its transactions may appear on the accounts.
+const SELF_TRANSFER_MATCH_DAYS: i64 = 5;
+
+/// How long a transfer proposal can be approved.
+const PROPOSAL_EXPIRY_SECS: i64 = 3 * 24 * 60 * 60;
+
+/// Retry policy for transfers the bank certainly did not carry out, as when
+/// the connection failed or the request was rate limited.
+const TRANSFER_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    base_delay: std::time::Duration::from_secs(60),
+    max_delay: std::time::Duration::from_secs(60 * 60),
+    jitter: false,
+};
+
+/// Settings key for the emergency stop flag.
+const EMERGENCY_STOP_KEY: &str = "emergency_stop";
+
+/// Settings key for the transfer limits.
+const TRANSFER_LIMITS_KEY: &str = "transfer_limits";
+
+/// Audit log actor for settings changed through the API, which has no
+/// notion of users.
+const CONFIG_ACTOR: &str = "api";
+
 /// Transaction fingerprint for change detection.
 pub struct TransactionFingerprint {
     pub transaction_id: String,
@@ -22,7 +64,8 @@ impl TransactionFingerprint {
             "{}|{}|{}|{}|{}",
             tx.id,
             tx.cleaned_description.as_deref().unwrap_or(""),
-            tx.amount,
+            // Decimal form keeps fingerprints stable across the switch to Money.
+            tx.amount.to_decimal(),
             tx.type_code,
             tx.booking_status
         );
@@ -38,36 +81,210 @@ impl TransactionFingerprint {
     }
 }
 
+/// A transfer worked out from a rule's action, ready to send or propose.
+struct PlannedTransfer {
+    rule_id: String,
+    transaction_id: Option<String>,
+    scheduled_for: Option<i64>,
+    action_index: usize,
+    idempotency_key: String,
+    amount: Money,
+    from_account: String,
+    to_account: String,
+    message: Option<String>,
+}
+
+impl From<&TransferProposal> for PlannedTransfer {
+    fn from(proposal: &TransferProposal) -> Self {
+        Self {
+            rule_id: proposal.rule_id.clone(),
+            transaction_id: proposal.transaction_id.clone(),
+            scheduled_for: proposal.scheduled_for,
+            action_index: proposal.action_index as usize,
+            idempotency_key: proposal.idempotency_key.clone(),
+            amount: proposal.amount,
+            from_account: proposal.from_account.clone(),
+            to_account: proposal.to_account.clone(),
+            message: proposal.message.clone(),
+        }
+    }
+}
+
+/// Enabled rules compiled for evaluation, by trigger account.
+type CompiledRules = HashMap<String, Vec<CompiledRule>>;
+
 /// Rule engine for evaluating and executing rules.
 pub struct RuleEngine {
     db: Database,
     bank_client: Arc<dyn BankApiClient>,
+    /// Enabled rules, loaded on the first poll after an invalidation.
+    rule_cache: RwLock<Option<Arc<CompiledRules>>>,
 }
 
 impl RuleEngine {
     /// Create a new rule engine.
     pub fn new(db: Database, bank_client: Arc<dyn BankApiClient>) -> Self {
-        Self { db, bank_client }
+        Self {
+            db,
+            bank_client,
+            rule_cache: RwLock::new(None),
+        }
+    }
+
+    /// Drop the compiled rules so the next poll reloads them. Call after
+    /// creating, changing or deleting a rule.
+    pub fn invalidate_rules(&self) {
+        *self.rule_cache.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// The enabled rules by trigger account, compiled. Rules that fail to
+    /// compile are logged and left out.
+    async fn compiled_rules(&self) -> Result<Arc<CompiledRules>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(rules) = self.rule_cache.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            return Ok(Arc::clone(rules));
+        }
+
+        let mut compiled = CompiledRules::new();
+        for (account_key, rules) in self.db.get_enabled_rules_by_account().await? {
+            let rules: Vec<_> = rules
+                .into_iter()
+                .filter_map(|rule| {
+                    let id = rule.id.clone();
+                    CompiledRule::compile(rule)
+                        .inspect_err(|e| error!("Rule {} not evaluated: {}", id, e))
+                        .ok()
+                })
+                .collect();
+            if !rules.is_empty() {
+                compiled.insert(account_key, rules);
+            }
+        }
+
+        let compiled = Arc::new(compiled);
+        *self.rule_cache.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::clone(&compiled));
+        Ok(compiled)
+    }
+
+    /// Returns true if the emergency stop is engaged.
+    pub async fn emergency_stop_engaged(&self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.db.get_setting(EMERGENCY_STOP_KEY).await?.unwrap_or(false))
+    }
+
+    /// Engage or release the emergency stop. While engaged, no transfers are
+    /// sent. The change is recorded in the audit log.
+    pub async fn set_emergency_stop(&self, engaged: bool) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if engaged {
+            warn!("Emergency stop engaged; all transfers are blocked");
+        } else {
+            info!("Emergency stop released");
+        }
+        self.db.set_setting(EMERGENCY_STOP_KEY, &engaged).await?;
+        self.log_config_changed(EMERGENCY_STOP_KEY, serde_json::json!({ "engaged": engaged })).await
+    }
+
+    /// Current transfer limits, or the defaults if none have been saved.
+    pub async fn transfer_limits(&self) -> Result<TransferLimits, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.db.get_setting(TRANSFER_LIMITS_KEY).await?.unwrap_or_default())
+    }
+
+    /// Save new transfer limits, recording the old and new limits in the
+    /// audit log.
+    pub async fn set_transfer_limits(&self, limits: &TransferLimits) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let previous = self.transfer_limits().await?;
+        self.db.set_setting(TRANSFER_LIMITS_KEY, limits).await?;
+        self.log_config_changed(
+            TRANSFER_LIMITS_KEY,
+            serde_json::json!({ "previous": previous, "limits": limits }),
+        )
+        .await
+    }
+
+    /// Record a change to the setting `key` in the audit log.
+    async fn log_config_changed(
+        &self,
+        key: &str,
+        details: serde_json::Value,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let entry = AuditEntry::new(AuditEventType::ConfigChanged, CONFIG_ACTOR, details).with_resource("setting", key);
+        Ok(self.db.log_audit(&entry).await?)
+    }
+
+    /// Returns the reason a transfer of `amount` must not be sent, if any.
+    /// `sent` is the number of transfers already sent by the poll, scheduled
+    /// run or backfill making this one, if any.
+    async fn check_transfer_allowed(
+        &self,
+        amount: Money,
+        sent: Option<u32>,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        if self.emergency_stop_engaged().await? {
+            return Ok(Some("Emergency stop is engaged".to_string()));
+        }
+
+        let (start_of_day, start_of_month) = period_starts();
+        let currency = amount.currency();
+
+        let totals = TransferTotals {
+            transfers_this_poll: sent,
+            today: self.db.sum_transferred_since(currency, start_of_day).await?,
+            this_month: self.db.sum_transferred_since(currency, start_of_month).await?,
+        };
+
+        Ok(self.transfer_limits().await?.check(amount, &totals))
+    }
+
+    /// Returns the rule limit that stops the rule from firing on `tx`, if any,
+    /// as a short kind and a description.
+    async fn check_rule_limits(
+        &self,
+        rule: &Rule,
+        ctx: &EvalContext<'_>,
+    ) -> Result<Option<(&'static str, String)>, Box<dyn std::error::Error + Send + Sync>> {
+        if rule.limits == RuleLimits::default() {
+            return Ok(None);
+        }
+
+        let currency = ctx.zero().currency();
+        let amount = rule.transfer_total(ctx);
+
+        let (start_of_day, start_of_month) = period_starts();
+        let history = self.db.rule_history(&rule.id, currency, start_of_day, start_of_month).await?;
+
+        Ok(rule.limits.check(amount, &history, Utc::now().timestamp()))
     }
 
     /// Evaluate all enabled rules against recent transactions.
     pub async fn evaluate_all(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let rules_by_account = self.db.get_enabled_rules_by_account().await?;
+        let rules_by_account = self.compiled_rules().await?;
+        if rules_by_account.is_empty() {
+            return Ok(());
+        }
+        let accounts = self.bank_client.get_accounts().await?.accounts;
+        let mut sent = 0;
 
-        for (account_key, rules) in rules_by_account {
+        for (account_key, rules) in rules_by_account.iter() {
             debug!("Processing {} rules for account {}", rules.len(), account_key);
+            let account_number = accounts.iter().find(|a| a.key == *account_key).map(|a| a.account_number.as_str());
+            if account_number.is_none() {
+                warn!("Account {} not found; own transfers on it will not be recognized", account_key);
+            }
 
-            let transactions = match self.bank_client.get_transactions(&account_key).await {
+            let query = TransactionQuery::since(Utc::now().date_naive() - Duration::days(TRANSACTION_LOOKBACK_DAYS));
+            let mut transactions = match self.bank_client.get_transactions(account_key, &query).await {
                 Ok(response) => response.transactions,
                 Err(e) => {
                     error!("Failed to fetch transactions for account {}: {}", account_key, e);
                     continue;
                 }
             };
+            // Oldest first, so aggregates over stored history see the earlier
+            // transactions of this poll
+            transactions.sort_by_key(|tx| tx.date);
 
             for tx in transactions {
                 let fingerprint = TransactionFingerprint::from_transaction(&tx);
-                let decision = self.check_processing_decision(&tx, &fingerprint).await?;
+                let previous = self.db.get_tracked_transaction(&tx.id).await?;
+                let decision = self.check_processing_decision(previous.as_ref(), &fingerprint);
 
                 match decision {
                     ProcessingDecision::Skip { reason } => {
@@ -79,10 +296,27 @@ impl RuleEngine {
                         continue;
                     }
                     ProcessingDecision::Process => {
-                        self.update_tracked_transaction(&tx, &fingerprint).await?;
+                        let tracked = self.update_tracked_transaction(&tx, &fingerprint, previous.as_ref()).await?;
+                        let own_execution = self.match_own_transfer(account_number, &tx).await?;
 
-                        for rule in &rules {
-                            if let Err(e) = self.evaluate_and_execute(rule, &tx, &fingerprint).await {
+                        for rule in rules {
+                            if let Some(execution_id) = &own_execution
+                                && !rule.include_self_transfers
+                            {
+                                debug!("Rule {} skipping transaction {}: created by execution {}", rule.id, tx.id, execution_id);
+                                continue;
+                            }
+                            if rule.predates_activation(&tx, tracked.first_seen_at) {
+                                debug!("Rule {} skipping transaction {}: predates activation", rule.id, tx.id);
+                                continue;
+                            }
+                            if let Some(other) = self.claimed_by(rule, rules, &tx.id).await? {
+                                debug!("Rule {} skipping transaction {}: matched by rule {}", rule.id, tx.id, other.id);
+                                continue;
+                            }
+                            if let Err(e) =
+                                self.evaluate_and_execute(rule, &tx, &tracked, &fingerprint, &accounts, &mut sent).await
+                            {
                                 error!("Error evaluating rule {} for transaction {}: {}", rule.id, tx.id, e);
                             }
                         }
@@ -94,218 +328,1109 @@ impl RuleEngine {
         Ok(())
     }
 
-    /// Check if a transaction should be processed.
-    async fn check_processing_decision(
+    /// The rule whose match on transaction `tx_id` keeps `rule` from running
+    /// on it, if any: an earlier rule in `rules` (the account's rules, in
+    /// evaluation order) that stops processing, or any other rule in the
+    /// same group.
+    ///
+    /// Matches are read from the processing log, so they hold across polls
+    /// and transaction versions.
+    async fn claimed_by<'r>(
         &self,
-        tx: &Transaction,
-        fingerprint: &TransactionFingerprint,
-    ) -> Result<ProcessingDecision, Box<dyn std::error::Error + Send + Sync>> {
-        let tracked = self.db.get_tracked_transaction(&tx.id).await?;
+        rule: &Rule,
+        rules: &'r [CompiledRule],
+        tx_id: &str,
+    ) -> Result<Option<&'r CompiledRule>, Box<dyn std::error::Error + Send + Sync>> {
+        let position = rules.iter().position(|r| r.id == rule.id);
+        for (index, other) in rules.iter().enumerate() {
+            if other.id == rule.id {
+                continue;
+            }
+            let earlier = position.is_none_or(|p| index < p);
+            let same_group = rule.group.is_some() && other.group == rule.group;
+            if ((earlier && other.stop_processing) || same_group) && self.db.has_matched(&other.id, tx_id).await? {
+                return Ok(Some(other));
+            }
+        }
+        Ok(None)
+    }
 
+    /// Check if a transaction should be processed, given its tracked record.
+    fn check_processing_decision(
+        &self,
+        tracked: Option<&TrackedTransaction>,
+        fingerprint: &TransactionFingerprint,
+    ) -> ProcessingDecision {
         match tracked {
             None => {
                 // New transaction
-                Ok(ProcessingDecision::Process)
+                ProcessingDecision::Process
             }
             Some(existing) => {
                 if existing.fingerprint == fingerprint.fingerprint {
                     // Same version, already processed
-                    Ok(ProcessingDecision::Skip {
+                    ProcessingDecision::Skip {
                         reason: "Already processed this version".to_string(),
-                    })
+                    }
                 } else {
                     // Transaction changed, re-evaluate
-                    Ok(ProcessingDecision::Process)
+                    ProcessingDecision::Process
                 }
             }
         }
     }
 
-    /// Update the tracked transaction record.
+    /// Match a transaction to the execution whose transfer created it, if any.
+    ///
+    /// A transaction carrying an execution's marker (see
+    /// [`RuleExecution::marker`]) is matched to that execution. Transfers that
+    /// show no marker, as the receiving side may not, are matched on amount
+    /// alone; other transactions without a marker are never our own. Returns
+    /// `None` when the account number is unknown, since the match is also made
+    /// on account number, amount and date.
+    async fn match_own_transfer(
+        &self,
+        account_number: Option<&str>,
+        tx: &Transaction,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let Some(account_number) = account_number else {
+            return Ok(None);
+        };
+        let marker = [&tx.kid_or_message, &tx.description]
+            .into_iter()
+            .flatten()
+            .find_map(|text| RuleExecution::find_marker(text));
+        if marker.is_none() && tx.type_code != TransactionType::Transfer {
+            return Ok(None);
+        }
+
+        let execution_id = self
+            .db
+            .match_execution_transaction(
+                account_number,
+                &tx.id,
+                tx.amount,
+                tx.date / 1000,
+                Duration::days(SELF_TRANSFER_MATCH_DAYS).num_seconds(),
+                marker,
+            )
+            .await?;
+        if let Some(id) = &execution_id {
+            debug!("Transaction {} was created by execution {}", tx.id, id);
+        }
+        Ok(execution_id)
+    }
+
+    /// Update the tracked transaction record, returning it as stored.
     async fn update_tracked_transaction(
         &self,
         tx: &Transaction,
         fingerprint: &TransactionFingerprint,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let now = chrono::Utc::now().timestamp();
-        let raw_data = serde_json::to_string(tx)?;
-
-        let tracked = TrackedTransaction {
-            id: tx.id.clone(),
-            account_key: tx.account_key.clone(),
-            fingerprint: fingerprint.fingerprint.clone(),
-            first_seen_at: now,
-            last_updated_at: now,
-            settled: tx.booking_status == "BOOKED",
-            raw_data,
+        previous: Option<&TrackedTransaction>,
+    ) -> Result<TrackedTransaction, Box<dyn std::error::Error + Send + Sync>> {
+        let tracked = tracked_version(tx, fingerprint, previous)?;
+        self.db.upsert_tracked_transaction(&tracked).await?;
+        Ok(tracked)
+    }
+
+    /// Evaluate a rule against historical transactions on explicit request.
+    ///
+    /// Transactions since `since` are evaluated even if they predate the rule's
+    /// activation. Once-per-transaction and lifecycle semantics still apply, so
+    /// a backfill never repeats a transfer, and transactions claimed by other
+    /// rules (see [`RuleEngine::claimed_by`]) are skipped. Returns the number
+    /// of transactions evaluated.
+    pub async fn backfill(
+        &self,
+        rule: &Rule,
+        since: NaiveDate,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        info!("Backfilling rule '{}' since {}", rule.name, since);
+        let rule = CompiledRule::compile(rule.clone())?;
+
+        let accounts = self.bank_client.get_accounts().await?.accounts;
+        let account_number = accounts
+            .iter()
+            .find(|a| a.key == rule.trigger_account_key)
+            .map(|a| a.account_number.as_str());
+
+        let query = TransactionQuery::since(since);
+        let transactions = self
+            .bank_client
+            .get_transactions(&rule.trigger_account_key, &query)
+            .await?
+            .transactions;
+
+        let rules_by_account = self.compiled_rules().await?;
+        let rules = rules_by_account.get(&rule.trigger_account_key).map_or(&[][..], Vec::as_slice);
+
+        let mut sent = 0;
+        for tx in &transactions {
+            if !rule.include_self_transfers && self.match_own_transfer(account_number, tx).await?.is_some() {
+                continue;
+            }
+            if let Some(other) = self.claimed_by(&rule, rules, &tx.id).await? {
+                debug!("Rule {} skipping transaction {}: matched by rule {}", rule.id, tx.id, other.id);
+                continue;
+            }
+            // Not stored, so the next poll still sees new transactions as new
+            let fingerprint = TransactionFingerprint::from_transaction(tx);
+            let previous = self.db.get_tracked_transaction(&tx.id).await?;
+            let tracked = tracked_version(tx, &fingerprint, previous.as_ref())?;
+            self.evaluate_and_execute(&rule, tx, &tracked, &fingerprint, &accounts, &mut sent).await?;
+        }
+
+        Ok(transactions.len())
+    }
+
+    /// Show what a rule would have done with its account's transactions dated
+    /// `from` to `to` (inclusive, in Europe/Oslo; default today), without
+    /// transferring or logging anything. See [`backtest::simulate`].
+    pub async fn backtest(
+        &self,
+        rule: &Rule,
+        from: NaiveDate,
+        to: Option<NaiveDate>,
+        source: BacktestSource,
+    ) -> Result<BacktestReport, Box<dyn std::error::Error + Send + Sync>> {
+        let rule = CompiledRule::compile(rule.clone())?;
+        let to = to.unwrap_or_else(|| Utc::now().with_timezone(&SCHEDULE_TIMEZONE).date_naive());
+        if from > to {
+            return Err("Backtest start is after its end".into());
+        }
+        let (Some(start), Some(end)) = (local_day_start(from), to.succ_opt().and_then(local_day_start)) else {
+            return Err("Invalid backtest window".into());
         };
+        let end = end - 1;
+        // Aggregates on the first transactions look back before the window
+        let history_start = rule
+            .conditions
+            .iter()
+            .filter_map(|c| c.history_start(start))
+            .min()
+            .map_or(start, |s| s.min(start));
 
-        self.db.upsert_tracked_transaction(&tracked).await?;
+        let mut transactions = match source {
+            BacktestSource::Stored => {
+                self.db
+                    .get_tracked_transactions_between(&rule.trigger_account_key, history_start, end)
+                    .await?
+            }
+            BacktestSource::Bank => {
+                let since = DateTime::from_timestamp_millis(history_start)
+                    .map_or(from, |t| t.with_timezone(&SCHEDULE_TIMEZONE).date_naive());
+                let query = TransactionQuery::since(since).until(to);
+                self.bank_client
+                    .get_transactions(&rule.trigger_account_key, &query)
+                    .await?
+                    .transactions
+            }
+        };
+        transactions.sort_by_key(|tx| tx.date);
+
+        let accounts = self.bank_client.get_accounts().await?.accounts;
+        Ok(backtest::simulate(&rule, &transactions, &accounts, start, end))
+    }
+
+    /// Run scheduled rules that are due.
+    ///
+    /// A rule with no next run yet is scheduled from now without running. A
+    /// due rule runs once for its latest due occurrence, however many were
+    /// missed; if that occurrence is more than [`SCHEDULE_GRACE_SECS`] old it
+    /// only runs when the rule catches up on missed runs. Executions are keyed
+    /// by the occurrence, so a run is never repeated. Returns the number of
+    /// rules run.
+    pub async fn run_scheduled(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let rules = self.db.get_enabled_scheduled_rules().await?;
+        let now = Utc::now().timestamp();
+        let mut accounts = None;
+        let mut ran = 0;
+        let mut sent = 0;
+
+        for rule in rules {
+            let RuleTrigger::Schedule { cron, catch_up } = &rule.trigger else {
+                continue;
+            };
+            let schedule = match Schedule::parse(cron) {
+                Ok(schedule) => schedule,
+                Err(e) => {
+                    error!("Rule {} has an invalid schedule: {}", rule.id, e);
+                    continue;
+                }
+            };
+            let next_run_at = schedule.next_after(now);
+
+            let Some(due) = rule.next_run_at else {
+                self.db.set_next_run_at(&rule.id, next_run_at).await?;
+                continue;
+            };
+            if due > now {
+                continue;
+            }
+
+            let occurrence = schedule.latest_between(due, now).unwrap_or(due);
+            if now - occurrence > SCHEDULE_GRACE_SECS && !catch_up {
+                info!("Rule '{}' missed its run at {}; not catching up", rule.name, occurrence);
+            } else {
+                if accounts.is_none() {
+                    accounts = Some(self.bank_client.get_accounts().await?.accounts);
+                }
+                let accounts = accounts.as_deref().unwrap_or_default();
+                match CompiledRule::compile(rule.clone()) {
+                    Ok(compiled) => {
+                        let ctx = EvalContext::scheduled(occurrence, accounts, &rule.trigger_account_key)
+                            .with_patterns(compiled.patterns());
+                        let history = self.load_history(&rule, &ctx).await?;
+                        let ctx = ctx.with_history(&history);
+
+                        if let Err(e) = self.run_scheduled_rule(&rule, &ctx, &mut sent).await {
+                            error!("Error running scheduled rule {}: {}", rule.id, e);
+                        }
+                        ran += 1;
+                    }
+                    Err(e) => error!("Scheduled rule {} not run: {}", rule.id, e),
+                }
+            }
+
+            self.db.set_next_run_at(&rule.id, next_run_at).await?;
+        }
+
+        Ok(ran)
+    }
+
+    /// Make one run of a scheduled rule. `sent` counts the transfers sent by
+    /// the scheduled runs so far.
+    async fn run_scheduled_rule(
+        &self,
+        rule: &Rule,
+        ctx: &EvalContext<'_>,
+        sent: &mut u32,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if !rule.conditions.iter().all(|c| c.evaluate(ctx)) {
+            debug!("Scheduled rule {} skipped: conditions not met", rule.id);
+            return Ok(());
+        }
+        if let Some((_, reason)) = self.check_rule_limits(rule, ctx).await? {
+            info!("Scheduled rule '{}' not running: {}", rule.name, reason);
+            return Ok(());
+        }
+
+        info!("Running scheduled rule '{}'", rule.name);
+        let statuses = self.execute_actions(rule, ctx, sent).await?;
+        debug!("Scheduled rule {} executed: {}", rule.id, statuses.join(","));
+        if statuses.iter().any(|s| s == "accumulated") {
+            self.flush_rule(rule, ctx.accounts, Some(sent)).await?;
+        }
         Ok(())
     }
 
+    /// Load the stored transactions the rule's aggregate conditions need, or
+    /// nothing if it has none.
+    async fn load_history(
+        &self,
+        rule: &Rule,
+        ctx: &EvalContext<'_>,
+    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error + Send + Sync>> {
+        let end = ctx.reference_time_millis();
+        match rule.conditions.iter().filter_map(|c| c.history_start(end)).min() {
+            Some(start) => Ok(self.db.get_tracked_transactions_between(&rule.trigger_account_key, start, end).await?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Check if a rule should be evaluated against this version of a transaction.
+    ///
+    /// A rule fires at most once per transaction, so a transaction that changes
+    /// after the rule fired (e.g. pending to booked) is not acted on again.
+    /// Whether the rule's lifecycle stage applies is read from `tracked`.
+    async fn check_rule_decision(
+        &self,
+        rule: &Rule,
+        tx: &Transaction,
+        tracked: &TrackedTransaction,
+        fingerprint: &TransactionFingerprint,
+    ) -> Result<ProcessingDecision, Box<dyn std::error::Error + Send + Sync>> {
+        if self.db.has_fired(&rule.id, &fingerprint.transaction_id).await? {
+            return Ok(ProcessingDecision::Skip {
+                reason: "Already fired for this transaction".to_string(),
+            });
+        }
+
+        if self.db.has_processed(&rule.id, &fingerprint.transaction_id, &fingerprint.fingerprint).await? {
+            return Ok(ProcessingDecision::Skip {
+                reason: "Already processed this version".to_string(),
+            });
+        }
+
+        if !rule.fire_on.applies_to_lifecycle(tracked) {
+            let reason = format!("Rule fires on {}, transaction is {}", rule.fire_on.as_str(), tx.booking_status);
+            return Ok(match rule.fire_on {
+                FireOn::Booked => ProcessingDecision::Wait { reason },
+                _ => ProcessingDecision::Skip { reason },
+            });
+        }
+
+        Ok(ProcessingDecision::Process)
+    }
+
     /// Evaluate a rule against a transaction and execute if matched.
+    ///
+    /// `accounts` are the accounts fetched for this poll; conditions and
+    /// amounts read balances from them. `sent` counts the transfers sent by
+    /// the poll or backfill so far, for the per-poll limit.
     async fn evaluate_and_execute(
         &self,
-        rule: &Rule,
+        rule: &CompiledRule,
         tx: &Transaction,
+        tracked: &TrackedTransaction,
         fingerprint: &TransactionFingerprint,
+        accounts: &[Account],
+        sent: &mut u32,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        // Check if already processed
-        if self.db.has_processed(&rule.id, &tx.id, &fingerprint.fingerprint).await? {
-            debug!("Rule {} already processed transaction {} with this fingerprint", rule.id, tx.id);
-            return Ok(());
+        match self.check_rule_decision(rule, tx, tracked, fingerprint).await? {
+            ProcessingDecision::Skip { reason } => {
+                debug!("Rule {} skipping transaction {}: {}", rule.id, tx.id, reason);
+                return Ok(());
+            }
+            ProcessingDecision::Wait { reason } => {
+                debug!("Rule {} waiting on transaction {}: {}", rule.id, tx.id, reason);
+                return Ok(());
+            }
+            ProcessingDecision::Process => {}
         }
 
         // Evaluate conditions
-        let all_match = rule.conditions.iter().all(|c| c.evaluate(tx));
+        let ctx = EvalContext::new(tx, accounts, &rule.trigger_account_key).with_patterns(rule.patterns());
+        let history = self.load_history(rule, &ctx).await?;
+        let ctx = ctx.with_history(&history);
+        let trace: Vec<_> = rule.conditions.iter().map(|c| c.trace(&ctx)).collect();
+        let all_match = trace.iter().all(|t| t.matched);
 
         let now = chrono::Utc::now().timestamp();
+        let log = |action_taken: String| RuleTransactionLog {
+            id: Uuid::new_v4().to_string(),
+            rule_id: rule.id.clone(),
+            transaction_id: tx.id.clone(),
+            transaction_fingerprint: fingerprint.fingerprint.clone(),
+            action_taken,
+            processed_at: now,
+            condition_trace: Some(trace.clone()),
+        };
 
         if !all_match {
             // Record skip
-            let log = RuleTransactionLog {
-                id: Uuid::new_v4().to_string(),
-                rule_id: rule.id.clone(),
-                transaction_id: tx.id.clone(),
-                transaction_fingerprint: fingerprint.fingerprint.clone(),
-                action_taken: "skipped".to_string(),
-                processed_at: now,
-            };
-            self.db.record_processing(&log).await?;
+            self.db.record_processing(&log("skipped".to_string())).await?;
             return Ok(());
         }
 
         info!("Rule '{}' matched transaction {}", rule.name, tx.id);
 
-        // Execute actions
-        for action in &rule.actions {
-            self.execute_action(rule, tx, action, fingerprint).await?;
+        if let Some((kind, reason)) = self.check_rule_limits(rule, &ctx).await? {
+            info!("Rule '{}' not firing on transaction {}: {}", rule.name, tx.id, reason);
+            self.db.record_processing(&log(format!("limited:{}", kind))).await?;
+            return Ok(());
+        }
+
+        let statuses = self.execute_actions(rule, &ctx, sent).await?;
+        if statuses.iter().any(|s| s == "accumulated") {
+            self.flush_rule(rule, accounts, Some(sent)).await?;
         }
 
+        // Record processing
+        self.db.record_processing(&log(format!("executed:{}", statuses.join(",")))).await?;
+
         Ok(())
     }
 
-    /// Execute a single action.
+    /// Execute all actions of a rule, returning the status of each.
+    async fn execute_actions(
+        &self,
+        rule: &Rule,
+        ctx: &EvalContext<'_>,
+        sent: &mut u32,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut statuses = Vec::with_capacity(rule.actions.len());
+        for (index, action) in rule.actions.iter().enumerate() {
+            statuses.push(self.execute_action(rule, ctx, index, action, sent).await?);
+        }
+        Ok(statuses)
+    }
+
+    /// Execute a single action, returning the resulting execution status.
     async fn execute_action(
         &self,
         rule: &Rule,
-        tx: &Transaction,
+        ctx: &EvalContext<'_>,
+        action_index: usize,
         action: &Action,
-        fingerprint: &TransactionFingerprint,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        sent: &mut u32,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         match action {
+            Action::Transfer { accumulate: None, .. } => {
+                let planned = self.plan_transfer(rule, ctx, action_index, action)?;
+                match planned {
+                    None => Ok("skipped".to_string()),
+                    Some(transfer) if rule.requires_approval => self.propose_transfer(&transfer).await,
+                    Some(transfer) => self.execute_transfer(&transfer, Some(sent)).await,
+                }
+            }
             Action::Transfer {
-                from_account,
-                to_account,
                 amount,
-                message,
-            } => {
-                self.execute_transfer(rule, tx, from_account, to_account, amount, message.clone(), fingerprint).await
-            }
+                accumulate: Some(_),
+                ..
+            } => self.accumulate(rule, ctx, action_index, amount).await,
         }
     }
 
-    /// Execute a transfer action.
-    async fn execute_transfer(
+    /// Work out the accounts and amount of a transfer action. Returns `None`
+    /// if the amount is zero or less.
+    fn plan_transfer(
         &self,
         rule: &Rule,
-        tx: &Transaction,
-        from_account: &AccountRef,
-        to_account: &AccountRef,
-        amount_spec: &AmountSpec,
-        message: Option<String>,
-        fingerprint: &TransactionFingerprint,
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let accounts = self.bank_client.get_accounts().await?.accounts;
+        ctx: &EvalContext<'_>,
+        action_index: usize,
+        action: &Action,
+    ) -> Result<Option<PlannedTransfer>, Box<dyn std::error::Error + Send + Sync>> {
+        let Action::Transfer {
+            from_account,
+            to_account,
+            amount: amount_spec,
+            message,
+            ..
+        } = action;
+        let from_acc = self.resolve_account_ref(from_account, ctx)?;
+        let to_acc = self.resolve_account_ref(to_account, ctx)?;
+        let amount = amount_spec.calculate(ctx);
+        if amount.ore() <= 0 {
+            debug!("Rule {} has nothing to transfer for {}: amount is {}", rule.id, ctx.source_id(), amount);
+            return Ok(None);
+        }
+
+        Ok(Some(PlannedTransfer {
+            rule_id: rule.id.clone(),
+            transaction_id: ctx.tx.map(|tx| tx.id.clone()),
+            scheduled_for: ctx.scheduled_for,
+            action_index,
+            idempotency_key: RuleExecution::idempotency_key(&rule.id, &ctx.source_id(), action_index),
+            amount,
+            from_account: from_acc.account_number.clone(),
+            to_account: to_acc.account_number.clone(),
+            message: message.clone(),
+        }))
+    }
+
+    /// Execute a transfer.
+    ///
+    /// The execution is written as pending before the transfer is sent, keyed
+    /// by rule, transaction and action index. If a row with that key already
+    /// exists the transfer has been attempted before and is not sent again.
+    ///
+    /// `sent` counts the transfers sent by the poll, scheduled run or
+    /// backfill making this one; it is `None` for approved proposals, which
+    /// the per-poll limit does not apply to.
+    async fn execute_transfer(
+        &self,
+        transfer: &PlannedTransfer,
+        sent: Option<&mut u32>,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
         let now = chrono::Utc::now().timestamp();
+        let amount = transfer.amount;
+        let blocked = self.check_transfer_allowed(amount, sent.as_deref().copied()).await?;
+
+        // Write-ahead record
+        let execution = RuleExecution {
+            id: Uuid::new_v4().to_string(),
+            rule_id: transfer.rule_id.clone(),
+            transaction_id: transfer.transaction_id.clone(),
+            scheduled_for: transfer.scheduled_for,
+            idempotency_key: transfer.idempotency_key.clone(),
+            transfer_payment_id: None,
+            amount,
+            from_account: transfer.from_account.clone(),
+            to_account: transfer.to_account.clone(),
+            status: if blocked.is_some() { RuleExecution::BLOCKED } else { RuleExecution::PENDING }.to_string(),
+            error_message: blocked.clone(),
+            executed_at: now,
+            debit_transaction_id: None,
+            credit_transaction_id: None,
+            message: transfer.message.clone(),
+            attempts: 1,
+            next_retry_at: None,
+        };
+        if !self.db.record_execution(&execution).await? {
+            info!(
+                "Transfer {} was already attempted, not sending again",
+                execution.idempotency_key
+            );
+            return Ok("duplicate".to_string());
+        }
+
+        if let Some(reason) = blocked {
+            warn!(
+                "Transfer blocked: {} -> {}, amount: {}: {}",
+                transfer.from_account, transfer.to_account, amount, reason
+            );
+            return Ok(RuleExecution::BLOCKED.to_string());
+        }
+
+        if let Some(sent) = sent {
+            *sent += 1;
+        }
+        Ok(self.send_transfer(&execution).await?.to_string())
+    }
+
+    /// Record a transfer as a proposal waiting for approval instead of
+    /// sending it.
+    async fn propose_transfer(
+        &self,
+        transfer: &PlannedTransfer,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let proposal = TransferProposal {
+            id: Uuid::new_v4().to_string(),
+            rule_id: transfer.rule_id.clone(),
+            transaction_id: transfer.transaction_id.clone(),
+            scheduled_for: transfer.scheduled_for,
+            action_index: transfer.action_index as i64,
+            idempotency_key: transfer.idempotency_key.clone(),
+            amount: transfer.amount,
+            from_account: transfer.from_account.clone(),
+            to_account: transfer.to_account.clone(),
+            message: transfer.message.clone(),
+            status: TransferProposal::PENDING.to_string(),
+            created_at: now,
+            expires_at: now + PROPOSAL_EXPIRY_SECS,
+            decided_at: None,
+            decided_by: None,
+            execution_status: None,
+        };
+        if !self.db.create_proposal(&proposal).await? {
+            return Ok("duplicate".to_string());
+        }
+
+        info!(
+            "Transfer proposed for approval: {} -> {}, amount: {}",
+            proposal.from_account, proposal.to_account, proposal.amount
+        );
+        Ok("proposed".to_string())
+    }
+
+    /// Approve a pending proposal and send its transfer, recording the
+    /// approval in the audit log. Emergency stop and transfer limits apply
+    /// as for any transfer.
+    pub async fn approve_proposal(
+        &self,
+        id: &str,
+        approved_by: &str,
+    ) -> Result<TransferProposal, Box<dyn std::error::Error + Send + Sync>> {
+        let proposal = self.decide_proposal(id, TransferProposal::APPROVED, approved_by).await?;
+
+        info!("Transfer proposal {} approved by {}", id, approved_by);
+        let result = self.execute_transfer(&PlannedTransfer::from(&proposal), None).await;
+        let execution_status = match &result {
+            Ok(status) => status.clone(),
+            Err(e) => format!("error: {}", e),
+        };
+
+        self.db.set_proposal_execution_status(id, &execution_status).await?;
+        let entry = AuditEntry::new(
+            AuditEventType::TransferApproved,
+            approved_by,
+            serde_json::json!({
+                "rule_id": proposal.rule_id,
+                "idempotency_key": proposal.idempotency_key,
+                "amount": proposal.amount.to_string(),
+                "currency": proposal.amount.currency().as_str(),
+                "from_account": proposal.from_account,
+                "to_account": proposal.to_account,
+                "execution_status": execution_status,
+            }),
+        )
+        .with_resource("transfer_proposal", id);
+        self.db.log_audit(&entry).await?;
+        result?;
+
+        Ok(self.db.get_proposal(id).await?.unwrap_or(proposal))
+    }
+
+    /// Reject a pending proposal, recording the rejection in the audit log.
+    pub async fn reject_proposal(
+        &self,
+        id: &str,
+        rejected_by: &str,
+    ) -> Result<TransferProposal, Box<dyn std::error::Error + Send + Sync>> {
+        let proposal = self.decide_proposal(id, TransferProposal::REJECTED, rejected_by).await?;
 
-        let from_acc = self.resolve_account_ref(from_account, &rule.trigger_account_key, &accounts)?;
-        let to_acc = self.resolve_account_ref(to_account, &rule.trigger_account_key, &accounts)?;
-        let amount = amount_spec.calculate(tx);
+        info!("Transfer proposal {} rejected by {}", id, rejected_by);
+        let entry = AuditEntry::new(
+            AuditEventType::TransferRejected,
+            rejected_by,
+            serde_json::json!({
+                "rule_id": proposal.rule_id,
+                "idempotency_key": proposal.idempotency_key,
+                "amount": proposal.amount.to_string(),
+                "currency": proposal.amount.currency().as_str(),
+            }),
+        )
+        .with_resource("transfer_proposal", id);
+        self.db.log_audit(&entry).await?;
 
+        Ok(proposal)
+    }
+
+    /// Move a pending proposal to `status`, returning it as decided.
+    async fn decide_proposal(
+        &self,
+        id: &str,
+        status: &str,
+        decided_by: &str,
+    ) -> Result<TransferProposal, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now().timestamp();
+        let proposal = self.db.get_proposal(id).await?.ok_or("Proposal not found")?;
+        if !self.db.decide_proposal(id, status, decided_by, now).await? {
+            self.db.expire_proposals(now).await?;
+            let current = self.db.get_proposal(id).await?.map_or(proposal.status, |p| p.status);
+            return Err(format!("Proposal is {}", current).into());
+        }
+
+        Ok(TransferProposal {
+            status: status.to_string(),
+            decided_at: Some(now),
+            decided_by: Some(decided_by.to_string()),
+            ..proposal
+        })
+    }
+
+    /// Mark proposals that were not decided on in time as expired. Returns
+    /// the number expired.
+    pub async fn expire_proposals(&self) -> Result<u64, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.db.expire_proposals(Utc::now().timestamp()).await?)
+    }
+
+    /// Send the transfer for a recorded pending execution and record the
+    /// outcome, returning the final status.
+    ///
+    /// Transient errors that leave the transfer unsent queue it to be retried
+    /// with backoff until the attempts run out. After a timeout or 5xx
+    /// response the transfer may have gone through, so it is looked for on the
+    /// account instead (see `resolve_uncertain_transfer`). Errors reported by
+    /// the bank, such as insufficient funds or a blocked account, fail it for
+    /// good. A flush that fails for good returns its amounts to the ledger.
+    async fn send_transfer(
+        &self,
+        execution: &RuleExecution,
+    ) -> Result<&'static str, Box<dyn std::error::Error + Send + Sync>> {
         info!(
-            "Executing transfer: {} -> {}, amount: {:.2}",
-            from_acc.account_number, to_acc.account_number, amount
+            "Executing transfer: {} -> {}, amount: {}",
+            execution.from_account, execution.to_account, execution.amount
         );
 
         let transfer = CreateTransferDTO {
-            amount: format!("{:.2}", amount),
+            amount: execution.amount.to_string(),
             due_date: None,
-            message,
-            to_account: to_acc.account_number.clone(),
-            from_account: from_acc.account_number.clone(),
+            message: Some(execution.transfer_message()),
+            to_account: execution.to_account.clone(),
+            from_account: execution.from_account.clone(),
             currency_code: None,
         };
 
         let result = self.bank_client.create_transfer(transfer).await;
 
         let (status, payment_id, error_msg) = match result {
-            Ok(response) if response.errors.is_empty() => {
-                ("success".to_string(), response.payment_id, None)
-            }
+            Ok(response) if response.errors.is_empty() => (RuleExecution::SUCCESS, response.payment_id, None),
             Ok(response) => {
                 let err = response.errors.first().map(|e| e.message.clone()).unwrap_or_default();
-                ("failed".to_string(), None, Some(err))
+                (RuleExecution::FAILED, None, Some(err))
+            }
+            Err(e) if e.is_transient() && !e.is_unprocessed() => {
+                warn!("Transfer {} may have gone through: {}", execution.idempotency_key, e);
+                return self.resolve_uncertain_transfer(execution, &e).await;
+            }
+            Err(e) if e.is_transient() => {
+                let attempt = u32::try_from(execution.attempts).unwrap_or(u32::MAX);
+                if TRANSFER_RETRY.can_retry(attempt) {
+                    let next_retry_at = Utc::now().timestamp() + TRANSFER_RETRY.backoff(attempt).as_secs() as i64;
+                    warn!(
+                        "Transfer failed on attempt {} of {}, retrying: {}",
+                        attempt, TRANSFER_RETRY.max_attempts, e
+                    );
+                    self.db.schedule_retry(&execution.id, &e.to_string(), next_retry_at).await?;
+                    return Ok(RuleExecution::RETRYING);
+                }
+                (
+                    RuleExecution::DEAD_LETTER,
+                    None,
+                    Some(format!("Gave up after {} attempts: {}", attempt, e)),
+                )
+            }
+            Err(e) => (RuleExecution::FAILED, None, Some(e.to_string())),
+        };
+
+        // Finalize execution
+        self.db
+            .complete_execution(&execution.id, status, payment_id.as_deref(), error_msg.as_deref())
+            .await?;
+
+        if let Some(err) = error_msg {
+            warn!("Transfer failed: {}", err);
+            self.db.release_accumulator_entries(&execution.id).await?;
+        }
+
+        Ok(status)
+    }
+
+    /// Settle an execution whose transfer may or may not have gone through,
+    /// as after a timeout or 5xx response. The transfer is looked for by its
+    /// marker among the source account's transactions; if it is not found,
+    /// the bank may not show it yet, so the execution is marked `unknown`
+    /// for manual review rather than sent again.
+    async fn resolve_uncertain_transfer(
+        &self,
+        execution: &RuleExecution,
+        error: &sb1_api::ApiError,
+    ) -> Result<&'static str, Box<dyn std::error::Error + Send + Sync>> {
+        let found = match self.find_sent_transfer(execution).await {
+            Ok(found) => found,
+            Err(e) => {
+                warn!("Could not look for transfer {}: {}", execution.idempotency_key, e);
+                None
             }
-            Err(e) => ("failed".to_string(), None, Some(e.to_string())),
         };
 
-        // Record execution
+        let Some(tx) = found else {
+            let message = format!("{}; the transfer was not found on the account, check it before sending again", error);
+            self.db
+                .complete_execution(&execution.id, RuleExecution::UNKNOWN, None, Some(&message))
+                .await?;
+            return Ok(RuleExecution::UNKNOWN);
+        };
+
+        info!("Transfer {} found as transaction {}", execution.idempotency_key, tx.id);
+        self.db
+            .complete_execution(&execution.id, RuleExecution::SUCCESS, None, None)
+            .await?;
+        self.match_own_transfer(Some(&execution.from_account), &tx).await?;
+        Ok(RuleExecution::SUCCESS)
+    }
+
+    /// The transaction on the source account carrying the execution's
+    /// marker, if the bank shows one.
+    async fn find_sent_transfer(
+        &self,
+        execution: &RuleExecution,
+    ) -> Result<Option<Transaction>, Box<dyn std::error::Error + Send + Sync>> {
+        let accounts = self.bank_client.get_accounts().await?.accounts;
+        let Some(account) = accounts.iter().find(|a| a.account_number == execution.from_account) else {
+            return Ok(None);
+        };
+
+        let sent = DateTime::from_timestamp(execution.executed_at, 0).unwrap_or_else(Utc::now);
+        let query = TransactionQuery::since(sent.date_naive() - Duration::days(1));
+        let transactions = self.bank_client.get_transactions(&account.key, &query).await?.transactions;
+
+        let marker = execution.marker();
+        Ok(transactions.into_iter().find(|tx| {
+            tx.amount == -execution.amount
+                && [&tx.kid_or_message, &tx.description]
+                    .into_iter()
+                    .flatten()
+                    .any(|text| text.contains(&marker))
+        }))
+    }
+
+    /// Send transfers queued for retry whose backoff has passed. Retries are
+    /// held back while the emergency stop is engaged; transfer limits are not
+    /// checked again, as queued transfers already count towards them.
+    /// Transfers of rules that have since been disabled are dead-lettered
+    /// instead. Returns the number of transfers retried.
+    pub async fn retry_failed_transfers(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        self.retry_transfers_due(Utc::now().timestamp()).await
+    }
+
+    async fn retry_transfers_due(&self, now: i64) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let abandoned = self
+            .db
+            .dead_letter_inactive_retries("Rule was disabled before the transfer was retried")
+            .await?;
+        for id in &abandoned {
+            warn!("Not retrying execution {}: its rule is disabled", id);
+            self.db.release_accumulator_entries(id).await?;
+        }
+
+        let due = self.db.list_due_retries(now).await?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+        if self.emergency_stop_engaged().await? {
+            warn!("Emergency stop is engaged, holding back {} transfer retries", due.len());
+            return Ok(0);
+        }
+
+        let mut retried = 0;
+        for execution in due {
+            if !self.db.begin_retry(&execution.id).await? {
+                continue;
+            }
+            let execution = RuleExecution {
+                status: RuleExecution::PENDING.to_string(),
+                attempts: execution.attempts + 1,
+                next_retry_at: None,
+                ..execution
+            };
+
+            info!(
+                "Retrying transfer {} (attempt {} of {})",
+                execution.idempotency_key, execution.attempts, TRANSFER_RETRY.max_attempts
+            );
+            self.send_transfer(&execution).await?;
+            retried += 1;
+        }
+
+        Ok(retried)
+    }
+
+    /// Send a transfer that was blocked by the emergency stop or a transfer
+    /// limit. A block uses up the transfer's idempotency key, so later polls
+    /// never send it; this is the way to send it once the block is lifted.
+    /// The emergency stop and transfer limits are checked again first.
+    pub async fn resend_blocked(&self, id: &str) -> Result<RuleExecution, Box<dyn std::error::Error + Send + Sync>> {
+        let execution = self.db.get_execution(id).await?.ok_or("Execution not found")?;
+        if execution.status != RuleExecution::BLOCKED {
+            return Err(format!("Execution is {}", execution.status).into());
+        }
+        if let Some(reason) = self.check_transfer_allowed(execution.amount, None).await? {
+            return Err(format!("Transfer is still blocked: {}", reason).into());
+        }
+
+        let now = Utc::now().timestamp();
+        if !self.db.begin_resend(id, now).await? {
+            return Err("Execution is no longer blocked".into());
+        }
         let execution = RuleExecution {
-            id: Uuid::new_v4().to_string(),
-            rule_id: rule.id.clone(),
-            transaction_id: tx.id.clone(),
-            transfer_payment_id: payment_id,
-            amount,
-            from_account: from_acc.account_number.clone(),
-            to_account: to_acc.account_number.clone(),
-            status: status.clone(),
-            error_message: error_msg.clone(),
+            status: RuleExecution::PENDING.to_string(),
+            error_message: None,
             executed_at: now,
+            ..execution
         };
-        self.db.record_execution(&execution).await?;
 
-        // Record processing
-        let log = RuleTransactionLog {
-            id: Uuid::new_v4().to_string(),
-            rule_id: rule.id.clone(),
-            transaction_id: tx.id.clone(),
-            transaction_fingerprint: fingerprint.fingerprint.clone(),
-            action_taken: format!("executed:{}", status),
-            processed_at: now,
+        info!("Sending blocked transfer {}", execution.idempotency_key);
+        self.send_transfer(&execution).await?;
+        Ok(self.db.get_execution(id).await?.unwrap_or(execution))
+    }
+
+    /// Collect the amount of an accumulating transfer action in the ledger.
+    async fn accumulate(
+        &self,
+        rule: &Rule,
+        ctx: &EvalContext<'_>,
+        action_index: usize,
+        amount_spec: &AmountSpec,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let amount = amount_spec.calculate(ctx);
+        if amount.ore() <= 0 {
+            debug!("Rule {} has nothing to collect for {}", rule.id, ctx.source_id());
+            return Ok("skipped".to_string());
+        }
+
+        if !self.db.add_accumulator_entry(&rule.id, action_index, &ctx.source_id(), amount).await? {
+            return Ok("duplicate".to_string());
+        }
+        debug!("Rule {} collected {} for {}", rule.id, amount, ctx.source_id());
+        Ok("accumulated".to_string())
+    }
+
+    /// Flush the collected amounts of every enabled rule that are due.
+    /// Returns the number of flush transfers attempted.
+    pub async fn flush_accumulators(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let rules: Vec<_> = self
+            .db
+            .list_rules()
+            .await?
+            .into_iter()
+            .filter(|rule| rule.enabled && rule.actions.iter().any(Action::accumulates))
+            .collect();
+        if rules.is_empty() {
+            return Ok(0);
+        }
+
+        let accounts = self.bank_client.get_accounts().await?.accounts;
+        let mut flushed = 0;
+        for rule in &rules {
+            flushed += self.flush_rule(rule, &accounts, None).await?;
+        }
+        Ok(flushed)
+    }
+
+    /// Flush the collected amounts of a rule's accumulating actions that are
+    /// due. `sent` counts the transfers sent by the poll, scheduled run or
+    /// backfill flushing them, if any.
+    async fn flush_rule(
+        &self,
+        rule: &Rule,
+        accounts: &[Account],
+        mut sent: Option<&mut u32>,
+    ) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let ctx = EvalContext::without_trigger(accounts, &rule.trigger_account_key);
+        let mut flushed = 0;
+
+        for (action_index, action) in rule.actions.iter().enumerate() {
+            let Action::Transfer {
+                from_account,
+                to_account,
+                message,
+                accumulate: Some(accumulate),
+                ..
+            } = action
+            else {
+                continue;
+            };
+            let Some(pending) = self.db.pending_accumulation(&rule.id, action_index).await? else {
+                continue;
+            };
+            if !self.flush_due(rule, accumulate, &pending) {
+                continue;
+            }
+
+            let from_acc = self.resolve_account_ref(from_account, &ctx)?;
+            let to_acc = self.resolve_account_ref(to_account, &ctx)?;
+            if let Some(reason) = self.check_transfer_allowed(pending.total, sent.as_deref().copied()).await? {
+                warn!("Flush of {} for rule {} held back: {}", pending.total, rule.id, reason);
+                continue;
+            }
+
+            let id = Uuid::new_v4().to_string();
+            let execution = RuleExecution {
+                idempotency_key: RuleExecution::idempotency_key(&rule.id, &format!("flush:{}", id), action_index),
+                id,
+                rule_id: rule.id.clone(),
+                transaction_id: None,
+                scheduled_for: None,
+                transfer_payment_id: None,
+                amount: pending.total,
+                from_account: from_acc.account_number.clone(),
+                to_account: to_acc.account_number.clone(),
+                status: RuleExecution::PENDING.to_string(),
+                error_message: None,
+                executed_at: Utc::now().timestamp(),
+                debit_transaction_id: None,
+                credit_transaction_id: None,
+                message: message.clone(),
+                attempts: 1,
+                next_retry_at: None,
+            };
+            if !self.db.begin_flush(&execution, action_index, pending.up_to_id).await? {
+                continue;
+            }
+
+            info!("Flushing {} collected by rule '{}'", pending.total, rule.name);
+            if let Some(sent) = sent.as_deref_mut() {
+                *sent += 1;
+            }
+            self.send_transfer(&execution).await?;
+            flushed += 1;
+        }
+
+        Ok(flushed)
+    }
+
+    /// Whether the collected amounts are due to be flushed.
+    fn flush_due(&self, rule: &Rule, accumulate: &Accumulate, pending: &PendingAccumulation) -> bool {
+        if accumulate
+            .threshold
+            .is_some_and(|threshold| pending.total >= threshold.with_currency(pending.total.currency()))
+        {
+            return true;
+        }
+        let Some(cron) = &accumulate.flush_schedule else {
+            return false;
         };
-        self.db.record_processing(&log).await?;
+        match Schedule::parse(cron) {
+            Ok(schedule) => schedule.latest_between(pending.oldest_at + 1, Utc::now().timestamp()).is_some(),
+            Err(e) => {
+                error!("Rule {} has an invalid flush schedule: {}", rule.id, e);
+                false
+            }
+        }
+    }
 
-        if let Some(err) = error_msg {
-            warn!("Transfer failed: {}", err);
+    /// Reconcile executions left pending by an earlier run.
+    ///
+    /// A pending row means the process stopped between writing the execution
+    /// and recording the bank's answer, so the transfer may or may not have
+    /// gone through. Such rows are marked `unknown` for manual review; the
+    /// transfer is never re-sent. Returns the number of rows reconciled.
+    pub async fn reconcile_pending_executions(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let pending = self.db.list_pending_executions().await?;
+
+        for execution in &pending {
+            warn!(
+                "Execution {} ({} -> {}, amount: {}) was interrupted; marking as unknown",
+                execution.id, execution.from_account, execution.to_account, execution.amount
+            );
+            self.db
+                .complete_execution(
+                    &execution.id,
+                    RuleExecution::UNKNOWN,
+                    None,
+                    Some("Interrupted before the bank's response was recorded; check the account before retrying"),
+                )
+                .await?;
         }
 
-        Ok(())
+        Ok(pending.len())
     }
 
     /// Resolve an account reference to an actual account.
     fn resolve_account_ref<'a>(
         &self,
         account_ref: &AccountRef,
-        trigger_account_key: &str,
-        accounts: &'a [Account],
+        ctx: &EvalContext<'a>,
     ) -> Result<&'a Account, Box<dyn std::error::Error + Send + Sync>> {
-        match account_ref {
-            AccountRef::TriggerAccount => accounts
-                .iter()
-                .find(|a| a.key == trigger_account_key)
-                .ok_or_else(|| format!("Trigger account {} not found", trigger_account_key).into()),
+        ctx.account(account_ref).ok_or_else(|| match account_ref {
+            AccountRef::TriggerAccount => format!("Trigger account {} not found", ctx.trigger_account_key).into(),
+            AccountRef::ByKey { key } => format!("Account with key {} not found", key).into(),
+            AccountRef::ByNumber { number } => format!("Account with number {} not found", number).into(),
+        })
+    }
+}
 
-            AccountRef::ByKey { key } => accounts
-                .iter()
-                .find(|a| a.key == *key)
-                .ok_or_else(|| format!("Account with key {} not found", key).into()),
+/// Unix timestamps for the start of the current day and month in
+/// [`SCHEDULE_TIMEZONE`].
+fn period_starts() -> (i64, i64) {
+    period_starts_at(Utc::now())
+}
 
-            AccountRef::ByNumber { number } => accounts
-                .iter()
-                .find(|a| a.account_number == *number)
-                .ok_or_else(|| format!("Account with number {} not found", number).into()),
-        }
-    }
+/// Unix timestamps for the start of the day and month of `now` in
+/// [`SCHEDULE_TIMEZONE`].
+pub(crate) fn period_starts_at(now: DateTime<Utc>) -> (i64, i64) {
+    let today = now.with_timezone(&SCHEDULE_TIMEZONE).date_naive();
+    let first_of_month = today.with_day(1).unwrap_or(today);
+    let start = |date: NaiveDate| local_day_start(date).map_or(now.timestamp(), |millis| millis / 1000);
+    (start(today), start(first_of_month))
 }
+
+/// The tracked record for this version of a transaction. Lifecycle
+/// timestamps already set on `previous` are kept, as the database does.
+fn tracked_version(
+    tx: &Transaction,
+    fingerprint: &TransactionFingerprint,
+    previous: Option<&TrackedTransaction>,
+) -> Result<TrackedTransaction, serde_json::Error> {
+    let now = Utc::now().timestamp();
+    let settled = tx.booking_status.is_booked();
+
+    Ok(TrackedTransaction {
+        id: tx.id.clone(),
+        account_key: tx.account_key.clone(),
+        fingerprint: fingerprint.fingerprint.clone(),
+        first_seen_at: previous.map_or(now, |p| p.first_seen_at),
+        last_updated_at: now,
+        settled,
+        pending_seen_at: previous.and_then(|p| p.pending_seen_at).or((!settled).then_some(now)),
+        booked_at: previous.and_then(|p| p.booked_at).or(settled.then_some(now)),
+        raw_data: serde_json::to_string(tx)?,
+    })
+}
+
+#[cfg(test)]
+mod tests;
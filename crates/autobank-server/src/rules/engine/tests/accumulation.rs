@@ -0,0 +1,71 @@
+//! Accumulating transfers and flushes.
+
+use super::*;
+
+fn round_up_rule(accumulate: Accumulate) -> Rule {
+    Rule {
+        actions: vec![Action::Transfer {
+            from_account: AccountRef::TriggerAccount,
+            to_account: AccountRef::ByKey { key: "savings".to_string() },
+            amount: AmountSpec::RoundUp { to: Money::nok(1000) },
+            message: None,
+            accumulate: Some(accumulate),
+        }],
+        ..savings_rule(FireOn::FirstSeen)
+    }
+}
+
+#[tokio::test]
+async fn test_round_up_accumulates_until_threshold() {
+    let transactions = vec![
+        transaction("tx-1", Money::nok(-14700), BookingStatus::Booked),
+        transaction("tx-2", Money::nok(-2000), BookingStatus::Booked),
+        transaction("tx-3", Money::nok(-9450), BookingStatus::Booked),
+    ];
+    let accumulate = Accumulate {
+        threshold: Some(Money::nok(800)),
+        flush_schedule: None,
+    };
+    let (db, bank, engine) = setup(round_up_rule(accumulate), transactions).await;
+
+    engine.evaluate_all().await.unwrap();
+
+    // 3.00 + 0.00 + 5.50 reaches the 8.00 threshold on the third purchase
+    let transfers = bank.get_transfer_history().await;
+    assert_eq!(transfers.len(), 1);
+    assert!(matches!(&transfers[0], TransferRecord::Regular(t) if t.amount == "8.50"));
+    assert!(db.get_pending_accumulator_entries("rule-1").await.unwrap().is_empty());
+
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].amount, Money::nok(850));
+}
+
+#[tokio::test]
+async fn test_accumulated_amounts_flush_on_schedule() {
+    let accumulate = Accumulate {
+        threshold: None,
+        flush_schedule: Some("* * * * *".to_string()),
+    };
+    let (db, bank, engine) =
+        setup(round_up_rule(accumulate), vec![transaction("tx-1", Money::nok(-14700), BookingStatus::Booked)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+    assert_eq!(db.get_pending_accumulator_entries("rule-1").await.unwrap().len(), 1);
+
+    // Collected just now; the schedule has not run since
+    let pending = db.pending_accumulation("rule-1", 0).await.unwrap().unwrap();
+    assert_eq!(pending.total, Money::nok(300));
+    let rule = db.get_rule("rule-1").await.unwrap().unwrap();
+    let Action::Transfer { accumulate: Some(accumulate), .. } = &rule.actions[0] else {
+        unreachable!()
+    };
+    assert!(!engine.flush_due(&rule, accumulate, &pending));
+
+    let collected_earlier = PendingAccumulation {
+        oldest_at: pending.oldest_at - 120,
+        ..pending
+    };
+    assert!(engine.flush_due(&rule, accumulate, &collected_earlier));
+}
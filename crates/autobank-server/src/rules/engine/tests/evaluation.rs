@@ -0,0 +1,171 @@
+//! Rule evaluation: aggregates, backtests, traces, ordering and the rule cache.
+
+use super::*;
+
+#[tokio::test]
+async fn test_aggregate_condition_reads_stored_transactions() {
+    // A minute apart; the bank returns them newest first
+    let now = Utc::now().timestamp_millis();
+    let transactions = (0..4)
+        .map(|i| Transaction {
+            date: now - (3 - i) * 60_000,
+            ..transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked)
+        })
+        .collect();
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.conditions = vec![Condition::Aggregate(Aggregate {
+        function: AggregateFunction::Count { value: 2 },
+        window: AggregateWindow::Rolling { days: 1 },
+        filter: Some(Box::new(Condition::AmountLessThan { value: Money::nok(0) })),
+        comparison: Comparison::GreaterThan,
+    })];
+    let (db, bank, engine) = setup(rule, transactions).await;
+
+    // Fires from the third purchase on, as they are evaluated oldest first
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+    let log = processing_log(&db).await;
+    assert_eq!(log.iter().filter(|a| *a == "skipped").count(), 2);
+}
+
+#[tokio::test]
+async fn test_backtest_does_not_transfer_or_log() {
+    let transactions = vec![
+        transaction("tx-1", Money::nok(-14900), BookingStatus::Booked),
+        transaction("tx-2", Money::nok(50000), BookingStatus::Booked),
+    ];
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), transactions).await;
+    let yesterday = Utc::now().date_naive() - Duration::days(1);
+
+    let report = engine
+        .backtest(&savings_rule(FireOn::FirstSeen), yesterday, None, BacktestSource::Bank)
+        .await
+        .unwrap();
+    assert_eq!(report.transactions_evaluated, 2);
+    assert_eq!(report.matches.len(), 1);
+    assert_eq!(report.total_amount, Money::nok(1000));
+    assert!(bank.get_transfer_history().await.is_empty());
+    assert!(processing_log(&db).await.is_empty());
+
+    // Stored transactions are only those seen while polling
+    let report = engine
+        .backtest(&savings_rule(FireOn::FirstSeen), yesterday, None, BacktestSource::Stored)
+        .await
+        .unwrap();
+    assert_eq!(report.transactions_evaluated, 0);
+}
+
+#[tokio::test]
+async fn test_condition_trace_is_logged() {
+    let transactions = vec![transaction("income", Money::nok(50000), BookingStatus::Booked)];
+    let (db, _bank, engine) = setup(savings_rule(FireOn::FirstSeen), transactions).await;
+
+    engine.evaluate_all().await.unwrap();
+    let log = db.get_transaction_processing_log("rule-1", "income").await.unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].action_taken, "skipped");
+    let trace = log[0].condition_trace.as_ref().unwrap();
+    assert_eq!(trace[0].condition, "amount_less_than");
+    assert!(!trace[0].matched);
+    assert_eq!(trace[0].actual.as_deref(), Some("500.00"));
+}
+
+#[tokio::test]
+async fn test_stop_processing_skips_later_rules() {
+    let transactions = vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)];
+    let (db, bank, engine) = setup(fixed_rule("rule-1", 1000), transactions).await;
+    let mut first = fixed_rule("rule-2", 2000);
+    first.priority = 10;
+    first.stop_processing = true;
+    db.create_rule(&first).await.unwrap();
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(transferred_amounts(&bank).await, vec!["20.00"]);
+    assert!(processing_log(&db).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_first_match_wins_within_group() {
+    let netflix = || Transaction {
+        description: Some("NETFLIX.COM".to_string()),
+        ..transaction("netflix", Money::nok(-14900), BookingStatus::Pending)
+    };
+    let spotify = Transaction {
+        description: Some("SPOTIFY".to_string()),
+        ..transaction("spotify", Money::nok(-11900), BookingStatus::Booked)
+    };
+    let mut any_subscription = fixed_rule("rule-1", 2000);
+    any_subscription.group = Some("subscriptions".to_string());
+    let (db, bank, engine) = setup(any_subscription, vec![netflix(), spotify.clone()]).await;
+
+    let mut specific = fixed_rule("rule-2", 1000);
+    specific.group = Some("subscriptions".to_string());
+    specific.priority = 1;
+    specific.conditions.push(Condition::DescriptionMatches {
+        pattern: "netflix".to_string(),
+        case_insensitive: true,
+    });
+    db.create_rule(&specific).await.unwrap();
+    db.create_rule(&fixed_rule("rule-3", 500)).await.unwrap();
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(transferred_amounts(&bank).await, vec!["10.00", "20.00", "5.00", "5.00"]);
+
+    // The group was claimed on the pending version
+    let booked = Transaction {
+        booking_status: BookingStatus::Booked,
+        ..netflix()
+    };
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![booked, spotify],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 4);
+}
+
+#[tokio::test]
+async fn test_rules_reload_after_invalidation() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    db.set_rule_enabled("rule-1", false).await.unwrap();
+    engine.invalidate_rules();
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![transaction("tx-2", Money::nok(-14900), BookingStatus::Booked)],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_rule_with_invalid_pattern_is_not_evaluated() {
+    let transactions = vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)];
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), transactions).await;
+    let mut broken = savings_rule(FireOn::FirstSeen);
+    broken.id = "rule-2".to_string();
+    broken.conditions = vec![Condition::DescriptionMatches {
+        pattern: "(unclosed".to_string(),
+        case_insensitive: false,
+    }];
+    db.create_rule(&broken).await.unwrap();
+
+    // The other rule still runs
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+    assert!(db.get_transaction_processing_log("rule-2", "tx-1").await.unwrap().is_empty());
+
+    let yesterday = Utc::now().date_naive() - Duration::days(1);
+    let error = engine.backtest(&broken, yesterday, None, BacktestSource::Stored).await.unwrap_err();
+    assert!(error.to_string().contains("invalid pattern"));
+}
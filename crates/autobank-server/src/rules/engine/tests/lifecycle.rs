@@ -0,0 +1,259 @@
+//! Transaction lifecycle, idempotency and own transfers.
+
+use super::*;
+
+#[tokio::test]
+async fn test_transfer_is_not_repeated_when_transaction_changes() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Pending)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    // Settling changes the fingerprint, so the rule is evaluated again.
+    settle(&bank, "tx-1").await;
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].status, RuleExecution::SUCCESS);
+    assert_eq!(executions[0].idempotency_key, "rule-1:tx-1:0");
+    assert_eq!(executions[0].transfer_payment_id.as_deref(), Some("mock-payment-id"));
+}
+
+#[tokio::test]
+async fn test_pending_execution_is_reconciled_without_resending() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+
+    // Simulate a crash after the write-ahead record was stored.
+    let pending = RuleExecution {
+        id: "exec-1".to_string(),
+        rule_id: "rule-1".to_string(),
+        transaction_id: Some("tx-1".to_string()),
+        scheduled_for: None,
+        idempotency_key: RuleExecution::idempotency_key("rule-1", "tx-1", 0),
+        transfer_payment_id: None,
+        amount: Money::nok(1000),
+        from_account: "12345678901".to_string(),
+        to_account: "12345678902".to_string(),
+        status: RuleExecution::PENDING.to_string(),
+        error_message: None,
+        executed_at: 0,
+        debit_transaction_id: None,
+        credit_transaction_id: None,
+        message: None,
+        attempts: 1,
+        next_retry_at: None,
+    };
+    assert!(db.record_execution(&pending).await.unwrap());
+
+    assert_eq!(engine.reconcile_pending_executions().await.unwrap(), 1);
+    let execution = db.get_execution("exec-1").await.unwrap().unwrap();
+    assert_eq!(execution.status, RuleExecution::UNKNOWN);
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+    assert_eq!(db.get_rule_executions("rule-1").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_booked_rule_waits_for_booking() {
+    let (db, bank, engine) =
+        setup(savings_rule(FireOn::Booked), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Pending)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+    let tracked = db.get_tracked_transaction("tx-1").await.unwrap().unwrap();
+    assert!(tracked.pending_seen_at.is_some());
+    assert!(tracked.booked_at.is_none());
+
+    settle(&bank, "tx-1").await;
+    engine.evaluate_all().await.unwrap();
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+    let tracked = db.get_tracked_transaction("tx-1").await.unwrap().unwrap();
+    assert!(tracked.pending_seen_at.is_some());
+    assert!(tracked.booked_at.is_some());
+}
+
+#[tokio::test]
+async fn test_pending_rule_ignores_booked_transactions() {
+    let (_db, bank, engine) =
+        setup(savings_rule(FireOn::Pending), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_pending_rule_ignores_transactions_seen_booked() {
+    let (db, bank, engine) =
+        setup(savings_rule(FireOn::Pending), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    engine.evaluate_all().await.unwrap();
+
+    // The bank reports the booked transaction as pending again
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Pending)],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+    engine.evaluate_all().await.unwrap();
+
+    assert!(bank.get_transfer_history().await.is_empty());
+    let tracked = db.get_tracked_transaction("tx-1").await.unwrap().unwrap();
+    assert!(tracked.booked_at.is_some());
+    assert!(tracked.pending_seen_at.is_some());
+}
+
+#[tokio::test]
+async fn test_historical_transactions_need_explicit_backfill() {
+    let now = Utc::now();
+    let rule = Rule {
+        activated_at: now.timestamp(),
+        ..savings_rule(FireOn::FirstSeen)
+    };
+    let old = Transaction {
+        date: (now - Duration::days(10)).timestamp_millis(),
+        ..transaction("tx-old", Money::nok(-14900), BookingStatus::Booked)
+    };
+    let (_db, bank, engine) = setup(rule.clone(), vec![old, transaction("tx-new", Money::nok(-5000), BookingStatus::Booked)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let since = (now - Duration::days(20)).date_naive();
+    assert_eq!(engine.backfill(&rule, since).await.unwrap(), 2);
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+
+    engine.backfill(&rule, since).await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+}
+
+#[test]
+fn test_activation_day_is_the_local_day() {
+    use chrono::TimeZone;
+
+    // Activated at 10:00 on 19 March in Oslo
+    let activated = Utc.with_ymd_and_hms(2026, 3, 19, 9, 0, 0).unwrap();
+    let rule = Rule {
+        activated_at: activated.timestamp(),
+        ..savings_rule(FireOn::FirstSeen)
+    };
+    let seen = activated.timestamp() + 60;
+
+    // 00:30 on the activation day in Oslo, still the day before in UTC
+    let early = Transaction {
+        date: Utc.with_ymd_and_hms(2026, 3, 18, 23, 30, 0).unwrap().timestamp_millis(),
+        ..transaction("tx-early", Money::nok(-14900), BookingStatus::Booked)
+    };
+    assert!(!rule.predates_activation(&early, seen));
+
+    // 23:30 the day before in Oslo
+    let late = Transaction {
+        date: Utc.with_ymd_and_hms(2026, 3, 18, 22, 30, 0).unwrap().timestamp_millis(),
+        ..transaction("tx-late", Money::nok(-14900), BookingStatus::Booked)
+    };
+    assert!(rule.predates_activation(&late, seen));
+}
+
+#[tokio::test]
+async fn test_own_transfers_do_not_trigger_rules() {
+    let (db, bank, engine) = setup(
+        savings_rule(FireOn::FirstSeen),
+        vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)],
+    )
+    .await;
+    let sweep_back = Rule {
+        id: "rule-2".to_string(),
+        trigger_account_key: "savings".to_string(),
+        conditions: vec![Condition::AmountGreaterThan { value: Money::nok(0) }],
+        actions: vec![Action::Transfer {
+            from_account: AccountRef::TriggerAccount,
+            to_account: AccountRef::ByKey { key: "checking".to_string() },
+            amount: AmountSpec::TransactionAmount,
+            message: None,
+            accumulate: None,
+        }],
+        ..savings_rule(FireOn::FirstSeen)
+    };
+    db.create_rule(&sweep_back).await.unwrap();
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    // The transfer shows up on both accounts in the next poll: with its
+    // message on the source account, as a plain transfer on the other.
+    let debit = Transaction {
+        kid_or_message: sent_message(&bank).await,
+        ..transaction("tx-debit", Money::nok(-1000), BookingStatus::Booked)
+    };
+    let credit = Transaction {
+        account_key: "savings".to_string(),
+        type_code: TransactionType::Transfer,
+        ..transaction("tx-credit", Money::nok(1000), BookingStatus::Booked)
+    };
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked), debit],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+    bank.set_transactions(
+        "savings",
+        TransactionResponse {
+            transactions: vec![credit],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let execution = &db.get_rule_executions("rule-1").await.unwrap()[0];
+    assert_eq!(execution.debit_transaction_id.as_deref(), Some("tx-debit"));
+    assert_eq!(execution.credit_transaction_id.as_deref(), Some("tx-credit"));
+}
+
+async fn sent_message(bank: &MockBankClient) -> Option<String> {
+    match bank.get_transfer_history().await.pop() {
+        Some(TransferRecord::Regular(transfer)) => transfer.message,
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn test_purchase_of_same_amount_is_not_own_transfer() {
+    let (db, bank, engine) = setup(
+        savings_rule(FireOn::FirstSeen),
+        vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)],
+    )
+    .await;
+    engine.evaluate_all().await.unwrap();
+    let marker = db.get_rule_executions("rule-1").await.unwrap()[0].marker();
+    assert!(sent_message(&bank).await.unwrap().contains(&marker));
+
+    // A purchase of the transferred amount, with no marker
+    let purchase = Transaction {
+        type_code: TransactionType::Purchase,
+        ..transaction("tx-2", Money::nok(-1000), BookingStatus::Booked)
+    };
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked), purchase],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+    engine.evaluate_all().await.unwrap();
+
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert!(executions.iter().all(|e| e.debit_transaction_id.is_none()));
+}
@@ -0,0 +1,225 @@
+//! Global transfer limits, the emergency stop and per-rule limits.
+
+use super::*;
+
+#[tokio::test]
+async fn test_emergency_stop_blocks_transfers() {
+    let (db, bank, engine) =
+        setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    engine.set_emergency_stop(true).await.unwrap();
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].status, RuleExecution::BLOCKED);
+    assert!(engine.emergency_stop_engaged().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_safety_settings_changes_are_audited() {
+    let (db, _bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![]).await;
+    engine.set_emergency_stop(true).await.unwrap();
+    let limits = TransferLimits {
+        max_transfers_per_poll: Some(2),
+        ..TransferLimits::default()
+    };
+    engine.set_transfer_limits(&limits).await.unwrap();
+
+    let audit = db.query_audit(10, Some("config_changed")).await.unwrap();
+    assert_eq!(audit.len(), 2);
+    let setting = |key: &str| audit.iter().find(|e| e.resource_id.as_deref() == Some(key)).unwrap();
+    assert_eq!(setting("emergency_stop").details["engaged"], true);
+    assert_eq!(setting("transfer_limits").details["limits"]["max_transfers_per_poll"], 2);
+    assert_eq!(setting("transfer_limits").details["previous"]["max_transfers_per_poll"], 10);
+}
+
+#[tokio::test]
+async fn test_transfers_per_poll_are_limited() {
+    let transactions = (0..3)
+        .map(|i| transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked))
+        .collect();
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), transactions).await;
+    engine
+        .set_transfer_limits(&TransferLimits {
+            max_transfers_per_poll: Some(2),
+            ..TransferLimits::default()
+        })
+        .await
+        .unwrap();
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+
+    let blocked: Vec<_> = db
+        .get_rule_executions("rule-1")
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|e| e.status == RuleExecution::BLOCKED)
+        .collect();
+    assert_eq!(blocked.len(), 1);
+}
+
+#[tokio::test]
+async fn test_backfill_does_not_reset_poll_count() {
+    let transactions = (0..3)
+        .map(|i| transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked))
+        .collect();
+    let (db, _bank, engine) = setup(savings_rule(FireOn::FirstSeen), transactions).await;
+    engine
+        .set_transfer_limits(&TransferLimits {
+            max_transfers_per_poll: Some(2),
+            ..TransferLimits::default()
+        })
+        .await
+        .unwrap();
+    let backfilled = Rule {
+        enabled: false,
+        ..fixed_rule("rule-2", 2000)
+    };
+    db.create_rule(&backfilled).await.unwrap();
+
+    let (poll, backfill) = tokio::join!(engine.evaluate_all(), engine.backfill(&backfilled, Utc::now().date_naive()));
+    poll.unwrap();
+    backfill.unwrap();
+
+    // Each run sends its own two transfers, however they interleave
+    for rule_id in ["rule-1", "rule-2"] {
+        let executions = db.get_rule_executions(rule_id).await.unwrap();
+        let blocked = executions.iter().filter(|e| e.status == RuleExecution::BLOCKED).count();
+        assert_eq!((executions.len(), blocked), (3, 1), "{}", rule_id);
+    }
+}
+
+#[tokio::test]
+async fn test_rule_daily_transfer_limit() {
+    let transactions = (0..3)
+        .map(|i| transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked))
+        .collect();
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.limits.max_transfers_per_day = Some(2);
+    let (db, bank, engine) = setup(rule, transactions).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+
+    let log = processing_log(&db).await;
+    assert_eq!(log.iter().filter(|a| a.starts_with("executed:")).count(), 2);
+    assert_eq!(log.iter().filter(|a| *a == "limited:daily_count").count(), 1);
+    assert_eq!(db.get_rule_executions("rule-1").await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_rule_cooldown_and_monthly_cap() {
+    let transactions = (0..2)
+        .map(|i| transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked))
+        .collect();
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.limits.cooldown_secs = Some(3600);
+    let (db, bank, engine) = setup(rule, transactions).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+    assert!(processing_log(&db).await.contains(&"limited:cooldown".to_string()));
+
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.limits.max_amount_per_month = Some(Money::nok(1500));
+    let transactions = (0..2)
+        .map(|i| transaction(&format!("tx-{}", i), Money::nok(-14900), BookingStatus::Booked))
+        .collect();
+    let (db, bank, engine) = setup(rule, transactions).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+    assert!(processing_log(&db).await.contains(&"limited:monthly_amount".to_string()));
+}
+
+#[tokio::test]
+async fn test_currency_account_amounts_and_limits_use_its_currency() {
+    let eur = Currency::parse("EUR").unwrap();
+    let rule = Rule {
+        limits: RuleLimits {
+            max_amount_per_day: Some(Money::nok(1500)),
+            ..RuleLimits::default()
+        },
+        ..savings_rule(FireOn::FirstSeen)
+    };
+    let (db, bank, engine) = setup(rule, vec![]).await;
+    let eur_account = |key, number| Account {
+        balance: Money::new(1000000, eur),
+        available_balance: Money::new(1000000, eur),
+        currency_code: "EUR".to_string(),
+        ..account(key, number)
+    };
+    bank.set_accounts(AccountData {
+        accounts: vec![eur_account("checking", "12345678901"), eur_account("savings", "12345678902")],
+        errors: vec![],
+    })
+    .await;
+    let eur_transaction = |id| Transaction {
+        amount: Money::new(-14900, eur),
+        currency_code: "EUR".to_string(),
+        ..transaction(id, Money::nok(0), BookingStatus::Booked)
+    };
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![eur_transaction("tx-1"), eur_transaction("tx-2")],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+
+    engine.evaluate_all().await.unwrap();
+
+    // 10 EUR is sent; a second would exceed the rule's daily 15 EUR
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].status, RuleExecution::SUCCESS);
+    assert_eq!(executions[0].amount, Money::new(1000, eur));
+    assert!(processing_log(&db).await.contains(&"limited:daily_amount".to_string()));
+}
+
+#[test]
+fn test_periods_start_at_local_midnight() {
+    use chrono::TimeZone;
+
+    // 00:30 on 1 March in Oslo, still February in UTC
+    let now = Utc.with_ymd_and_hms(2026, 2, 28, 23, 30, 0).unwrap();
+    let midnight = Utc.with_ymd_and_hms(2026, 2, 28, 23, 0, 0).unwrap().timestamp();
+    assert_eq!(period_starts_at(now), (midnight, midnight));
+
+    // Midday on 15 July, in summer time
+    let now = Utc.with_ymd_and_hms(2026, 7, 15, 12, 0, 0).unwrap();
+    let day = Utc.with_ymd_and_hms(2026, 7, 14, 22, 0, 0).unwrap().timestamp();
+    let month = Utc.with_ymd_and_hms(2026, 6, 30, 22, 0, 0).unwrap().timestamp();
+    assert_eq!(period_starts_at(now), (day, month));
+}
+
+#[tokio::test]
+async fn test_blocked_transfer_is_resent_on_request() {
+    let (db, bank, engine) =
+        setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    engine.set_emergency_stop(true).await.unwrap();
+    engine.evaluate_all().await.unwrap();
+    let blocked = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(blocked.status, RuleExecution::BLOCKED);
+
+    // Not while the stop is engaged
+    assert!(engine.resend_blocked(&blocked.id).await.is_err());
+
+    // The block counts as fired, so later polls do not send it
+    engine.set_emergency_stop(false).await.unwrap();
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+
+    let sent = engine.resend_blocked(&blocked.id).await.unwrap();
+    assert_eq!(sent.status, RuleExecution::SUCCESS);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    // Only blocked executions are resent
+    assert!(engine.resend_blocked(&blocked.id).await.is_err());
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
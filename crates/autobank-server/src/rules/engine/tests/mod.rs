@@ -0,0 +1,144 @@
+//! Rule engine tests, grouped by feature.
+
+mod accumulation;
+mod evaluation;
+mod lifecycle;
+mod limits;
+mod proposals;
+mod retries;
+mod scheduling;
+
+use super::*;
+use crate::rules::{Aggregate, AggregateFunction, AggregateWindow, Comparison, Condition};
+use sb1_api::models::{AccountData, BookingStatus, TransactionResponse, TransactionType};
+use sb1_api::mock::TransferRecord;
+use sb1_api::{ApiError, Currency, MockBankClient, Money};
+
+async fn test_db() -> Database {
+    // A named in-memory database, shared by the pool's connections and gone
+    // when the last one closes
+    let url = format!("sqlite:file:engine-test-{}?mode=memory&cache=shared", Uuid::new_v4());
+    let db = Database::connect(&url).await.unwrap();
+    db.run_migrations().await.unwrap();
+    db
+}
+
+fn account(key: &str, number: &str) -> Account {
+    Account {
+        key: key.to_string(),
+        account_number: number.to_string(),
+        balance: Money::nok(1000000),
+        available_balance: Money::nok(1000000),
+        currency_code: "NOK".to_string(),
+        ..Account::default()
+    }
+}
+
+fn transaction(id: &str, amount: Money, booking_status: BookingStatus) -> Transaction {
+    Transaction {
+        id: id.to_string(),
+        amount,
+        date: Utc::now().timestamp_millis(),
+        currency_code: "NOK".to_string(),
+        booking_status,
+        account_key: "checking".to_string(),
+        ..Transaction::default()
+    }
+}
+
+fn savings_rule(fire_on: FireOn) -> Rule {
+    Rule {
+        id: "rule-1".to_string(),
+        name: "Save on purchases".to_string(),
+        description: None,
+        enabled: true,
+        trigger: RuleTrigger::Transaction,
+        trigger_account_key: "checking".to_string(),
+        fire_on,
+        include_self_transfers: false,
+        limits: RuleLimits::default(),
+        priority: 0,
+        stop_processing: false,
+        group: None,
+        requires_approval: false,
+        conditions: vec![Condition::AmountLessThan { value: Money::nok(0) }],
+        actions: vec![Action::Transfer {
+            from_account: AccountRef::TriggerAccount,
+            to_account: AccountRef::ByKey { key: "savings".to_string() },
+            amount: AmountSpec::Fixed { value: Money::nok(1000) },
+            message: None,
+            accumulate: None,
+        }],
+        created_at: 0,
+        updated_at: 0,
+        activated_at: 0,
+        next_run_at: None,
+    }
+}
+
+async fn setup(rule: Rule, transactions: Vec<Transaction>) -> (Database, Arc<MockBankClient>, RuleEngine) {
+    let db = test_db().await;
+    db.create_rule(&rule).await.unwrap();
+
+    let bank = Arc::new(MockBankClient::new());
+    bank.set_accounts(AccountData {
+        accounts: vec![account("checking", "12345678901"), account("savings", "12345678902")],
+        errors: vec![],
+    })
+    .await;
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions,
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+
+    let engine = RuleEngine::new(db.clone(), bank.clone());
+    (db, bank, engine)
+}
+
+async fn settle(bank: &MockBankClient, id: &str) {
+    bank.set_transactions(
+        "checking",
+        TransactionResponse {
+            transactions: vec![transaction(id, Money::nok(-14900), BookingStatus::Booked)],
+            ..TransactionResponse::default()
+        },
+    )
+    .await;
+}
+
+async fn processing_log(db: &Database) -> Vec<String> {
+    let log = db.get_processing_log("rule-1", 100).await.unwrap();
+    log.into_iter().map(|entry| entry.action_taken).collect()
+}
+
+fn fixed_rule(id: &str, ore: i64) -> Rule {
+    Rule {
+        id: id.to_string(),
+        actions: vec![Action::Transfer {
+            from_account: AccountRef::TriggerAccount,
+            to_account: AccountRef::ByKey { key: "savings".to_string() },
+            amount: AmountSpec::Fixed { value: Money::nok(ore) },
+            message: None,
+            accumulate: None,
+        }],
+        ..savings_rule(FireOn::FirstSeen)
+    }
+}
+
+async fn transferred_amounts(bank: &MockBankClient) -> Vec<String> {
+    let mut amounts: Vec<_> = bank
+        .get_transfer_history()
+        .await
+        .into_iter()
+        .filter_map(|transfer| match transfer {
+            TransferRecord::Regular(t) => Some(t.amount),
+            _ => None,
+        })
+        .collect();
+    amounts.sort();
+    amounts
+}
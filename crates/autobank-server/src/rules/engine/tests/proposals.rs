@@ -0,0 +1,65 @@
+//! Transfers waiting for approval.
+
+use super::*;
+
+#[tokio::test]
+async fn test_approval_rule_proposes_transfer() {
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.requires_approval = true;
+    let (db, bank, engine) = setup(rule, vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+
+    engine.evaluate_all().await.unwrap();
+    assert!(bank.get_transfer_history().await.is_empty());
+    assert_eq!(processing_log(&db).await, vec!["executed:proposed"]);
+    let proposals = db.list_proposals(Some(TransferProposal::PENDING), 10).await.unwrap();
+    assert_eq!(proposals.len(), 1);
+    assert_eq!(proposals[0].amount, Money::nok(1000));
+    assert_eq!(proposals[0].to_account, "12345678902");
+    assert!(proposals[0].expires_at > proposals[0].created_at);
+
+    let approved = engine.approve_proposal(&proposals[0].id, "alice").await.unwrap();
+    assert_eq!(approved.status, TransferProposal::APPROVED);
+    assert_eq!(approved.decided_by.as_deref(), Some("alice"));
+    assert_eq!(approved.execution_status.as_deref(), Some(RuleExecution::SUCCESS));
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions[0].idempotency_key, "rule-1:tx-1:0");
+
+    let audit = db.query_audit(10, Some("transfer_approved")).await.unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].actor, "alice");
+    assert_eq!(audit[0].resource_id.as_deref(), Some(proposals[0].id.as_str()));
+
+    // A proposal is decided once
+    assert!(engine.approve_proposal(&proposals[0].id, "alice").await.is_err());
+    assert!(engine.reject_proposal(&proposals[0].id, "bob").await.is_err());
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_rejected_and_expired_proposals_are_not_sent() {
+    let mut rule = savings_rule(FireOn::FirstSeen);
+    rule.requires_approval = true;
+    let (db, bank, engine) = setup(rule, vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    engine.evaluate_all().await.unwrap();
+    let proposal = db.list_proposals(None, 10).await.unwrap().remove(0);
+
+    let rejected = engine.reject_proposal(&proposal.id, "bob").await.unwrap();
+    assert_eq!(rejected.status, TransferProposal::REJECTED);
+    assert_eq!(db.query_audit(10, Some("transfer_rejected")).await.unwrap()[0].actor, "bob");
+
+    let expired = TransferProposal {
+        id: "proposal-2".to_string(),
+        idempotency_key: "rule-1:tx-2:0".to_string(),
+        status: TransferProposal::PENDING.to_string(),
+        expires_at: Utc::now().timestamp() - 1,
+        decided_at: None,
+        decided_by: None,
+        ..proposal
+    };
+    assert!(db.create_proposal(&expired).await.unwrap());
+    let error = engine.approve_proposal("proposal-2", "alice").await.unwrap_err();
+    assert_eq!(error.to_string(), "Proposal is expired");
+
+    assert!(bank.get_transfer_history().await.is_empty());
+}
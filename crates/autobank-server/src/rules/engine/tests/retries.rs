@@ -0,0 +1,164 @@
+//! Retrying failed transfers.
+
+use super::*;
+
+fn rate_limited() -> ApiError {
+    ApiError::Status {
+        status: 429,
+        message: "Too Many Requests".to_string(),
+    }
+}
+
+fn gateway_timeout() -> ApiError {
+    ApiError::Status {
+        status: 504,
+        message: "Gateway Timeout".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_transient_failure_is_retried() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(rate_limited())).await;
+
+    engine.evaluate_all().await.unwrap();
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::RETRYING);
+    assert_eq!(execution.attempts, 1);
+    let next_retry_at = execution.next_retry_at.unwrap();
+
+    // Not due yet
+    assert_eq!(engine.retry_transfers_due(next_retry_at - 1).await.unwrap(), 0);
+    assert_eq!(engine.retry_transfers_due(next_retry_at).await.unwrap(), 1);
+    assert_eq!(bank.get_transfer_history().await.len(), 2);
+
+    let execution = db.get_execution(&execution.id).await.unwrap().unwrap();
+    assert_eq!(execution.status, RuleExecution::SUCCESS);
+    assert_eq!(execution.attempts, 2);
+    assert_eq!(execution.transfer_payment_id.as_deref(), Some("mock-payment-id"));
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_retries_end_in_dead_letter() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    for _ in 0..TRANSFER_RETRY.max_attempts {
+        bank.queue_transfer_result(Err(rate_limited())).await;
+    }
+
+    engine.evaluate_all().await.unwrap();
+    while engine.retry_transfers_due(i64::MAX).await.unwrap() > 0 {}
+
+    assert_eq!(bank.get_transfer_history().await.len(), TRANSFER_RETRY.max_attempts as usize);
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::DEAD_LETTER);
+    assert_eq!(execution.attempts, i64::from(TRANSFER_RETRY.max_attempts));
+    assert!(execution.error_message.unwrap().starts_with("Gave up after 5 attempts"));
+}
+
+#[tokio::test]
+async fn test_permanent_failure_is_not_retried() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(ApiError::Api {
+        code: "INSUFFICIENT_FUNDS".to_string(),
+        message: "Not enough money".to_string(),
+        trace_id: "trace-1".to_string(),
+    }))
+    .await;
+
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::FAILED);
+    assert_eq!(execution.next_retry_at, None);
+}
+
+#[tokio::test]
+async fn test_retries_wait_for_emergency_stop() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(rate_limited())).await;
+    engine.evaluate_all().await.unwrap();
+
+    engine.set_emergency_stop(true).await.unwrap();
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    engine.set_emergency_stop(false).await.unwrap();
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 1);
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::SUCCESS);
+}
+
+#[tokio::test]
+async fn test_timed_out_transfer_that_went_through_is_not_resent() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_lost_transfer_response(gateway_timeout()).await;
+
+    engine.evaluate_all().await.unwrap();
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::SUCCESS);
+    assert_eq!(execution.debit_transaction_id.as_deref(), Some("mock-transfer-1"));
+
+    // Neither retried nor taken for a new purchase on the next poll
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_timed_out_transfer_not_found_is_unknown() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(gateway_timeout())).await;
+
+    engine.evaluate_all().await.unwrap();
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::UNKNOWN);
+    assert_eq!(execution.next_retry_at, None);
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_retries_of_disabled_rules_are_dead_lettered() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(rate_limited())).await;
+    engine.evaluate_all().await.unwrap();
+
+    db.set_rule_enabled("rule-1", false).await.unwrap();
+    assert!(db.list_due_retries(i64::MAX).await.unwrap().is_empty());
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::DEAD_LETTER);
+    assert_eq!(execution.next_retry_at, None);
+}
+
+#[tokio::test]
+async fn test_deleting_a_rule_dead_letters_its_retries() {
+    let (db, bank, engine) = setup(savings_rule(FireOn::FirstSeen), vec![transaction("tx-1", Money::nok(-14900), BookingStatus::Booked)]).await;
+    bank.queue_transfer_result(Err(rate_limited())).await;
+    engine.evaluate_all().await.unwrap();
+
+    // The rule has run, so it is disabled rather than removed
+    assert!(!db.delete_rule("rule-1").await.unwrap());
+    assert!(!db.get_rule("rule-1").await.unwrap().unwrap().enabled);
+    let execution = db.get_rule_executions("rule-1").await.unwrap().remove(0);
+    assert_eq!(execution.status, RuleExecution::DEAD_LETTER);
+    assert_eq!(execution.next_retry_at, None);
+
+    assert_eq!(engine.retry_transfers_due(i64::MAX).await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    // A rule that never ran is removed
+    db.create_rule(&Rule {
+        id: "rule-2".to_string(),
+        ..savings_rule(FireOn::FirstSeen)
+    })
+    .await
+    .unwrap();
+    assert!(db.delete_rule("rule-2").await.unwrap());
+    assert!(db.get_rule("rule-2").await.unwrap().is_none());
+}
@@ -0,0 +1,59 @@
+//! Scheduled rules.
+
+use super::*;
+
+fn scheduled_rule(next_run_at: Option<i64>, catch_up: bool) -> Rule {
+    Rule {
+        trigger: RuleTrigger::Schedule {
+            cron: "0 7 25 * *".to_string(),
+            catch_up,
+        },
+        conditions: vec![],
+        next_run_at,
+        ..savings_rule(FireOn::FirstSeen)
+    }
+}
+
+#[tokio::test]
+async fn test_scheduled_rule_runs_once_when_due() {
+    let due = Utc::now().timestamp() - 60;
+    let (db, bank, engine) = setup(scheduled_rule(Some(due), false), vec![]).await;
+
+    assert_eq!(engine.run_scheduled().await.unwrap(), 1);
+    assert_eq!(engine.run_scheduled().await.unwrap(), 0);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+
+    let executions = db.get_rule_executions("rule-1").await.unwrap();
+    assert_eq!(executions.len(), 1);
+    assert_eq!(executions[0].transaction_id, None);
+    assert_eq!(executions[0].scheduled_for, Some(due));
+
+    let rule = db.get_rule("rule-1").await.unwrap().unwrap();
+    assert!(rule.next_run_at.unwrap() > Utc::now().timestamp());
+
+    // Transaction polling ignores scheduled rules
+    engine.evaluate_all().await.unwrap();
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_scheduled_rule_catch_up() {
+    let missed = Utc::now().timestamp() - Duration::days(2).num_seconds();
+
+    let (_db, bank, engine) = setup(scheduled_rule(Some(missed), false), vec![]).await;
+    assert_eq!(engine.run_scheduled().await.unwrap(), 0);
+    assert!(bank.get_transfer_history().await.is_empty());
+
+    let (_db, bank, engine) = setup(scheduled_rule(Some(missed), true), vec![]).await;
+    assert_eq!(engine.run_scheduled().await.unwrap(), 1);
+    assert_eq!(bank.get_transfer_history().await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_new_scheduled_rule_waits_for_first_run() {
+    let (db, bank, engine) = setup(scheduled_rule(None, true), vec![]).await;
+
+    assert_eq!(engine.run_scheduled().await.unwrap(), 0);
+    assert!(bank.get_transfer_history().await.is_empty());
+    assert!(db.get_rule("rule-1").await.unwrap().unwrap().next_run_at.is_some());
+}
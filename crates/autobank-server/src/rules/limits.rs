@@ -0,0 +1,211 @@
+//! Transfer safety limits, server-wide and per rule.
+
+use sb1_api::Money;
+use serde::{Deserialize, Serialize};
+
+/// Limits checked before every transfer. `None` disables a limit. Amounts
+/// apply in the currency of the transfer being checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferLimits {
+    /// Largest amount a single transfer may move.
+    pub max_per_transfer: Option<Money>,
+    /// Largest total moved per calendar day (Europe/Oslo).
+    pub max_per_day: Option<Money>,
+    /// Largest total moved per calendar month (Europe/Oslo).
+    pub max_per_month: Option<Money>,
+    /// Most transfers sent in a single poll cycle, scheduled run or backfill.
+    /// Approvals, retries and resent transfers are not counted.
+    pub max_transfers_per_poll: Option<u32>,
+}
+
+impl Default for TransferLimits {
+    fn default() -> Self {
+        Self {
+            max_per_transfer: Some(Money::nok(1_000_000)),
+            max_per_day: Some(Money::nok(2_500_000)),
+            max_per_month: Some(Money::nok(10_000_000)),
+            max_transfers_per_poll: Some(10),
+        }
+    }
+}
+
+/// Amounts already moved, for checking a new transfer against the limits.
+#[derive(Debug, Clone, Copy)]
+pub struct TransferTotals {
+    /// Transfers already sent in the poll, scheduled run or backfill making
+    /// this transfer, or `None` if it is not made by one.
+    pub transfers_this_poll: Option<u32>,
+    pub today: Money,
+    pub this_month: Money,
+}
+
+impl TransferLimits {
+    /// Returns the reason the transfer is blocked, or `None` if it is allowed.
+    ///
+    /// Totals in a different currency than the transfer block it, since the
+    /// amounts cannot be added up.
+    pub fn check(&self, amount: Money, totals: &TransferTotals) -> Option<String> {
+        let amount = amount.abs();
+        let bind = |max: Option<Money>| max.map(|max| max.with_currency(amount.currency()));
+
+        if let Some(max) = bind(self.max_per_transfer)
+            && amount > max
+        {
+            return Some(format!("Amount {} exceeds the per-transfer limit of {}", amount, max));
+        }
+
+        if let Some(max) = self.max_transfers_per_poll
+            && totals.transfers_this_poll.is_some_and(|sent| sent >= max)
+        {
+            return Some(format!("Limit of {} transfers per poll reached", max));
+        }
+
+        if let Some(max) = bind(self.max_per_day)
+            && !totals.today.checked_add(amount).is_some_and(|total| total <= max)
+        {
+            return Some(format!("Transfer would exceed the daily limit of {}", max));
+        }
+
+        if let Some(max) = bind(self.max_per_month)
+            && !totals.this_month.checked_add(amount).is_some_and(|total| total <= max)
+        {
+            return Some(format!("Transfer would exceed the monthly limit of {}", max));
+        }
+
+        None
+    }
+}
+
+/// Optional per-rule limits on how often and how much a rule may transfer.
+/// Amounts apply in the currency of the rule's transfers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RuleLimits {
+    /// Most transfers per calendar day (Europe/Oslo).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_transfers_per_day: Option<u32>,
+    /// Largest total transferred per calendar day (Europe/Oslo).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount_per_day: Option<Money>,
+    /// Largest total transferred per calendar month (Europe/Oslo).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_amount_per_month: Option<Money>,
+    /// Minimum time between two firings of the rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_secs: Option<i64>,
+}
+
+/// A rule's transfer history, for checking its limits.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleHistory {
+    pub transfers_today: u32,
+    pub amount_today: Money,
+    pub amount_this_month: Money,
+    pub last_transfer_at: Option<i64>,
+}
+
+impl RuleLimits {
+    /// Returns the limit that blocks a firing transferring `amount` in total at
+    /// `now`, as a short kind (used in the processing log) and a description.
+    pub fn check(&self, amount: Money, history: &RuleHistory, now: i64) -> Option<(&'static str, String)> {
+        let amount = amount.abs();
+        let bind = |max: Option<Money>| max.map(|max| max.with_currency(amount.currency()));
+
+        if let Some(cooldown) = self.cooldown_secs
+            && let Some(last) = history.last_transfer_at
+            && now - last < cooldown
+        {
+            return Some(("cooldown", format!("Rule is cooling down for {} more seconds", cooldown - (now - last))));
+        }
+
+        if let Some(max) = self.max_transfers_per_day
+            && history.transfers_today >= max
+        {
+            return Some(("daily_count", format!("Limit of {} transfers per day reached", max)));
+        }
+
+        if let Some(max) = bind(self.max_amount_per_day)
+            && !history.amount_today.checked_add(amount).is_some_and(|total| total <= max)
+        {
+            return Some(("daily_amount", format!("Transfer would exceed the rule's daily limit of {}", max)));
+        }
+
+        if let Some(max) = bind(self.max_amount_per_month)
+            && !history.amount_this_month.checked_add(amount).is_some_and(|total| total <= max)
+        {
+            return Some(("monthly_amount", format!("Transfer would exceed the rule's monthly limit of {}", max)));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(transfers_this_poll: u32, today: i64, this_month: i64) -> TransferTotals {
+        TransferTotals {
+            transfers_this_poll: Some(transfers_this_poll),
+            today: Money::nok(today),
+            this_month: Money::nok(this_month),
+        }
+    }
+
+    #[test]
+    fn test_within_limits() {
+        let limits = TransferLimits::default();
+        assert_eq!(limits.check(Money::nok(10000), &totals(0, 0, 0)), None);
+        assert_eq!(limits.check(Money::nok(-10000), &totals(9, 2_490_000, 9_990_000)), None);
+    }
+
+    #[test]
+    fn test_each_limit_blocks() {
+        let limits = TransferLimits::default();
+        assert!(limits.check(Money::nok(1_000_001), &totals(0, 0, 0)).is_some());
+        assert!(limits.check(Money::nok(100), &totals(10, 0, 0)).is_some());
+        let outside_poll = TransferTotals {
+            transfers_this_poll: None,
+            ..totals(0, 0, 0)
+        };
+        assert_eq!(limits.check(Money::nok(100), &outside_poll), None);
+        assert!(limits.check(Money::nok(100), &totals(0, 2_499_950, 0)).is_some());
+        assert!(limits.check(Money::nok(100), &totals(0, 0, 9_999_950)).is_some());
+    }
+
+    #[test]
+    fn test_disabled_limits() {
+        let limits = TransferLimits {
+            max_per_transfer: None,
+            max_per_day: None,
+            max_per_month: None,
+            max_transfers_per_poll: None,
+        };
+        assert_eq!(limits.check(Money::nok(i64::MAX / 2), &totals(1000, 0, 0)), None);
+    }
+
+    fn history(transfers_today: u32, amount_today: i64, amount_this_month: i64, last_transfer_at: Option<i64>) -> RuleHistory {
+        RuleHistory {
+            transfers_today,
+            amount_today: Money::nok(amount_today),
+            amount_this_month: Money::nok(amount_this_month),
+            last_transfer_at,
+        }
+    }
+
+    #[test]
+    fn test_rule_limits() {
+        let limits = RuleLimits {
+            max_transfers_per_day: Some(3),
+            max_amount_per_day: None,
+            max_amount_per_month: Some(Money::nok(200_000)),
+            cooldown_secs: Some(3600),
+        };
+        let amount = Money::nok(10_000);
+
+        assert_eq!(limits.check(amount, &history(2, 0, 190_000, Some(0)), 3600), None);
+        assert_eq!(limits.check(amount, &history(0, 0, 0, Some(1000)), 3600).map(|l| l.0), Some("cooldown"));
+        assert_eq!(limits.check(amount, &history(3, 0, 0, None), 0).map(|l| l.0), Some("daily_count"));
+        assert_eq!(limits.check(amount, &history(0, 0, 190_001, None), 0).map(|l| l.0), Some("monthly_amount"));
+        assert_eq!(RuleLimits::default().check(amount, &history(100, 0, i64::MAX / 2, Some(0)), 0), None);
+    }
+}
@@ -1,9 +1,19 @@
 //! Rule engine for transaction-based automation.
 
+mod aggregate;
+mod backtest;
+mod compiled;
 mod condition;
+mod context;
 mod engine;
+mod limits;
+mod schedule;
+mod trace;
 mod types;
+mod validation;
 
-pub use condition::*;
+pub use backtest::*;
 pub use engine::*;
+pub use limits::*;
 pub use types::*;
+pub use validation::*;
@@ -0,0 +1,102 @@
+//! Cron schedules for time-triggered rules.
+
+use super::types::RuleTrigger;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone};
+use chrono_tz::Tz;
+use croner::Cron;
+
+/// Time zone schedules are evaluated in.
+pub const SCHEDULE_TIMEZONE: Tz = chrono_tz::Europe::Oslo;
+
+/// How late a run may start and still count as on time. Later runs are
+/// only made if the rule catches up on missed runs.
+pub const SCHEDULE_GRACE_SECS: i64 = 60 * 60;
+
+/// How far back to look for the latest missed occurrence.
+const CATCH_UP_WINDOW_DAYS: i64 = 31;
+
+/// Start of a calendar day in [`SCHEDULE_TIMEZONE`] (Unix millis).
+pub fn local_day_start(date: NaiveDate) -> Option<i64> {
+    SCHEDULE_TIMEZONE
+        .from_local_datetime(&date.and_time(NaiveTime::MIN))
+        .earliest()
+        .map(|t| t.timestamp_millis())
+}
+
+/// A parsed cron expression.
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    cron: Cron,
+}
+
+impl Schedule {
+    /// Parse a five-field cron expression (minute, hour, day of month, month,
+    /// day of week).
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        Cron::new(expression)
+            .parse()
+            .map(|cron| Self { cron })
+            .map_err(|e| format!("Invalid cron expression '{}': {}", expression, e))
+    }
+
+    /// The first occurrence strictly after `after` (Unix seconds).
+    pub fn next_after(&self, after: i64) -> Option<i64> {
+        let start = DateTime::from_timestamp(after, 0)?.with_timezone(&SCHEDULE_TIMEZONE);
+        self.cron.find_next_occurrence(&start, false).ok().map(|t| t.timestamp())
+    }
+
+    /// The latest occurrence in `from..=to`, searching at most
+    /// [`CATCH_UP_WINDOW_DAYS`] back from `to`.
+    pub fn latest_between(&self, from: i64, to: i64) -> Option<i64> {
+        let start = from.max(to - Duration::days(CATCH_UP_WINDOW_DAYS).num_seconds());
+        let start = DateTime::from_timestamp(start, 0)?.with_timezone(&SCHEDULE_TIMEZONE);
+        self.cron
+            .iter_from(start)
+            .map(|t| t.timestamp())
+            .take_while(|&t| t <= to)
+            .last()
+    }
+}
+
+impl RuleTrigger {
+    /// The first run after `after` for scheduled rules, `None` for
+    /// transaction-triggered rules. Fails if the cron expression is invalid.
+    pub fn next_run_after(&self, after: i64) -> Result<Option<i64>, String> {
+        match self {
+            RuleTrigger::Transaction => Ok(None),
+            RuleTrigger::Schedule { cron, .. } => Ok(Schedule::parse(cron)?.next_after(after)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn oslo(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        SCHEDULE_TIMEZONE.with_ymd_and_hms(y, m, d, h, min, 0).unwrap().timestamp()
+    }
+
+    #[test]
+    fn test_next_after_uses_local_time() {
+        let schedule = Schedule::parse("0 7 25 * *").unwrap();
+        assert_eq!(schedule.next_after(oslo(2026, 1, 10, 12, 0)), Some(oslo(2026, 1, 25, 7, 0)));
+        assert_eq!(schedule.next_after(oslo(2026, 1, 25, 7, 0)), Some(oslo(2026, 2, 25, 7, 0)));
+        // Summer time: 07:00 in Oslo is 05:00 UTC
+        assert_eq!(schedule.next_after(oslo(2026, 6, 1, 0, 0)), Some(oslo(2026, 6, 25, 7, 0)));
+    }
+
+    #[test]
+    fn test_latest_between() {
+        let fridays = Schedule::parse("0 18 * * FRI").unwrap();
+        let from = oslo(2026, 3, 6, 18, 0);
+        assert_eq!(fridays.latest_between(from, oslo(2026, 3, 25, 9, 0)), Some(oslo(2026, 3, 20, 18, 0)));
+        assert_eq!(fridays.latest_between(from, oslo(2026, 3, 6, 17, 0)), None);
+    }
+
+    #[test]
+    fn test_invalid_expression() {
+        assert!(Schedule::parse("every friday").is_err());
+    }
+}
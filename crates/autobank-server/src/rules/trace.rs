@@ -0,0 +1,212 @@
+//! Evaluation traces explaining why a rule did or did not fire.
+
+use super::condition::{days_until_month_end, local_date};
+use super::context::EvalContext;
+use super::types::{Comparison, Condition, ConditionTrace};
+use chrono::Datelike;
+use sb1_api::models::BookingStatus;
+
+impl Condition {
+    /// Evaluate this condition in `ctx`, recording the values compared.
+    ///
+    /// Unlike [`Condition::evaluate`], every branch of `and` and `or` is
+    /// evaluated so the trace is complete.
+    pub fn trace(&self, ctx: &EvalContext) -> ConditionTrace {
+        let (matched, children) = match self {
+            Condition::And { conditions } => {
+                let children: Vec<_> = conditions.iter().map(|c| c.trace(ctx)).collect();
+                (children.iter().all(|t| t.matched), children)
+            }
+            Condition::Or { conditions } => {
+                let children: Vec<_> = conditions.iter().map(|c| c.trace(ctx)).collect();
+                (children.iter().any(|t| t.matched), children)
+            }
+            Condition::Not { condition } => {
+                let child = condition.trace(ctx);
+                (!child.matched, vec![child])
+            }
+            _ => (self.evaluate(ctx), Vec::new()),
+        };
+        let (actual, expected) = self.compared_values(ctx);
+
+        ConditionTrace {
+            condition: self.kind().to_string(),
+            matched,
+            actual,
+            expected,
+            children,
+        }
+    }
+
+    /// The condition type, as in the rule's JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Condition::DescriptionMatches { .. } => "description_matches",
+            Condition::AmountGreaterThan { .. } => "amount_greater_than",
+            Condition::AmountLessThan { .. } => "amount_less_than",
+            Condition::AmountBetween { .. } => "amount_between",
+            Condition::AmountEquals { .. } => "amount_equals",
+            Condition::TransactionType { .. } => "transaction_type",
+            Condition::TransactionSource { .. } => "transaction_source",
+            Condition::Currency { .. } => "currency",
+            Condition::RemoteAccountNumber { .. } => "remote_account_number",
+            Condition::RemoteNameMatches { .. } => "remote_name_matches",
+            Condition::KidOrMessageMatches { .. } => "kid_or_message_matches",
+            Condition::KidOrMessageEquals { .. } => "kid_or_message_equals",
+            Condition::IsSettled => "is_settled",
+            Condition::Weekday { .. } => "weekday",
+            Condition::DayOfMonthBetween { .. } => "day_of_month_between",
+            Condition::TimeOfDayBetween { .. } => "time_of_day_between",
+            Condition::NearMonthEnd { .. } => "near_month_end",
+            Condition::AccountBalanceBelow { .. } => "account_balance_below",
+            Condition::AccountBalanceAbove { .. } => "account_balance_above",
+            Condition::Aggregate(_) => "aggregate",
+            Condition::And { .. } => "and",
+            Condition::Or { .. } => "or",
+            Condition::Not { .. } => "not",
+        }
+    }
+
+    /// The value a leaf condition looked at and what it was compared to.
+    fn compared_values(&self, ctx: &EvalContext) -> (Option<String>, Option<String>) {
+        let tx = ctx.tx;
+        let local_time = |timezone: &Option<String>| tx.and_then(|tx| local_date(tx, timezone.as_deref()));
+
+        match self {
+            Condition::DescriptionMatches { pattern, case_insensitive } => (
+                tx.and_then(|tx| tx.cleaned_description.clone().or_else(|| tx.description.clone())),
+                Some(regex_display(pattern, *case_insensitive)),
+            ),
+            Condition::AmountGreaterThan { value } => (tx.map(|tx| tx.amount.to_string()), Some(format!("> {}", value))),
+            Condition::AmountLessThan { value } => (tx.map(|tx| tx.amount.to_string()), Some(format!("< {}", value))),
+            Condition::AmountBetween { min, max } => {
+                (tx.map(|tx| tx.amount.to_string()), Some(format!("{} to {}", min, max)))
+            }
+            Condition::AmountEquals { value, tolerance } => {
+                (tx.map(|tx| tx.amount.to_string()), Some(format!("{} ± {}", value, tolerance)))
+            }
+            Condition::TransactionType { type_code } => {
+                (tx.map(|tx| tx.type_code.to_string()), Some(type_code.to_string()))
+            }
+            Condition::TransactionSource { source } => (tx.map(|tx| tx.source.to_string()), Some(source.to_string())),
+            Condition::Currency { currency } => {
+                (tx.map(|tx| tx.amount.currency().to_string()), Some(currency.to_string()))
+            }
+            Condition::RemoteAccountNumber { number } => {
+                (tx.and_then(|tx| tx.remote_account_number.clone()), Some(number.clone()))
+            }
+            Condition::RemoteNameMatches { pattern, case_insensitive } => (
+                tx.and_then(|tx| tx.remote_account_name.clone()),
+                Some(regex_display(pattern, *case_insensitive)),
+            ),
+            Condition::KidOrMessageMatches { pattern, case_insensitive } => (
+                tx.and_then(|tx| tx.kid_or_message.clone()),
+                Some(regex_display(pattern, *case_insensitive)),
+            ),
+            Condition::KidOrMessageEquals { value } => (tx.and_then(|tx| tx.kid_or_message.clone()), Some(value.clone())),
+            Condition::IsSettled => (
+                tx.map(|tx| tx.booking_status.to_string()),
+                Some(BookingStatus::Booked.to_string()),
+            ),
+            Condition::Weekday { days, timezone } => (
+                local_time(timezone).map(|t| t.weekday().to_string()),
+                Some(days.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+            ),
+            Condition::DayOfMonthBetween { from, to, timezone } => {
+                (local_time(timezone).map(|t| t.day().to_string()), Some(format!("{} to {}", from, to)))
+            }
+            Condition::TimeOfDayBetween { from, to, timezone } => (
+                local_time(timezone).map(|t| t.format("%H:%M").to_string()),
+                Some(format!("{} to {}", from.format("%H:%M"), to.format("%H:%M"))),
+            ),
+            Condition::NearMonthEnd { days, timezone } => (
+                local_time(timezone)
+                    .and_then(|t| days_until_month_end(t.date_naive()))
+                    .map(|remaining| format!("{} days left", remaining)),
+                Some(format!("at most {} days left", days)),
+            ),
+            Condition::AccountBalanceBelow { account, value, balance } => {
+                (ctx.balance(account, *balance).map(|b| b.to_string()), Some(format!("< {}", value)))
+            }
+            Condition::AccountBalanceAbove { account, value, balance } => {
+                (ctx.balance(account, *balance).map(|b| b.to_string()), Some(format!("> {}", value)))
+            }
+            Condition::Aggregate(aggregate) => {
+                let op = match aggregate.comparison {
+                    Comparison::GreaterThan => ">",
+                    Comparison::LessThan => "<",
+                };
+                (
+                    aggregate.measure(ctx).map(|measured| measured.to_string()),
+                    Some(format!("{} {}", op, aggregate.function.value())),
+                )
+            }
+            Condition::And { .. } | Condition::Or { .. } | Condition::Not { .. } => (None, None),
+        }
+    }
+}
+
+fn regex_display(pattern: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        format!("/{}/i", pattern)
+    } else {
+        format!("/{}/", pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sb1_api::Money;
+    use sb1_api::models::Transaction;
+
+    #[test]
+    fn test_trace_records_nested_results_and_values() {
+        let tx = Transaction {
+            id: "tx-1".to_string(),
+            description: Some("REMA 1000".to_string()),
+            amount: Money::nok(-14900),
+            booking_status: BookingStatus::Pending,
+            ..Transaction::default()
+        };
+        let ctx = EvalContext::new(&tx, &[], "acc-1");
+        let condition = Condition::And {
+            conditions: vec![
+                Condition::AmountLessThan { value: Money::nok(-10000) },
+                Condition::Or {
+                    conditions: vec![
+                        Condition::DescriptionMatches { pattern: "kiwi".to_string(), case_insensitive: true },
+                        Condition::Not { condition: Box::new(Condition::IsSettled) },
+                    ],
+                },
+            ],
+        };
+
+        let trace = condition.trace(&ctx);
+        assert!(trace.matched);
+        assert_eq!(trace.matched, condition.evaluate(&ctx));
+        assert_eq!(trace.children[0].actual.as_deref(), Some("-149.00"));
+        assert_eq!(trace.children[0].expected.as_deref(), Some("< -100.00"));
+
+        let or = &trace.children[1];
+        assert_eq!(or.condition, "or");
+        assert!(!or.children[0].matched);
+        assert_eq!(or.children[0].actual.as_deref(), Some("REMA 1000"));
+        assert_eq!(or.children[0].expected.as_deref(), Some("/kiwi/i"));
+        assert!(or.children[1].matched);
+        assert_eq!(or.children[1].children[0].actual.as_deref(), Some("PENDING"));
+    }
+
+    #[test]
+    fn test_kind_matches_serialized_type() {
+        let conditions = [
+            Condition::IsSettled,
+            Condition::KidOrMessageEquals { value: "rent".to_string() },
+            Condition::Not { condition: Box::new(Condition::IsSettled) },
+        ];
+        for condition in conditions {
+            let json = serde_json::to_value(&condition).unwrap();
+            assert_eq!(json["type"], condition.kind());
+        }
+    }
+}
@@ -1,5 +1,11 @@
 //! Rule and related types.
 
+use super::RuleLimits;
+use super::condition::local_date;
+use super::schedule::SCHEDULE_TIMEZONE;
+use chrono::{NaiveTime, Weekday};
+use sb1_api::models::{BookingStatus, Transaction, TransactionSource, TransactionType};
+use sb1_api::{Currency, Money};
 use serde::{Deserialize, Serialize};
 
 /// A rule that triggers actions based on transaction conditions.
@@ -9,11 +15,144 @@ pub struct Rule {
     pub name: String,
     pub description: Option<String>,
     pub enabled: bool,
+    /// What makes the rule run.
+    #[serde(default)]
+    pub trigger: RuleTrigger,
+    /// The account watched for transactions, and the account
+    /// `trigger_account` refers to in actions and conditions.
     pub trigger_account_key: String,
+    /// Which version of a transaction the rule fires on.
+    #[serde(default)]
+    pub fire_on: FireOn,
+    /// Whether transactions created by autobank's own transfers can trigger the rule.
+    #[serde(default)]
+    pub include_self_transfers: bool,
+    /// Rate limits, cooldown and spending caps for this rule.
+    #[serde(default)]
+    pub limits: RuleLimits,
+    /// Rules on an account are evaluated highest priority first, then
+    /// oldest first.
+    #[serde(default)]
+    pub priority: i32,
+    /// Whether a match stops later rules from running on the transaction.
+    #[serde(default)]
+    pub stop_processing: bool,
+    /// Rules in the same group act on a transaction at most once between
+    /// them: the first to match wins.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// Whether transfers wait for approval as proposals instead of being
+    /// sent when the rule fires.
+    #[serde(default)]
+    pub requires_approval: bool,
     pub conditions: Vec<Condition>,
     pub actions: Vec<Action>,
     pub created_at: i64,
     pub updated_at: i64,
+    /// When the rule was created or last re-enabled. Transactions from
+    /// before this are only acted on through an explicit backfill.
+    pub activated_at: i64,
+    /// Next time a scheduled rule is due to run (Unix seconds). Maintained
+    /// by the engine; `None` for transaction-triggered rules.
+    #[serde(default)]
+    pub next_run_at: Option<i64>,
+}
+
+impl Rule {
+    /// Returns true if the transaction predates the rule's activation.
+    ///
+    /// A transaction is historical if it is dated before the activation day
+    /// in the schedule time zone, or if it was already seen (at
+    /// `first_seen_at`) before activation.
+    pub fn predates_activation(&self, tx: &Transaction, first_seen_at: i64) -> bool {
+        if first_seen_at < self.activated_at {
+            return true;
+        }
+        let tx_date = local_date(tx, None).map(|d| d.date_naive());
+        let activation_date = chrono::DateTime::from_timestamp(self.activated_at, 0)
+            .map(|d| d.with_timezone(&SCHEDULE_TIMEZONE).date_naive());
+        matches!((tx_date, activation_date), (Some(tx_date), Some(activation_date)) if tx_date < activation_date)
+    }
+}
+
+/// What makes a rule run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// New and changed transactions on the trigger account.
+    #[default]
+    Transaction,
+    /// A cron schedule (minute, hour, day of month, month, day of week) in
+    /// Norwegian time. Conditions and amounts that read the transaction
+    /// never match and evaluate to zero on scheduled runs.
+    Schedule {
+        cron: String,
+        /// Whether a run missed while the server was down is made once when
+        /// it comes back. Several missed runs are only made up once.
+        #[serde(default = "default_catch_up")]
+        catch_up: bool,
+    },
+}
+
+fn default_catch_up() -> bool {
+    true
+}
+
+impl RuleTrigger {
+    /// Returns true if the rule runs on a schedule.
+    pub fn is_scheduled(&self) -> bool {
+        matches!(self, RuleTrigger::Schedule { .. })
+    }
+}
+
+/// Lifecycle stage at which a rule fires.
+///
+/// A rule fires at most once per transaction, whichever stage it fires on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FireOn {
+    /// Only pending versions are evaluated; transactions first seen as
+    /// booked are never fired on.
+    Pending,
+    /// Only booked versions are evaluated.
+    Booked,
+    /// Every version is evaluated until the rule fires.
+    #[default]
+    FirstSeen,
+}
+
+impl FireOn {
+    /// Returns true if a tracked transaction is at a stage the rule fires on.
+    ///
+    /// Stages are read from the lifecycle timestamps rather than the current
+    /// status, so a transaction once seen as booked is not fired on as pending
+    /// should the bank report it as pending again.
+    pub fn applies_to_lifecycle(&self, tracked: &TrackedTransaction) -> bool {
+        match self {
+            FireOn::Pending => tracked.pending_seen_at.is_some() && tracked.booked_at.is_none(),
+            FireOn::Booked => tracked.booked_at.is_some(),
+            FireOn::FirstSeen => true,
+        }
+    }
+
+    /// Returns true if a transaction with this booking status should be
+    /// evaluated. Used where transactions are not tracked, as in backtests.
+    pub fn applies_to(&self, booking_status: &BookingStatus) -> bool {
+        match self {
+            FireOn::Pending => *booking_status == BookingStatus::Pending,
+            FireOn::Booked => booking_status.is_booked(),
+            FireOn::FirstSeen => true,
+        }
+    }
+
+    /// The value as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FireOn::Pending => "pending",
+            FireOn::Booked => "booked",
+            FireOn::FirstSeen => "first_seen",
+        }
+    }
 }
 
 /// Rule condition types.
@@ -28,27 +167,109 @@ pub enum Condition {
     },
 
     /// Amount greater than value.
-    AmountGreaterThan { value: f64 },
+    AmountGreaterThan { value: Money },
 
     /// Amount less than value.
-    AmountLessThan { value: f64 },
+    AmountLessThan { value: Money },
 
     /// Amount between min and max (inclusive).
-    AmountBetween { min: f64, max: f64 },
+    AmountBetween { min: Money, max: Money },
 
     /// Amount equals value within tolerance.
     AmountEquals {
-        value: f64,
+        value: Money,
         #[serde(default = "default_tolerance")]
-        tolerance: f64,
+        tolerance: Money,
     },
 
     /// Transaction type code matches.
-    TransactionType { type_code: String },
+    TransactionType { type_code: TransactionType },
+
+    /// Channel the transaction came through matches.
+    TransactionSource { source: TransactionSource },
+
+    /// Transaction currency matches.
+    Currency { currency: Currency },
+
+    /// Counterparty account number equals `number`, ignoring formatting such
+    /// as dots and spaces.
+    RemoteAccountNumber { number: String },
+
+    /// Match counterparty name with regex pattern.
+    RemoteNameMatches {
+        pattern: String,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+
+    /// Match KID or message with regex pattern.
+    KidOrMessageMatches {
+        pattern: String,
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+
+    /// KID or message equals `value`, ignoring surrounding whitespace.
+    KidOrMessageEquals { value: String },
 
     /// Only trigger on settled transactions.
     IsSettled,
 
+    /// Transaction date falls on one of the given weekdays.
+    ///
+    /// Date and time conditions use local time in `timezone`, Europe/Oslo if
+    /// not set, and never match if the time zone is unknown.
+    Weekday {
+        days: Vec<Weekday>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+
+    /// Transaction date falls on a day of the month in `from..=to`. Wraps
+    /// around the end of the month if `from` is after `to`.
+    DayOfMonthBetween {
+        from: u32,
+        to: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+
+    /// Transaction time of day is in `from..to`. Wraps around midnight if
+    /// `from` is after `to`.
+    TimeOfDayBetween {
+        from: NaiveTime,
+        to: NaiveTime,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+
+    /// Transaction date is at most `days` days before the last day of its
+    /// month. Zero matches only the last day.
+    NearMonthEnd {
+        days: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timezone: Option<String>,
+    },
+
+    /// Balance of an account is below value. False if the account is not found.
+    AccountBalanceBelow {
+        account: AccountRef,
+        value: Money,
+        #[serde(default)]
+        balance: BalanceKind,
+    },
+
+    /// Balance of an account is above value. False if the account is not found.
+    AccountBalanceAbove {
+        account: AccountRef,
+        value: Money,
+        #[serde(default)]
+        balance: BalanceKind,
+    },
+
+    /// Aggregate over stored transactions on the watched account.
+    Aggregate(Aggregate),
+
     /// Logical AND of multiple conditions.
     And { conditions: Vec<Condition> },
 
@@ -59,8 +280,83 @@ pub enum Condition {
     Not { condition: Box<Condition> },
 }
 
-fn default_tolerance() -> f64 {
-    0.01
+fn default_tolerance() -> Money {
+    Money::nok(1)
+}
+
+/// Sum, count or average of the watched account's transactions in a window,
+/// compared to the function's `value`.
+///
+/// Computed from transactions stored while polling, so only transactions
+/// autobank has seen are included. Sums and averages use absolute amounts in
+/// the transaction's currency, so `filter` decides whether income or spending
+/// is measured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Aggregate {
+    #[serde(flatten)]
+    pub function: AggregateFunction,
+    pub window: AggregateWindow,
+    /// Only transactions matching this condition are included. It is
+    /// evaluated against each transaction in the window; aggregates inside it
+    /// see no transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Box<Condition>>,
+    pub comparison: Comparison,
+}
+
+/// How transactions are aggregated, and the value the result is compared to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "function", rename_all = "snake_case")]
+pub enum AggregateFunction {
+    Sum { value: Money },
+    Count { value: u32 },
+    /// Average amount; never matches when there are no transactions.
+    Average { value: Money },
+}
+
+/// An aggregated value: a number of transactions or an amount.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggregateValue {
+    Count(u32),
+    Amount(Money),
+}
+
+/// Transactions an aggregate covers, ending at the transaction being
+/// evaluated (or the scheduled run).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AggregateWindow {
+    /// The last `days` days.
+    Rolling { days: u32 },
+    /// The current calendar day, week (from Monday) or month in Europe/Oslo.
+    Calendar { period: CalendarPeriod },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+/// How an aggregate is compared to its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    GreaterThan,
+    LessThan,
+}
+
+/// Which balance of an account to read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceKind {
+    /// Booked balance.
+    Booked,
+    /// Balance available for use, after reservations and credit.
+    #[default]
+    Available,
 }
 
 /// Rule action types.
@@ -73,9 +369,34 @@ pub enum Action {
         to_account: AccountRef,
         amount: AmountSpec,
         message: Option<String>,
+        /// Collect the amounts in a ledger and transfer them together instead
+        /// of making one transfer per match.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        accumulate: Option<Accumulate>,
     },
 }
 
+impl Action {
+    /// Returns true if the action collects amounts instead of transferring them directly.
+    pub fn accumulates(&self) -> bool {
+        matches!(self, Action::Transfer { accumulate: Some(_), .. })
+    }
+}
+
+/// When the amounts collected by an accumulating transfer are sent.
+///
+/// The collected total is transferred once it reaches `threshold`, or at the
+/// first run of `flush_schedule` after the oldest collected amount, whichever
+/// comes first. With neither set, amounts are collected but never sent.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Accumulate {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<Money>,
+    /// Cron expression, in the same format and time zone as scheduled rules.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush_schedule: Option<String>,
+}
+
 /// Reference to an account.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -93,20 +414,68 @@ pub enum AccountRef {
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum AmountSpec {
     /// Fixed amount.
-    Fixed { value: f64 },
+    Fixed { value: Money },
     /// Same amount as the transaction.
     TransactionAmount,
     /// Absolute value of the transaction amount.
     TransactionAmountAbs,
-    /// Percentage of the transaction amount.
+    /// Percentage of the transaction amount, rounded to whole øre.
     Percentage { of_transaction: f64 },
     /// Minimum of multiple specs.
     Min { specs: Vec<AmountSpec> },
     /// Maximum of multiple specs.
     Max { specs: Vec<AmountSpec> },
+    /// What rounding the transaction amount up to the nearest multiple of
+    /// `to` adds, e.g. 3.00 for a purchase of 147.00 with `to` 10.00.
+    RoundUp { to: Money },
+    /// Balance of an account, or zero if the account is not found.
+    AccountBalance {
+        account: AccountRef,
+        #[serde(default)]
+        balance: BalanceKind,
+    },
+    /// How far an account's available balance is above `threshold`, to sweep
+    /// the excess. Zero if the account is not found.
+    ExcessAbove { account: AccountRef, threshold: Money },
+    /// How far an account's available balance is below `target`, to top it
+    /// up. Zero if the account is not found.
+    TopUpTo { account: AccountRef, target: Money },
+    /// Another spec limited to `min..=max`. Limits in a different currency
+    /// than the amount are ignored.
+    Clamp {
+        spec: Box<AmountSpec>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        min: Option<Money>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max: Option<Money>,
+    },
+}
+
+/// An amount collected by an accumulating transfer action.
+#[derive(Debug, Clone, Serialize)]
+pub struct AccumulatorEntry {
+    pub id: i64,
+    pub rule_id: String,
+    pub action_index: i64,
+    /// Transaction (or scheduled run) the amount was collected for.
+    pub source_id: String,
+    pub amount: Money,
+    pub created_at: i64,
+    /// Execution that transferred the amount; `None` while it is waiting.
+    pub flush_execution_id: Option<String>,
 }
 
-/// Tracked transaction for deduplication.
+/// Amounts waiting to be flushed for one accumulating action.
+#[derive(Debug, Clone, Copy)]
+pub struct PendingAccumulation {
+    pub total: Money,
+    /// Highest entry id included in `total`.
+    pub up_to_id: i64,
+    /// When the oldest included amount was collected.
+    pub oldest_at: i64,
+}
+
+/// Tracked transaction for deduplication and lifecycle tracking.
 #[derive(Debug, Clone)]
 pub struct TrackedTransaction {
     pub id: String,
@@ -115,11 +484,19 @@ pub struct TrackedTransaction {
     pub first_seen_at: i64,
     pub last_updated_at: i64,
     pub settled: bool,
+    /// When the transaction was first seen as pending, if ever.
+    pub pending_seen_at: Option<i64>,
+    /// When the transaction was first seen as booked, if ever.
+    pub booked_at: Option<i64>,
     pub raw_data: String,
 }
 
 /// Log entry for rule-transaction processing.
-#[derive(Debug, Clone)]
+///
+/// `action_taken` is `skipped` when the conditions did not match,
+/// `limited:<kind>` when one of the rule's limits stopped it from firing, or
+/// `executed:<statuses>` with the status of each action.
+#[derive(Debug, Clone, Serialize)]
 pub struct RuleTransactionLog {
     pub id: String,
     pub rule_id: String,
@@ -127,21 +504,157 @@ pub struct RuleTransactionLog {
     pub transaction_fingerprint: String,
     pub action_taken: String,
     pub processed_at: i64,
+    /// How each of the rule's conditions evaluated. Missing on entries
+    /// logged before traces were recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub condition_trace: Option<Vec<ConditionTrace>>,
 }
 
-/// Record of a rule execution (successful transfer).
+/// How a condition evaluated, with the values it compared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConditionTrace {
+    /// Condition type, as in the rule's JSON.
+    pub condition: String,
+    pub matched: bool,
+    /// The value the condition looked at, if there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<String>,
+    /// What the value was compared to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    /// Traces of nested conditions in `and`, `or` and `not`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ConditionTrace>,
+}
+
+/// Record of a rule execution (transfer attempt).
+///
+/// Executions are written as `pending` before the transfer is sent to the
+/// bank and finalized as `success` or `failed` afterwards. A row left
+/// `pending` by a crash is reconciled to `unknown` on startup. Transfers
+/// stopped by a safety limit are recorded as `blocked` and never sent.
+/// Transfers that failed transiently wait as `retrying` until their next
+/// attempt, and end up `dead_letter` when the attempts run out.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RuleExecution {
     pub id: String,
     pub rule_id: String,
-    pub transaction_id: String,
+    /// Transaction that triggered the execution; `None` for scheduled runs.
+    pub transaction_id: Option<String>,
+    /// Scheduled run the execution belongs to (Unix seconds).
+    pub scheduled_for: Option<i64>,
+    /// Identifies the action this execution performs; at most one execution
+    /// exists per key, so an action is never sent to the bank twice.
+    pub idempotency_key: String,
     pub transfer_payment_id: Option<String>,
-    pub amount: f64,
+    pub amount: Money,
     pub from_account: String,
     pub to_account: String,
     pub status: String,
     pub error_message: Option<String>,
     pub executed_at: i64,
+    /// Bank transaction on the source account that resulted from this transfer.
+    pub debit_transaction_id: Option<String>,
+    /// Bank transaction on the destination account that resulted from this transfer.
+    pub credit_transaction_id: Option<String>,
+    /// Message sent with the transfer.
+    pub message: Option<String>,
+    /// Number of times the transfer has been sent to the bank.
+    pub attempts: i64,
+    /// When a `retrying` transfer is sent again (Unix seconds).
+    pub next_retry_at: Option<i64>,
+}
+
+impl RuleExecution {
+    /// Execution is written but the bank has not confirmed the transfer yet.
+    pub const PENDING: &'static str = "pending";
+    /// The bank accepted the transfer.
+    pub const SUCCESS: &'static str = "success";
+    /// The bank rejected the transfer or the request failed permanently.
+    pub const FAILED: &'static str = "failed";
+    /// The request failed transiently; the transfer is sent again at `next_retry_at`.
+    pub const RETRYING: &'static str = "retrying";
+    /// The request kept failing transiently until the attempts ran out.
+    pub const DEAD_LETTER: &'static str = "dead_letter";
+    /// The process stopped before the bank's response was recorded.
+    pub const UNKNOWN: &'static str = "unknown";
+    /// The transfer was not sent because of a safety limit or the emergency stop.
+    pub const BLOCKED: &'static str = "blocked";
+
+    /// Idempotency key for the action at `action_index` of a rule, applied
+    /// to a transaction or a scheduled run (`schedule:<timestamp>`).
+    pub fn idempotency_key(rule_id: &str, source_id: &str, action_index: usize) -> String {
+        format!("{}:{}:{}", rule_id, source_id, action_index)
+    }
+
+    /// Prefix of the marker added to transfer messages.
+    const MARKER_PREFIX: &'static str = "autobank:";
+    /// Number of characters of the execution ID in a marker.
+    const MARKER_ID_LEN: usize = 8;
+
+    /// Marker identifying the execution in its transfer's message, so the
+    /// resulting bank transactions can be matched back to it.
+    pub fn marker(&self) -> String {
+        let id_len = Self::MARKER_ID_LEN.min(self.id.len());
+        format!("{}{}", Self::MARKER_PREFIX, &self.id[..id_len])
+    }
+
+    /// Message sent with the transfer: the rule's message followed by the marker.
+    pub fn transfer_message(&self) -> String {
+        match self.message.as_deref() {
+            Some(message) if !message.is_empty() => format!("{} {}", message, self.marker()),
+            _ => self.marker(),
+        }
+    }
+
+    /// The start of the execution ID in a marker found in `text`, if any.
+    pub fn find_marker(text: &str) -> Option<&str> {
+        let start = text.find(Self::MARKER_PREFIX)? + Self::MARKER_PREFIX.len();
+        let id = text.get(start..start + Self::MARKER_ID_LEN)?;
+        id.chars().all(|c| c.is_ascii_hexdigit()).then_some(id)
+    }
+}
+
+/// A transfer proposed by a rule that requires approval.
+///
+/// A proposal is `pending` until it is approved, rejected or expires.
+/// Approving it sends the transfer under the idempotency key the rule would
+/// have used, so it is sent at most once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferProposal {
+    pub id: String,
+    pub rule_id: String,
+    /// Transaction that triggered the proposal; `None` for scheduled runs.
+    pub transaction_id: Option<String>,
+    /// Scheduled run the proposal belongs to (Unix seconds).
+    pub scheduled_for: Option<i64>,
+    pub action_index: i64,
+    pub idempotency_key: String,
+    pub amount: Money,
+    pub from_account: String,
+    pub to_account: String,
+    pub message: Option<String>,
+    pub status: String,
+    pub created_at: i64,
+    /// After this the proposal can no longer be approved.
+    pub expires_at: i64,
+    /// When the proposal was approved or rejected.
+    pub decided_at: Option<i64>,
+    /// Who approved or rejected the proposal.
+    pub decided_by: Option<String>,
+    /// Status of the execution made on approval.
+    pub execution_status: Option<String>,
+}
+
+impl TransferProposal {
+    /// Waiting for a decision.
+    pub const PENDING: &'static str = "pending";
+    /// Approved; the transfer was attempted.
+    pub const APPROVED: &'static str = "approved";
+    /// Rejected; nothing was sent.
+    pub const REJECTED: &'static str = "rejected";
+    /// Not decided on before it expired; nothing was sent.
+    pub const EXPIRED: &'static str = "expired";
 }
 
 /// Decision on whether to process a transaction.
@@ -151,6 +664,6 @@ pub enum ProcessingDecision {
     Process,
     /// Skip processing (already handled this version).
     Skip { reason: String },
-    /// Wait for more data (transaction not settled).
+    /// Wait for more data (transaction not at the stage the rule fires on).
     Wait { reason: String },
 }
@@ -0,0 +1,392 @@
+//! Rule validation before saving.
+
+use super::context::EvalContext;
+use super::schedule::Schedule;
+use super::types::{AccountRef, Action, AggregateFunction, AggregateWindow, AmountSpec, Condition, Rule, RuleTrigger};
+use chrono_tz::Tz;
+use regex::Regex;
+use sb1_api::models::Account;
+use serde::Serialize;
+
+/// A problem with one field of a rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationError {
+    /// Path to the field, like `conditions[0].pattern`.
+    pub field: String,
+    pub message: String,
+}
+
+impl Rule {
+    /// Check the rule against the user's accounts. Returns every problem
+    /// found; an empty list means the rule is valid.
+    pub fn validate(&self, accounts: &[Account]) -> Vec<ValidationError> {
+        let mut validator = Validator {
+            ctx: EvalContext::without_trigger(accounts, &self.trigger_account_key),
+            errors: Vec::new(),
+        };
+        validator.rule(self);
+        validator.errors
+    }
+}
+
+struct Validator<'a> {
+    ctx: EvalContext<'a>,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validator<'a> {
+    fn error(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.errors.push(ValidationError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    fn rule(&mut self, rule: &Rule) {
+        if rule.name.trim().is_empty() {
+            self.error("name", "Name is required");
+        }
+        if let RuleTrigger::Schedule { cron, .. } = &rule.trigger
+            && let Err(e) = Schedule::parse(cron)
+        {
+            self.error("trigger.cron", e);
+        }
+        if self.ctx.account(&AccountRef::TriggerAccount).is_none() {
+            self.error("trigger_account_key", format!("Unknown account '{}'", rule.trigger_account_key));
+        }
+        if rule.limits.cooldown_secs.is_some_and(|secs| secs < 0) {
+            self.error("limits.cooldown_secs", "Cooldown cannot be negative");
+        }
+
+        for (i, condition) in rule.conditions.iter().enumerate() {
+            self.condition(&format!("conditions[{}]", i), condition, false);
+        }
+
+        if rule.actions.is_empty() {
+            self.error("actions", "At least one action is required");
+        }
+        for (i, action) in rule.actions.iter().enumerate() {
+            self.action(&format!("actions[{}]", i), action);
+        }
+        if rule.requires_approval && rule.actions.iter().any(Action::accumulates) {
+            self.error("requires_approval", "Accumulated transfers cannot require approval");
+        }
+    }
+
+    fn condition(&mut self, path: &str, condition: &Condition, in_filter: bool) {
+        match condition {
+            Condition::DescriptionMatches { pattern, case_insensitive }
+            | Condition::RemoteNameMatches { pattern, case_insensitive }
+            | Condition::KidOrMessageMatches { pattern, case_insensitive } => {
+                self.pattern(&format!("{}.pattern", path), pattern, *case_insensitive);
+            }
+            Condition::AmountBetween { min, max } => {
+                if min > max {
+                    self.error(format!("{}.min", path), "Minimum is greater than maximum");
+                }
+            }
+            Condition::AmountEquals { tolerance, .. } => {
+                if tolerance.is_negative() {
+                    self.error(format!("{}.tolerance", path), "Tolerance cannot be negative");
+                }
+            }
+            Condition::AccountBalanceBelow { account, .. } | Condition::AccountBalanceAbove { account, .. } => {
+                self.account_exists(&format!("{}.account", path), account);
+            }
+            Condition::Weekday { days, timezone } => {
+                if days.is_empty() {
+                    self.error(format!("{}.days", path), "At least one weekday is required");
+                }
+                self.timezone(path, timezone.as_deref());
+            }
+            Condition::DayOfMonthBetween { from, to, timezone } => {
+                for (field, day) in [("from", from), ("to", to)] {
+                    if !(1..=31).contains(day) {
+                        self.error(format!("{}.{}", path, field), "Day of month must be between 1 and 31");
+                    }
+                }
+                self.timezone(path, timezone.as_deref());
+            }
+            Condition::TimeOfDayBetween { from, to, timezone } => {
+                if from == to {
+                    self.error(format!("{}.to", path), "Time window is empty");
+                }
+                self.timezone(path, timezone.as_deref());
+            }
+            Condition::NearMonthEnd { timezone, .. } => self.timezone(path, timezone.as_deref()),
+            Condition::RemoteAccountNumber { number } => {
+                if !number.chars().any(|c| c.is_ascii_digit()) {
+                    self.error(format!("{}.number", path), "Account number has no digits");
+                }
+            }
+            Condition::Aggregate(aggregate) => {
+                if in_filter {
+                    self.error(path, "Aggregates cannot be used inside an aggregate filter");
+                }
+                if matches!(aggregate.window, AggregateWindow::Rolling { days: 0 }) {
+                    self.error(format!("{}.window.days", path), "Window must be at least one day");
+                }
+                if let AggregateFunction::Sum { value } | AggregateFunction::Average { value } = aggregate.function
+                    && value.is_negative()
+                {
+                    self.error(format!("{}.value", path), "Amount must not be negative");
+                }
+                if let Some(filter) = &aggregate.filter {
+                    self.condition(&format!("{}.filter", path), filter, true);
+                }
+            }
+            Condition::And { conditions } | Condition::Or { conditions } => {
+                if conditions.is_empty() {
+                    self.error(format!("{}.conditions", path), "At least one condition is required");
+                }
+                for (i, c) in conditions.iter().enumerate() {
+                    self.condition(&format!("{}.conditions[{}]", path, i), c, in_filter);
+                }
+            }
+            Condition::Not { condition } => self.condition(&format!("{}.condition", path), condition, in_filter),
+            Condition::AmountGreaterThan { .. }
+            | Condition::AmountLessThan { .. }
+            | Condition::TransactionType { .. }
+            | Condition::TransactionSource { .. }
+            | Condition::Currency { .. }
+            | Condition::KidOrMessageEquals { .. }
+            | Condition::IsSettled => {}
+        }
+    }
+
+    fn action(&mut self, path: &str, action: &Action) {
+        match action {
+            Action::Transfer {
+                from_account,
+                to_account,
+                amount,
+                accumulate,
+                ..
+            } => {
+                let from = self.account_exists(&format!("{}.from_account", path), from_account);
+                let to = self.account_exists(&format!("{}.to_account", path), to_account);
+                if let Some(from) = from
+                    && (!from.account_properties.is_transfer_from_enabled || from.account_properties.is_blocked)
+                {
+                    self.error(format!("{}.from_account", path), format!("Transfers from '{}' are not allowed", from.name));
+                }
+                if let Some(to) = to
+                    && (!to.account_properties.is_transfer_to_enabled || to.account_properties.is_blocked)
+                {
+                    self.error(format!("{}.to_account", path), format!("Transfers to '{}' are not allowed", to.name));
+                }
+                if let (Some(from), Some(to)) = (from, to)
+                    && from.key == to.key
+                {
+                    self.error(format!("{}.to_account", path), "Cannot transfer to the same account");
+                }
+
+                self.amount(&format!("{}.amount", path), amount);
+
+                if let Some(accumulate) = accumulate {
+                    if accumulate.threshold.is_none() && accumulate.flush_schedule.is_none() {
+                        self.error(format!("{}.accumulate", path), "A threshold or flush schedule is required");
+                    }
+                    if accumulate.threshold.is_some_and(|t| !t.is_positive()) {
+                        self.error(format!("{}.accumulate.threshold", path), "Threshold must be positive");
+                    }
+                    if let Some(Err(e)) = accumulate.flush_schedule.as_deref().map(Schedule::parse) {
+                        self.error(format!("{}.accumulate.flush_schedule", path), e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn amount(&mut self, path: &str, spec: &AmountSpec) {
+        match spec {
+            AmountSpec::Fixed { value } => {
+                if !value.is_positive() {
+                    self.error(format!("{}.value", path), "Amount must be positive");
+                }
+            }
+            AmountSpec::Percentage { of_transaction } => {
+                if !of_transaction.is_finite() || *of_transaction <= 0.0 {
+                    self.error(format!("{}.of_transaction", path), "Percentage must be positive");
+                }
+            }
+            AmountSpec::Min { specs } | AmountSpec::Max { specs } => {
+                if specs.is_empty() {
+                    self.error(format!("{}.specs", path), "At least one amount is required");
+                }
+                for (i, s) in specs.iter().enumerate() {
+                    self.amount(&format!("{}.specs[{}]", path, i), s);
+                }
+            }
+            AmountSpec::RoundUp { to } => {
+                if !to.is_positive() {
+                    self.error(format!("{}.to", path), "Rounding unit must be positive");
+                }
+            }
+            AmountSpec::AccountBalance { account, .. }
+            | AmountSpec::ExcessAbove { account, .. }
+            | AmountSpec::TopUpTo { account, .. } => {
+                self.account_exists(&format!("{}.account", path), account);
+            }
+            AmountSpec::Clamp { spec, min, max } => {
+                if let (Some(min), Some(max)) = (min, max)
+                    && min > max
+                {
+                    self.error(format!("{}.min", path), "Minimum is greater than maximum");
+                }
+                self.amount(&format!("{}.spec", path), spec);
+            }
+            AmountSpec::TransactionAmount | AmountSpec::TransactionAmountAbs => {}
+        }
+    }
+
+    /// Report an error if the account is not found; returns the account.
+    fn account_exists(&mut self, path: &str, account_ref: &AccountRef) -> Option<&'a Account> {
+        let account = self.ctx.account(account_ref);
+        if account.is_none() {
+            let message = match account_ref {
+                AccountRef::ByKey { key } => format!("Unknown account '{}'", key),
+                AccountRef::ByNumber { number } => format!("Unknown account number '{}'", number),
+                AccountRef::TriggerAccount => "Trigger account not found".to_string(),
+            };
+            self.error(path, message);
+        }
+        account
+    }
+
+    fn pattern(&mut self, path: &str, pattern: &str, case_insensitive: bool) {
+        let pattern = if case_insensitive {
+            format!("(?i){}", pattern)
+        } else {
+            pattern.to_string()
+        };
+        if let Err(e) = Regex::new(&pattern) {
+            self.error(path, format!("Invalid pattern: {}", e));
+        }
+    }
+
+    fn timezone(&mut self, path: &str, timezone: Option<&str>) {
+        if let Some(name) = timezone
+            && name.parse::<Tz>().is_err()
+        {
+            self.error(format!("{}.timezone", path), format!("Unknown time zone '{}'", name));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{FireOn, RuleLimits};
+    use sb1_api::Money;
+    use sb1_api::models::AccountProperties;
+
+    fn account(key: &str, transfers_enabled: bool) -> Account {
+        Account {
+            key: key.to_string(),
+            account_number: format!("{}-number", key),
+            name: key.to_string(),
+            account_properties: AccountProperties {
+                is_transfer_from_enabled: transfers_enabled,
+                is_transfer_to_enabled: transfers_enabled,
+                ..AccountProperties::default()
+            },
+            ..Account::default()
+        }
+    }
+
+    fn transfer(to: &str, amount: AmountSpec) -> Action {
+        Action::Transfer {
+            from_account: AccountRef::TriggerAccount,
+            to_account: AccountRef::ByKey { key: to.to_string() },
+            amount,
+            message: None,
+            accumulate: None,
+        }
+    }
+
+    fn rule(conditions: Vec<Condition>, actions: Vec<Action>) -> Rule {
+        Rule {
+            id: "rule-1".to_string(),
+            name: "Test".to_string(),
+            description: None,
+            enabled: true,
+            trigger: RuleTrigger::Transaction,
+            trigger_account_key: "checking".to_string(),
+            fire_on: FireOn::default(),
+            include_self_transfers: false,
+            limits: RuleLimits::default(),
+            priority: 0,
+            stop_processing: false,
+            group: None,
+            requires_approval: false,
+            conditions,
+            actions,
+            created_at: 0,
+            updated_at: 0,
+            activated_at: 0,
+            next_run_at: None,
+        }
+    }
+
+    fn fields(errors: &[ValidationError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_rule() {
+        let accounts = [account("checking", true), account("savings", true)];
+        let rule = rule(
+            vec![Condition::DescriptionMatches { pattern: "^REMA".to_string(), case_insensitive: true }],
+            vec![transfer("savings", AmountSpec::Fixed { value: Money::nok(1000) })],
+        );
+        assert_eq!(rule.validate(&accounts), vec![]);
+    }
+
+    #[test]
+    fn test_field_level_errors() {
+        let accounts = [account("checking", true), account("savings", true), account("loan", false)];
+        let rule = rule(
+            vec![
+                Condition::Or {
+                    conditions: vec![
+                        Condition::DescriptionMatches { pattern: "(unclosed".to_string(), case_insensitive: false },
+                        Condition::AmountBetween { min: Money::nok(500), max: Money::nok(100) },
+                    ],
+                },
+                Condition::AccountBalanceBelow {
+                    account: AccountRef::ByKey { key: "missing".to_string() },
+                    value: Money::nok(0),
+                    balance: Default::default(),
+                },
+            ],
+            vec![
+                transfer("checking", AmountSpec::Min { specs: vec![] }),
+                transfer("loan", AmountSpec::TransactionAmountAbs),
+            ],
+        );
+
+        assert_eq!(
+            fields(&rule.validate(&accounts)),
+            vec![
+                "conditions[0].conditions[0].pattern",
+                "conditions[0].conditions[1].min",
+                "conditions[1].account",
+                "actions[0].to_account",
+                "actions[0].amount.specs",
+                "actions[1].to_account",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_accounts_and_schedule() {
+        let mut rule = rule(vec![], vec![transfer("savings", AmountSpec::TransactionAmountAbs)]);
+        rule.trigger = RuleTrigger::Schedule { cron: "sometimes".to_string(), catch_up: false };
+
+        assert_eq!(
+            fields(&rule.validate(&[])),
+            vec!["trigger.cron", "trigger_account_key", "actions[0].from_account", "actions[0].to_account"]
+        );
+    }
+}
@@ -1,4 +1,4 @@
-//! Polling scheduler for periodic transaction checks.
+//! Polling scheduler for periodic transaction checks and scheduled rules.
 
 use crate::rules::RuleEngine;
 use std::sync::Arc;
@@ -38,8 +38,14 @@ impl Scheduler {
     }
 
     /// Run the scheduler loop.
+    ///
+    /// Scheduled rules that came due while the server was down are run
+    /// before the first poll.
     pub async fn run(&self, mut shutdown: broadcast::Receiver<()>) {
         info!("Scheduler started");
+        if self.is_enabled().await {
+            self.run_scheduled_rules().await;
+        }
 
         loop {
             let interval = {
@@ -82,6 +88,35 @@ impl Scheduler {
                 error!("Poll cycle failed: {}", e);
             }
         }
+
+        self.run_scheduled_rules().await;
+
+        match self.rule_engine.flush_accumulators().await {
+            Ok(0) => {}
+            Ok(flushed) => debug!("Flushed {} accumulated transfers", flushed),
+            Err(e) => error!("Flushing accumulated transfers failed: {}", e),
+        }
+
+        match self.rule_engine.retry_failed_transfers().await {
+            Ok(0) => {}
+            Ok(retried) => debug!("Retried {} failed transfers", retried),
+            Err(e) => error!("Retrying failed transfers failed: {}", e),
+        }
+
+        match self.rule_engine.expire_proposals().await {
+            Ok(0) => {}
+            Ok(expired) => debug!("Expired {} transfer proposals", expired),
+            Err(e) => error!("Expiring transfer proposals failed: {}", e),
+        }
+    }
+
+    /// Run scheduled rules that are due.
+    async fn run_scheduled_rules(&self) {
+        match self.rule_engine.run_scheduled().await {
+            Ok(0) => {}
+            Ok(ran) => debug!("Ran {} scheduled rules", ran),
+            Err(e) => error!("Running scheduled rules failed: {}", e),
+        }
     }
 
     /// Update the scheduler configuration.
@@ -1,15 +1,16 @@
 //! Integration tests for the rule engine.
 
 use sb1_api::models::{
-    Account, AccountData, AccountNumber, AccountProperties, ClassificationInput, Transaction,
-    TransactionResponse,
+    Account, AccountData, AccountNumber, AccountProperties, AccountType, BookingStatus,
+    ClassificationInput, Transaction, TransactionQuery, TransactionResponse, TransactionSource,
+    TransactionType,
 };
 use sb1_api::mock::TransferRecord;
-use sb1_api::{BankApiClient, MockBankClient};
+use sb1_api::{BankApiClient, MockBankClient, Money};
 use std::sync::Arc;
 
 /// Create a test account for testing
-fn create_test_account(key: &str, name: &str, number: &str, balance: f64) -> Account {
+fn create_test_account(key: &str, name: &str, number: &str, balance: Money) -> Account {
     Account {
         key: key.to_string(),
         account_number: number.to_string(),
@@ -21,7 +22,7 @@ fn create_test_account(key: &str, name: &str, number: &str, balance: f64) -> Acc
         currency_code: "NOK".to_string(),
         owner: None,
         product_type: "CURRENT".to_string(),
-        type_field: "ACCOUNT".to_string(),
+        type_field: AccountType::Account,
         product_id: None,
         description_code: None,
         account_properties: AccountProperties::default(),
@@ -34,9 +35,9 @@ fn create_test_account(key: &str, name: &str, number: &str, balance: f64) -> Acc
 fn create_test_transaction(
     id: &str,
     account_key: &str,
-    amount: f64,
+    amount: Money,
     description: &str,
-    booking_status: &str,
+    booking_status: BookingStatus,
 ) -> Transaction {
     Transaction {
         id: id.to_string(),
@@ -51,13 +52,13 @@ fn create_test_transaction(
         amount,
         date: 1739577600, // 2025-02-15
         interest_date: None,
-        type_code: "PURCHASE".to_string(),
+        type_code: TransactionType::Purchase,
         type_text: "Varekjøp".to_string(),
         currency_code: "NOK".to_string(),
         can_show_details: true,
-        source: "ONLINE".to_string(),
+        source: TransactionSource::Online,
         is_confidential: false,
-        booking_status: booking_status.to_string(),
+        booking_status,
         account_name: "Checking Account".to_string(),
         account_key: account_key.to_string(),
         account_currency: "NOK".to_string(),
@@ -85,8 +86,8 @@ mod rule_engine_tests {
 
         let accounts = AccountData {
             accounts: vec![
-                create_test_account("checking", "Checking Account", "12345678901", 10000.0),
-                create_test_account("savings", "Savings Account", "12345678902", 50000.0),
+                create_test_account("checking", "Checking Account", "12345678901", Money::nok(1000000)),
+                create_test_account("savings", "Savings Account", "12345678902", Money::nok(5000000)),
             ],
             errors: vec![],
         };
@@ -97,11 +98,12 @@ mod rule_engine_tests {
             transactions: vec![create_test_transaction(
                 "tx-001",
                 "checking",
-                -149.0, // Netflix subscription
+                Money::nok(-14900), // Netflix subscription
                 "NETFLIX.COM",
-                "BOOKED",
+                BookingStatus::Booked,
             )],
             errors: vec![],
+            next_cursor: None,
         };
         mock_client.set_transactions("checking", transactions).await;
 
@@ -109,7 +111,9 @@ mod rule_engine_tests {
         let fetched_accounts = mock_client.get_accounts().await.unwrap();
         assert_eq!(fetched_accounts.accounts.len(), 2);
 
-        let fetched_txns = mock_client.get_transactions("checking").await.unwrap();
+        let fetched_txns = mock_client
+            .get_transactions("checking", &TransactionQuery::default())
+            .await.unwrap();
         assert_eq!(fetched_txns.transactions.len(), 1);
         assert_eq!(
             fetched_txns.transactions[0].cleaned_description,
@@ -124,8 +128,8 @@ mod rule_engine_tests {
         // Setup accounts
         let accounts = AccountData {
             accounts: vec![
-                create_test_account("checking", "Checking", "12345678901", 10000.0),
-                create_test_account("savings", "Savings", "12345678902", 50000.0),
+                create_test_account("checking", "Checking", "12345678901", Money::nok(1000000)),
+                create_test_account("savings", "Savings", "12345678902", Money::nok(5000000)),
             ],
             errors: vec![],
         };
@@ -166,22 +170,22 @@ mod condition_tests {
 
     #[test]
     fn test_transaction_has_expected_fields() {
-        let tx = create_test_transaction("tx-1", "account-1", -99.99, "SPOTIFY", "BOOKED");
+        let tx = create_test_transaction("tx-1", "account-1", Money::nok(-9999), "SPOTIFY", BookingStatus::Booked);
 
         assert_eq!(tx.id, "tx-1");
         assert_eq!(tx.account_key, "account-1");
-        assert_eq!(tx.amount, -99.99);
+        assert_eq!(tx.amount, Money::nok(-9999));
         assert_eq!(tx.cleaned_description, Some("SPOTIFY".to_string()));
-        assert_eq!(tx.booking_status, "BOOKED");
+        assert_eq!(tx.booking_status, BookingStatus::Booked);
     }
 
     #[test]
     fn test_accounts_have_expected_fields() {
-        let acc = create_test_account("key-1", "My Account", "11112222333", 1234.56);
+        let acc = create_test_account("key-1", "My Account", "11112222333", Money::nok(123456));
 
         assert_eq!(acc.key, "key-1");
         assert_eq!(acc.name, "My Account");
         assert_eq!(acc.account_number, "11112222333");
-        assert_eq!(acc.balance, 1234.56);
+        assert_eq!(acc.balance, Money::nok(123456));
     }
 }
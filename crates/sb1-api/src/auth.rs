@@ -1,36 +1,67 @@
 //! OAuth authentication for SpareBank 1 API.
 
-use crate::config::{read_token_data, save_token_data, AppConfig};
+use crate::config::{read_token_data_from, save_token_data_to, token_file_path, AppConfig};
 use crate::error::ApiError;
 use crate::models::TokenData;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::debug;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, info};
 
 const TOKEN_ENDPOINT: &str = "https://api.sparebank1.no/oauth/token";
 
+/// Refresh access tokens this many seconds before they actually expire.
+const REFRESH_MARGIN_SECS: i64 = 60;
+
 /// Provider trait for obtaining access tokens.
 #[async_trait::async_trait]
 pub trait TokenProvider: Send + Sync {
     /// Returns a valid access token, refreshing if necessary.
     async fn get_access_token(&self) -> Result<String, ApiError>;
+
+    /// Returns a new access token after the API rejected `rejected`.
+    ///
+    /// Providers that cannot refresh return the current token, in which case
+    /// the caller gives up instead of retrying.
+    async fn refresh_access_token(&self, rejected: &str) -> Result<String, ApiError> {
+        let _ = rejected;
+        self.get_access_token().await
+    }
 }
 
 /// File-based token provider that stores tokens on disk.
+///
+/// Access tokens are refreshed shortly before they expire. Refreshes are
+/// serialized through a lock so concurrent callers trigger a single refresh.
 pub struct FileTokenProvider {
     config: AppConfig,
+    token_path: PathBuf,
+    token_endpoint: String,
     token_data: Arc<RwLock<Option<TokenData>>>,
+    refresh_lock: Mutex<()>,
     http_client: reqwest::Client,
 }
 
 impl FileTokenProvider {
     /// Creates a new file-based token provider.
     pub fn new(config: AppConfig) -> Result<Self, ApiError> {
-        let token_data = read_token_data()?;
+        Self::with_endpoint(config, token_file_path()?, TOKEN_ENDPOINT)
+    }
+
+    /// Creates a provider with a custom token file and token endpoint (for testing).
+    pub fn with_endpoint(
+        config: AppConfig,
+        token_path: PathBuf,
+        token_endpoint: impl Into<String>,
+    ) -> Result<Self, ApiError> {
+        let token_data = read_token_data_from(&token_path)?;
 
         Ok(Self {
             config,
+            token_path,
+            token_endpoint: token_endpoint.into(),
             token_data: Arc::new(RwLock::new(token_data)),
+            refresh_lock: Mutex::new(()),
             http_client: reqwest::Client::new(),
         })
     }
@@ -48,7 +79,7 @@ impl FileTokenProvider {
 
         let response = self
             .http_client
-            .post(TOKEN_ENDPOINT)
+            .post(&self.token_endpoint)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .form(&params)
             .send()
@@ -66,7 +97,53 @@ impl FileTokenProvider {
         let token_data: TokenData = response.json().await?;
         debug!("Token refreshed successfully");
 
-        Ok(token_data)
+        Ok(token_data.issued(chrono::Utc::now().timestamp()))
+    }
+
+    /// Persists token data to disk and updates the cached copy.
+    async fn store_token(&self, token_data: TokenData) -> Result<(), ApiError> {
+        save_token_data_to(&self.token_path, &token_data)?;
+        *self.token_data.write().await = Some(token_data);
+        Ok(())
+    }
+
+    /// Refreshes the access token under the refresh lock.
+    ///
+    /// With `rejected` set, the refresh is forced unless the cached token has
+    /// already been replaced since it was handed out. Without it, the token is
+    /// only refreshed if it is about to expire. Either way, callers waiting on
+    /// the lock reuse the token obtained by whoever refreshed first.
+    async fn refresh(&self, rejected: Option<&str>) -> Result<String, ApiError> {
+        let _guard = self.refresh_lock.lock().await;
+
+        let current = self.token_data.read().await.clone().ok_or(ApiError::NoToken)?;
+        let now = chrono::Utc::now().timestamp();
+
+        let stale = match rejected {
+            Some(token) => current.access_token == token,
+            None => current.needs_refresh(now, REFRESH_MARGIN_SECS),
+        };
+        if !stale {
+            return Ok(current.access_token);
+        }
+
+        if current.refresh_token_expired(now) {
+            return Err(ApiError::Auth(
+                "Refresh token has expired, re-authorization is required".into(),
+            ));
+        }
+
+        let new_data = self.refresh_token(&current.refresh_token).await?;
+        let access_token = new_data.access_token.clone();
+        self.store_token(new_data).await?;
+
+        info!("Access token refreshed");
+        Ok(access_token)
+    }
+
+    /// Forces a refresh of an access token the API has rejected.
+    pub async fn refresh_rejected(&self, rejected: &str) -> Result<String, ApiError> {
+        self.refresh(Some(rejected)).await
     }
 
     /// Exchanges an authorization code for tokens.
@@ -83,7 +160,7 @@ impl FileTokenProvider {
 
         let response = self
             .http_client
-            .post(TOKEN_ENDPOINT)
+            .post(&self.token_endpoint)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .form(&params)
             .send()
@@ -99,11 +176,11 @@ impl FileTokenProvider {
         }
 
         let token_data: TokenData = response.json().await?;
+        let token_data = token_data.issued(chrono::Utc::now().timestamp());
         debug!("Access token obtained successfully");
 
         // Save and cache the new token
-        save_token_data(&token_data)?;
-        *self.token_data.write().await = Some(token_data.clone());
+        self.store_token(token_data.clone()).await?;
 
         Ok(token_data)
     }
@@ -123,41 +200,23 @@ impl FileTokenProvider {
 #[async_trait::async_trait]
 impl TokenProvider for FileTokenProvider {
     async fn get_access_token(&self) -> Result<String, ApiError> {
-        // Check if we have a token
         let token_data = self.token_data.read().await.clone();
 
         match token_data {
-            Some(data) => {
-                // Try to use existing token - if it fails, try refresh
-                // For now, we just return the token and let the API call handle expiry
-                // In a more robust implementation, we'd check expiry time
+            Some(data) if !data.needs_refresh(chrono::Utc::now().timestamp(), REFRESH_MARGIN_SECS) => {
                 Ok(data.access_token)
             }
+            Some(_) => self.refresh(None).await,
             None => Err(ApiError::NoToken),
         }
     }
+
+    async fn refresh_access_token(&self, rejected: &str) -> Result<String, ApiError> {
+        self.refresh_rejected(rejected).await
+    }
 }
 
 /// Attempts to get a valid token, refreshing if necessary.
 pub async fn ensure_authenticated(provider: &FileTokenProvider) -> Result<String, ApiError> {
-    let token_data = provider.token_data.read().await.clone();
-
-    match token_data {
-        Some(data) => {
-            // Try refresh if we have a refresh token
-            match provider.refresh_token(&data.refresh_token).await {
-                Ok(new_data) => {
-                    save_token_data(&new_data)?;
-                    *provider.token_data.write().await = Some(new_data.clone());
-                    Ok(new_data.access_token)
-                }
-                Err(_) => {
-                    // Refresh failed, return existing token and hope it works
-                    // or caller will need to re-authenticate
-                    Ok(data.access_token)
-                }
-            }
-        }
-        None => Err(ApiError::NoToken),
-    }
+    provider.get_access_token().await
 }
@@ -3,12 +3,15 @@
 use crate::auth::TokenProvider;
 use crate::error::ApiError;
 use crate::models::{
-    AccountData, CreateTransferDTO, TransactionResponse, TransferResponse, TransferToCreditCardDTO,
+    AccountData, CreateTransferDTO, TransactionQuery, TransactionResponse, TransferResponse,
+    TransferToCreditCardDTO,
 };
+use crate::retry::{RetryPolicy, is_retryable_status};
 use async_trait::async_trait;
+use reqwest::StatusCode;
 use reqwest::header::{ACCEPT, AUTHORIZATION, HeaderMap, HeaderValue};
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
 const BASE_URL: &str = "https://api.sparebank1.no";
 const ACCEPT_HEADER: &str = "application/vnd.sparebank1.v1+json; charset=utf-8";
@@ -19,8 +22,12 @@ pub trait BankApiClient: Send + Sync {
     /// Fetches all accounts for the authenticated user.
     async fn get_accounts(&self) -> Result<AccountData, ApiError>;
 
-    /// Fetches transactions for a specific account.
-    async fn get_transactions(&self, account_key: &str) -> Result<TransactionResponse, ApiError>;
+    /// Fetches transactions for a specific account matching the query.
+    async fn get_transactions(
+        &self,
+        account_key: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionResponse, ApiError>;
 
     /// Creates a transfer between accounts.
     async fn create_transfer(&self, transfer: CreateTransferDTO) -> Result<TransferResponse, ApiError>;
@@ -37,16 +44,13 @@ pub struct SpareBank1Client {
     http_client: reqwest::Client,
     base_url: String,
     token_provider: Arc<dyn TokenProvider>,
+    retry_policy: RetryPolicy,
 }
 
 impl SpareBank1Client {
     /// Creates a new SpareBank 1 API client.
     pub fn new(token_provider: Arc<dyn TokenProvider>) -> Self {
-        Self {
-            http_client: reqwest::Client::new(),
-            base_url: BASE_URL.to_string(),
-            token_provider,
-        }
+        Self::with_base_url(token_provider, BASE_URL.to_string())
     }
 
     /// Creates a new client with a custom base URL (for testing).
@@ -55,13 +59,18 @@ impl SpareBank1Client {
             http_client: reqwest::Client::new(),
             base_url,
             token_provider,
+            retry_policy: RetryPolicy::default(),
         }
     }
 
-    /// Builds headers with authentication.
-    async fn build_headers(&self) -> Result<HeaderMap, ApiError> {
-        let access_token = self.token_provider.get_access_token().await?;
+    /// Sets the retry policy used for idempotent requests.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
 
+    /// Builds headers with authentication.
+    fn build_headers(&self, access_token: &str) -> Result<HeaderMap, ApiError> {
         let mut headers = HeaderMap::new();
         headers.insert(
             AUTHORIZATION,
@@ -73,23 +82,76 @@ impl SpareBank1Client {
         Ok(headers)
     }
 
+    /// Sends a request and returns the final status and body.
+    ///
+    /// A 401 response is retried once with a refreshed token, since the request
+    /// was rejected before being processed. Transient failures (timeouts, 429
+    /// and 5xx) are only retried for `idempotent` requests, following the
+    /// client's retry policy.
+    async fn send(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+        idempotent: bool,
+    ) -> Result<(StatusCode, String), ApiError> {
+        let mut access_token = self.token_provider.get_access_token().await?;
+        let mut token_refreshed = false;
+        let mut attempt = 1;
+
+        loop {
+            let headers = self.build_headers(&access_token)?;
+
+            let response = match request().headers(headers).send().await {
+                Ok(response) => response,
+                Err(e) if idempotent && (e.is_timeout() || e.is_connect()) && self.retry_policy.can_retry(attempt) => {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!("Request failed ({}), retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let status = response.status();
+
+            if status == StatusCode::UNAUTHORIZED && !token_refreshed {
+                token_refreshed = true;
+                let refreshed = self.token_provider.refresh_access_token(&access_token).await?;
+                if refreshed != access_token {
+                    debug!("Access token rejected, retrying with refreshed token");
+                    access_token = refreshed;
+                    continue;
+                }
+            }
+
+            if idempotent
+                && is_retryable_status(status)
+                && self.retry_policy.can_retry(attempt)
+                && let Some(delay) = self.retry_policy.delay_for(attempt, response.headers())
+            {
+                warn!("Request returned {}, retrying in {:?}", status, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+
+            let text = response.text().await?;
+            return Ok((status, text));
+        }
+    }
+
     /// Makes a GET request to the API.
     async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
-        let headers = self.build_headers().await?;
         let url = format!("{}{}", self.base_url, path);
 
         debug!("GET {}", url);
 
-        let response = self.http_client.get(&url).headers(headers).send().await?;
-
-        let status = response.status();
-        let text = response.text().await?;
+        let (status, text) = self.send(|| self.http_client.get(&url), true).await?;
 
         if !status.is_success() {
-            return Err(ApiError::Api {
-                code: status.as_str().to_string(),
+            return Err(ApiError::Status {
+                status: status.as_u16(),
                 message: text,
-                trace_id: String::new(),
             });
         }
 
@@ -97,42 +159,35 @@ impl SpareBank1Client {
     }
 
     /// Makes a POST request to the API.
+    ///
+    /// POSTs are not idempotent and are never retried after reaching the bank.
     async fn post<T: serde::de::DeserializeOwned, B: serde::Serialize>(
         &self,
         path: &str,
         body: &B,
     ) -> Result<T, ApiError> {
-        let headers = self.build_headers().await?;
         let url = format!("{}{}", self.base_url, path);
 
         debug!("POST {}", url);
 
-        let response = self
-            .http_client
-            .post(&url)
-            .headers(headers)
-            .json(body)
-            .send()
+        let (status, text) = self
+            .send(|| self.http_client.post(&url).json(body), false)
             .await?;
 
-        let status = response.status();
-        let text = response.text().await?;
-
         if !status.is_success() {
             // Try to parse as API error
-            if let Ok(error_response) = serde_json::from_str::<TransferResponse>(&text) {
-                if let Some(error) = error_response.errors.first() {
-                    return Err(ApiError::Api {
-                        code: error.code.clone(),
-                        message: error.message.clone(),
-                        trace_id: error.trace_id.clone(),
-                    });
-                }
+            if let Ok(error_response) = serde_json::from_str::<TransferResponse>(&text)
+                && let Some(error) = error_response.errors.first()
+            {
+                return Err(ApiError::Api {
+                    code: error.code.clone(),
+                    message: error.message.clone(),
+                    trace_id: error.trace_id.clone(),
+                });
             }
-            return Err(ApiError::Api {
-                code: status.as_str().to_string(),
+            return Err(ApiError::Status {
+                status: status.as_u16(),
                 message: text,
-                trace_id: String::new(),
             });
         }
 
@@ -147,9 +202,34 @@ impl BankApiClient for SpareBank1Client {
             .await
     }
 
-    async fn get_transactions(&self, account_key: &str) -> Result<TransactionResponse, ApiError> {
-        let path = format!("/personal/banking/transactions?accountKey={}", account_key);
-        self.get(&path).await
+    async fn get_transactions(
+        &self,
+        account_key: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionResponse, ApiError> {
+        let mut path = format!(
+            "/personal/banking/transactions?accountKey={}",
+            urlencoding::encode(account_key)
+        );
+        if let Some(from_date) = query.from_date {
+            path.push_str(&format!("&fromDate={}", from_date));
+        }
+        if let Some(to_date) = query.to_date {
+            path.push_str(&format!("&toDate={}", to_date));
+        }
+
+        // The bank has no paging of its own, so the cursor is an offset into
+        // the window: fetch everything up to the end of the requested page
+        // (plus one row to detect a following page) and cut it out locally.
+        if let Some(row_limit) = query.row_limit {
+            let rows = query.offset() + row_limit as usize + 1;
+            path.push_str(&format!("&rowLimit={}", rows));
+        }
+
+        let response: TransactionResponse = self.get(&path).await?;
+        let mut page = query.paginate(response.transactions);
+        page.errors = response.errors;
+        Ok(page)
     }
 
     async fn create_transfer(&self, transfer: CreateTransferDTO) -> Result<TransferResponse, ApiError> {
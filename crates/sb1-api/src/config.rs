@@ -3,7 +3,7 @@
 use crate::error::ApiError;
 use crate::models::TokenData;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tracing::debug;
 
 /// Application configuration loaded from config file.
@@ -67,13 +67,16 @@ pub fn load_config() -> Result<AppConfig, ApiError> {
 
 /// Reads the stored access token from file.
 pub fn read_token_data() -> Result<Option<TokenData>, ApiError> {
-    let token_path = token_file_path()?;
+    read_token_data_from(&token_file_path()?)
+}
 
+/// Reads stored token data from the given path.
+pub fn read_token_data_from(token_path: &Path) -> Result<Option<TokenData>, ApiError> {
     if !token_path.exists() {
         return Ok(None);
     }
 
-    let content = std::fs::read_to_string(&token_path)?;
+    let content = std::fs::read_to_string(token_path)?;
     let token_data: TokenData = serde_json::from_str(&content)?;
 
     Ok(Some(token_data))
@@ -81,10 +84,19 @@ pub fn read_token_data() -> Result<Option<TokenData>, ApiError> {
 
 /// Saves token data to file.
 pub fn save_token_data(token_data: &TokenData) -> Result<(), ApiError> {
-    let token_path = token_file_path()?;
+    save_token_data_to(&token_file_path()?, token_data)
+}
 
+/// Saves token data to the given path.
+///
+/// The data is written to a temporary file next to the target and then renamed
+/// into place, so a crash mid-write never leaves a truncated token file behind.
+pub fn save_token_data_to(token_path: &Path, token_data: &TokenData) -> Result<(), ApiError> {
     let json_content = serde_json::to_string_pretty(token_data)?;
-    std::fs::write(&token_path, json_content)?;
+
+    let tmp_path = token_path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json_content)?;
+    std::fs::rename(&tmp_path, token_path)?;
 
     debug!("Token data saved to {}", token_path.display());
 
@@ -21,6 +21,10 @@ pub enum ApiError {
         trace_id: String,
     },
 
+    /// API returned a non-success HTTP status without a structured error body
+    #[error("HTTP status {status}: {message}")]
+    Status { status: u16, message: String },
+
     /// Failed to parse response
     #[error("Parse error: {0}")]
     Parse(#[from] serde_json::Error),
@@ -37,3 +41,36 @@ pub enum ApiError {
     #[error("No access token available")]
     NoToken,
 }
+
+impl ApiError {
+    /// HTTP status code of the failed response, if there was one.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ApiError::Status { status, .. } => Some(*status),
+            ApiError::Http(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the request certainly did not take effect: the
+    /// connection could not be made, or the server turned it away with
+    /// `429 Too Many Requests`. A timeout or 5xx response leaves it open
+    /// whether the request was carried out.
+    pub fn is_unprocessed(&self) -> bool {
+        match self {
+            ApiError::Http(e) if e.is_connect() => true,
+            _ => self.status() == Some(429),
+        }
+    }
+
+    /// Returns true if the same request may succeed when sent again later.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ApiError::Http(e) => e.is_timeout() || e.is_connect(),
+            ApiError::Status { status, .. } => reqwest::StatusCode::from_u16(*status)
+                .map(crate::retry::is_retryable_status)
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+}
@@ -28,8 +28,12 @@ pub mod config;
 pub mod error;
 pub mod mock;
 pub mod models;
+pub mod money;
+pub mod retry;
 
 pub use auth::{FileTokenProvider, TokenProvider};
 pub use client::{BankApiClient, SpareBank1Client};
 pub use error::ApiError;
 pub use mock::{MockBankClient, MockTokenProvider};
+pub use money::{Currency, Money};
+pub use retry::RetryPolicy;
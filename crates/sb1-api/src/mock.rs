@@ -3,8 +3,10 @@
 use crate::auth::TokenProvider;
 use crate::client::BankApiClient;
 use crate::error::ApiError;
+use crate::money::Money;
 use crate::models::{
-    AccountData, CreateTransferDTO, TransactionResponse, TransferResponse, TransferToCreditCardDTO,
+    AccountData, BookingStatus, CreateTransferDTO, Transaction, TransactionQuery, TransactionResponse,
+    TransactionType, TransferResponse, TransferToCreditCardDTO,
 };
 use async_trait::async_trait;
 use std::collections::{HashMap, VecDeque};
@@ -12,13 +14,23 @@ use tokio::sync::RwLock;
 
 /// Mock token provider for testing.
 pub struct MockTokenProvider {
-    token: String,
+    token: RwLock<String>,
+    refreshed_token: Option<String>,
 }
 
 impl MockTokenProvider {
     pub fn new(token: impl Into<String>) -> Self {
         Self {
-            token: token.into(),
+            token: RwLock::new(token.into()),
+            refreshed_token: None,
+        }
+    }
+
+    /// Creates a provider that hands out `refreshed` once a token is rejected.
+    pub fn with_refresh(token: impl Into<String>, refreshed: impl Into<String>) -> Self {
+        Self {
+            token: RwLock::new(token.into()),
+            refreshed_token: Some(refreshed.into()),
         }
     }
 }
@@ -26,7 +38,15 @@ impl MockTokenProvider {
 #[async_trait]
 impl TokenProvider for MockTokenProvider {
     async fn get_access_token(&self) -> Result<String, ApiError> {
-        Ok(self.token.clone())
+        Ok(self.token.read().await.clone())
+    }
+
+    async fn refresh_access_token(&self, _rejected: &str) -> Result<String, ApiError> {
+        let mut token = self.token.write().await;
+        if let Some(refreshed) = &self.refreshed_token {
+            *token = refreshed.clone();
+        }
+        Ok(token.clone())
     }
 }
 
@@ -34,10 +54,17 @@ impl TokenProvider for MockTokenProvider {
 pub struct MockBankClient {
     accounts: RwLock<AccountData>,
     transactions: RwLock<HashMap<String, TransactionResponse>>,
-    transfer_results: RwLock<VecDeque<Result<TransferResponse, ApiError>>>,
+    transfer_results: RwLock<VecDeque<QueuedResult>>,
     transfer_history: RwLock<Vec<TransferRecord>>,
 }
 
+/// Result queued for a transfer call.
+enum QueuedResult {
+    Response(Result<TransferResponse, ApiError>),
+    /// The transfer goes through but the caller gets this error.
+    Lost(ApiError),
+}
+
 /// Record of a transfer attempt.
 #[derive(Debug, Clone)]
 pub enum TransferRecord {
@@ -71,7 +98,57 @@ impl MockBankClient {
 
     /// Queues a transfer result to be returned on the next transfer call.
     pub async fn queue_transfer_result(&self, result: Result<TransferResponse, ApiError>) {
-        self.transfer_results.write().await.push_back(result);
+        self.transfer_results.write().await.push_back(QueuedResult::Response(result));
+    }
+
+    /// Queues an error to be returned on the next transfer call after the
+    /// transfer has gone through, as when the response is lost to a timeout.
+    /// A regular transfer is added to the transactions of its source account.
+    pub async fn queue_lost_transfer_response(&self, error: ApiError) {
+        self.transfer_results.write().await.push_back(QueuedResult::Lost(error));
+    }
+
+    /// Returns the next queued result, or success if none is queued.
+    async fn next_transfer_result(&self, transfer: Option<&CreateTransferDTO>) -> Result<TransferResponse, ApiError> {
+        let queued = self.transfer_results.write().await.pop_front();
+        match queued {
+            Some(QueuedResult::Response(result)) => result,
+            Some(QueuedResult::Lost(error)) => {
+                if let Some(transfer) = transfer {
+                    self.book_transfer(transfer).await;
+                }
+                Err(error)
+            }
+            None => Ok(TransferResponse {
+                errors: vec![],
+                payment_id: Some("mock-payment-id".to_string()),
+                status: Some("COMPLETED".to_string()),
+            }),
+        }
+    }
+
+    /// Books a transfer as the newest transaction on its source account.
+    async fn book_transfer(&self, transfer: &CreateTransferDTO) {
+        let accounts = self.accounts.read().await;
+        let Some(account) = accounts.accounts.iter().find(|a| a.account_number == transfer.from_account) else {
+            return;
+        };
+        let amount: Money = transfer.amount.parse().unwrap_or_default();
+
+        let mut transactions = self.transactions.write().await;
+        let response = transactions.entry(account.key.clone()).or_default();
+        let transaction = Transaction {
+            id: format!("mock-transfer-{}", response.transactions.len()),
+            amount: -amount,
+            date: chrono::Utc::now().timestamp_millis(),
+            type_code: TransactionType::Transfer,
+            booking_status: BookingStatus::Booked,
+            account_key: account.key.clone(),
+            kid_or_message: transfer.message.clone(),
+            remote_account_number: Some(transfer.to_account.clone()),
+            ..Transaction::default()
+        };
+        response.transactions.insert(0, transaction);
     }
 
     /// Returns all transfer attempts made.
@@ -97,11 +174,15 @@ impl BankApiClient for MockBankClient {
         Ok(self.accounts.read().await.clone())
     }
 
-    async fn get_transactions(&self, account_key: &str) -> Result<TransactionResponse, ApiError> {
+    async fn get_transactions(
+        &self,
+        account_key: &str,
+        query: &TransactionQuery,
+    ) -> Result<TransactionResponse, ApiError> {
         let transactions = self.transactions.read().await;
         transactions
             .get(account_key)
-            .cloned()
+            .map(|response| query.apply(response.transactions.iter().cloned()))
             .ok_or_else(|| ApiError::Api {
                 code: "NOT_FOUND".to_string(),
                 message: format!("No transactions for account {}", account_key),
@@ -114,20 +195,10 @@ impl BankApiClient for MockBankClient {
         self.transfer_history
             .write()
             .await
-            .push(TransferRecord::Regular(transfer));
+            .push(TransferRecord::Regular(transfer.clone()));
 
         // Return queued result or default success
-        self.transfer_results
-            .write()
-            .await
-            .pop_front()
-            .unwrap_or_else(|| {
-                Ok(TransferResponse {
-                    errors: vec![],
-                    payment_id: Some("mock-payment-id".to_string()),
-                    status: Some("COMPLETED".to_string()),
-                })
-            })
+        self.next_transfer_result(Some(&transfer)).await
     }
 
     async fn create_credit_card_transfer(
@@ -141,26 +212,18 @@ impl BankApiClient for MockBankClient {
             .push(TransferRecord::CreditCard(transfer));
 
         // Return queued result or default success
-        self.transfer_results
-            .write()
-            .await
-            .pop_front()
-            .unwrap_or_else(|| {
-                Ok(TransferResponse {
-                    errors: vec![],
-                    payment_id: Some("mock-payment-id".to_string()),
-                    status: Some("COMPLETED".to_string()),
-                })
-            })
+        self.next_transfer_result(None).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{Account, AccountProperties};
+    use crate::Money;
+    use crate::models::{Account, AccountProperties, AccountType, Transaction};
+    use chrono::NaiveDate;
 
-    fn create_test_account(key: &str, name: &str, balance: f64) -> Account {
+    fn create_test_account(key: &str, name: &str, balance: Money) -> Account {
         Account {
             key: key.to_string(),
             account_number: format!("1234567890{}", key),
@@ -172,7 +235,7 @@ mod tests {
             currency_code: "NOK".to_string(),
             owner: None,
             product_type: "CURRENT".to_string(),
-            type_field: "ACCOUNT".to_string(),
+            type_field: AccountType::Account,
             product_id: None,
             description_code: None,
             account_properties: AccountProperties::default(),
@@ -187,8 +250,8 @@ mod tests {
 
         let accounts = AccountData {
             accounts: vec![
-                create_test_account("1", "Checking", 1000.0),
-                create_test_account("2", "Savings", 5000.0),
+                create_test_account("1", "Checking", Money::nok(100000)),
+                create_test_account("2", "Savings", Money::nok(500000)),
             ],
             errors: vec![],
         };
@@ -198,7 +261,7 @@ mod tests {
         let result = client.get_accounts().await.unwrap();
         assert_eq!(result.accounts.len(), 2);
         assert_eq!(result.accounts[0].name, "Checking");
-        assert_eq!(result.accounts[1].balance, 5000.0);
+        assert_eq!(result.accounts[1].balance, Money::nok(500000));
     }
 
     #[tokio::test]
@@ -247,4 +310,74 @@ mod tests {
         let result = client.create_transfer(transfer).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_mock_client_lost_response() {
+        let client = MockBankClient::new();
+        client
+            .set_accounts(AccountData {
+                accounts: vec![create_test_account("1", "Checking", Money::nok(100000))],
+                errors: vec![],
+            })
+            .await;
+        client
+            .queue_lost_transfer_response(ApiError::Status {
+                status: 504,
+                message: "Gateway Timeout".to_string(),
+            })
+            .await;
+
+        let transfer = CreateTransferDTO {
+            amount: "100.50".to_string(),
+            due_date: None,
+            message: Some("Savings".to_string()),
+            to_account: "2".to_string(),
+            from_account: "12345678901".to_string(),
+            currency_code: None,
+        };
+
+        let result = client.create_transfer(transfer).await;
+        assert_eq!(result.unwrap_err().status(), Some(504));
+
+        // The transfer went through all the same
+        let transactions = client.get_transactions("1", &TransactionQuery::default()).await.unwrap().transactions;
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].amount, Money::nok(-10050));
+        assert_eq!(transactions[0].kid_or_message.as_deref(), Some("Savings"));
+    }
+
+    #[tokio::test]
+    async fn test_mock_client_transaction_query() {
+        let client = MockBankClient::new();
+        let day = 86_400_000;
+
+        let transactions = (0..5)
+            .map(|i| Transaction {
+                id: format!("tx-{}", i),
+                date: 1707753600000 - i * day, // 2024-02-12 and earlier
+                ..Transaction::default()
+            })
+            .collect();
+        client
+            .set_transactions(
+                "1",
+                TransactionResponse {
+                    transactions,
+                    errors: vec![],
+                    next_cursor: None,
+                },
+            )
+            .await;
+
+        let query = TransactionQuery::since(NaiveDate::from_ymd_opt(2024, 2, 9).unwrap()).limit(2);
+        let first = client.get_transactions("1", &query).await.unwrap();
+        assert_eq!(first.transactions.len(), 2);
+        assert_eq!(first.transactions[0].id, "tx-0");
+
+        let cursor = first.next_cursor.expect("should have a second page");
+        let second = client.get_transactions("1", &query.clone().after(cursor)).await.unwrap();
+        assert_eq!(second.transactions.len(), 2);
+        assert_eq!(second.transactions[1].id, "tx-3");
+        assert!(second.next_cursor.is_none());
+    }
 }
@@ -1,4 +1,6 @@
-use serde::{Deserialize, Serialize};
+use super::AccountType;
+use crate::money::{Currency, Money};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -9,28 +11,46 @@ pub struct AccountData {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(remote = "Self", rename_all = "camelCase")]
 pub struct Account {
     pub key: String,
     pub account_number: String,
     pub iban: String,
     pub name: String,
     pub description: String,
-    pub balance: f64,
-    pub available_balance: f64,
+    pub balance: Money,
+    pub available_balance: Money,
     pub currency_code: String,
     pub owner: Option<Owner>,
     pub product_type: String,
     #[serde(rename = "type")]
-    pub type_field: String,
+    pub type_field: AccountType,
     pub product_id: Option<String>,
     pub description_code: Option<String>,
     pub account_properties: AccountProperties,
-    pub credit_card_credit_limit: Option<f64>,
+    pub credit_card_credit_limit: Option<Money>,
     #[serde(rename = "creditCardAccountID")]
     pub credit_card_account_id: Option<String>,
 }
 
+impl Serialize for Account {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Account::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Account {
+    /// Binds the balances to the account's `currencyCode`.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut account = Account::deserialize(deserializer)?;
+        let currency = Currency::parse(&account.currency_code).unwrap_or_default();
+        account.balance = account.balance.with_currency(currency);
+        account.available_balance = account.available_balance.with_currency(currency);
+        account.credit_card_credit_limit = account.credit_card_credit_limit.map(|l| l.with_currency(currency));
+        Ok(account)
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Owner {
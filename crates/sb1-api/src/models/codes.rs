@@ -0,0 +1,149 @@
+//! Code values sent by the bank as plain strings.
+//!
+//! Each enum lists the values we know about and keeps anything else in an
+//! `Other` variant, so new codes from the bank still deserialize. All of them
+//! serialize back to the exact string they were read from.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+macro_rules! code_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $( $(#[$variant_meta:meta])* $variant:ident => $code:literal, )+
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $name {
+            $( $(#[$variant_meta])* $variant, )+
+            /// A value not known to this version of the client.
+            Other(String),
+        }
+
+        impl $name {
+            /// The code as sent by the bank.
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $( $name::$variant => $code, )+
+                    $name::Other(code) => code,
+                }
+            }
+        }
+
+        impl Default for $name {
+            /// An empty code, for placeholder values.
+            fn default() -> Self {
+                $name::Other(String::new())
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(code: &str) -> Self {
+                match code {
+                    $( $code => $name::$variant, )+
+                    other => $name::Other(other.to_string()),
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(code: String) -> Self {
+                match code.as_str() {
+                    $( $code => $name::$variant, )+
+                    _ => $name::Other(code),
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer).map($name::from)
+            }
+        }
+    };
+}
+
+code_enum! {
+    /// Whether a transaction has been booked or is still reserved.
+    pub enum BookingStatus {
+        /// Settled on the account.
+        Booked => "BOOKED",
+        /// Reserved but not yet settled; amount and description may change.
+        Pending => "PENDING",
+    }
+}
+
+code_enum! {
+    /// Transaction type code.
+    pub enum TransactionType {
+        Purchase => "PURCHASE",
+        Transfer => "TRANSFER",
+        Salary => "SALARY",
+        Visa => "VISA",
+    }
+}
+
+code_enum! {
+    /// Channel the transaction originated from.
+    pub enum TransactionSource {
+        Card => "CARD",
+        Transfer => "TRANSFER",
+        Online => "ONLINE",
+        Visa => "VISA",
+    }
+}
+
+code_enum! {
+    /// Kind of account.
+    pub enum AccountType {
+        Account => "ACCOUNT",
+        CreditCard => "CREDITCARD",
+    }
+}
+
+impl BookingStatus {
+    /// Returns true if the transaction is booked.
+    pub fn is_booked(&self) -> bool {
+        *self == BookingStatus::Booked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_codes_roundtrip() {
+        let status: BookingStatus = serde_json::from_str("\"PENDING\"").unwrap();
+        assert_eq!(status, BookingStatus::Pending);
+        assert_eq!(serde_json::to_string(&status).unwrap(), "\"PENDING\"");
+        assert_eq!(AccountType::from("CREDITCARD"), AccountType::CreditCard);
+    }
+
+    #[test]
+    fn test_unknown_code_is_preserved() {
+        let kind: TransactionType = serde_json::from_str("\"REFUND\"").unwrap();
+        assert_eq!(kind, TransactionType::Other("REFUND".to_string()));
+        assert_eq!(kind.to_string(), "REFUND");
+        assert_eq!(serde_json::to_string(&kind).unwrap(), "\"REFUND\"");
+    }
+
+    #[test]
+    fn test_codes_are_case_sensitive() {
+        assert_eq!(BookingStatus::from("booked"), BookingStatus::Other("booked".to_string()));
+    }
+}
@@ -1,11 +1,13 @@
 //! Data models for SpareBank 1 API responses.
 
 mod accounts;
+mod codes;
 mod token;
 mod transactions;
 mod transfers;
 
 pub use accounts::*;
+pub use codes::*;
 pub use token::*;
 pub use transactions::*;
 pub use transfers::*;
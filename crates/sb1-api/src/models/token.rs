@@ -8,4 +8,51 @@ pub struct TokenData {
     pub refresh_token_absolute_expires_in: u64,
     pub token_type: String,
     pub refresh_token: String,
+    /// Unix timestamp (seconds) when the token was issued.
+    ///
+    /// Not part of the token endpoint response; stamped locally when the token
+    /// is received so the relative `*_expires_in` values can be resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<i64>,
+}
+
+impl TokenData {
+    /// Returns the token with `issued_at` set to the given timestamp.
+    pub fn issued(mut self, now: i64) -> Self {
+        self.issued_at = Some(now);
+        self
+    }
+
+    /// Unix timestamp when the access token expires, if the issue time is known.
+    pub fn access_token_expires_at(&self) -> Option<i64> {
+        self.issued_at.map(|issued| issued + self.expires_in as i64)
+    }
+
+    /// Unix timestamp when the refresh token expires, if the issue time is known.
+    ///
+    /// This is the earlier of the sliding and the absolute refresh token expiry.
+    pub fn refresh_token_expires_at(&self) -> Option<i64> {
+        let lifetime = self
+            .refresh_token_expires_in
+            .min(self.refresh_token_absolute_expires_in);
+        self.issued_at.map(|issued| issued + lifetime as i64)
+    }
+
+    /// Returns true if the access token expires within `margin` seconds of `now`.
+    ///
+    /// Tokens without a known issue time are always considered due for refresh.
+    pub fn needs_refresh(&self, now: i64, margin: i64) -> bool {
+        match self.access_token_expires_at() {
+            Some(expires_at) => now + margin >= expires_at,
+            None => true,
+        }
+    }
+
+    /// Returns true if the refresh token can no longer be used at `now`.
+    pub fn refresh_token_expired(&self, now: i64) -> bool {
+        match self.refresh_token_expires_at() {
+            Some(expires_at) => now >= expires_at,
+            None => false,
+        }
+    }
 }
@@ -1,4 +1,7 @@
-use serde::{Deserialize, Serialize};
+use super::{BookingStatus, TransactionSource, TransactionType};
+use crate::money::{Currency, Money};
+use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -6,26 +9,29 @@ use serde_json::Value;
 pub struct TransactionResponse {
     pub transactions: Vec<Transaction>,
     pub errors: Vec<Value>,
+    /// Cursor for the next page, if more transactions match the query.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(remote = "Self", rename_all = "camelCase")]
 pub struct Transaction {
     pub id: String,
     pub non_unique_id: String,
     pub description: Option<String>,
     pub cleaned_description: Option<String>,
     pub account_number: AccountNumber,
-    pub amount: f64,
+    pub amount: Money,
     pub date: i64,
     pub interest_date: Option<i64>,
-    pub type_code: String,
+    pub type_code: TransactionType,
     pub type_text: String,
     pub currency_code: String,
     pub can_show_details: bool,
-    pub source: String,
+    pub source: TransactionSource,
     pub is_confidential: bool,
-    pub booking_status: String,
+    pub booking_status: BookingStatus,
     pub account_name: String,
     pub account_key: String,
     pub account_currency: String,
@@ -36,6 +42,24 @@ pub struct Transaction {
     pub kid_or_message: Option<String>,
 }
 
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Transaction::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Transaction {
+    /// Binds the amounts to the transaction's `currencyCode`, which the bank
+    /// sends separately from the plain-number amounts.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut tx = Transaction::deserialize(deserializer)?;
+        let currency = Currency::parse(&tx.currency_code).unwrap_or_default();
+        tx.amount = tx.amount.with_currency(currency);
+        tx.classification_input.amount = tx.classification_input.amount.with_currency(currency);
+        Ok(tx)
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AccountNumber {
@@ -48,9 +72,104 @@ pub struct AccountNumber {
 #[serde(rename_all = "camelCase")]
 pub struct ClassificationInput {
     pub id: String,
-    pub amount: f64,
+    pub amount: Money,
     #[serde(rename = "type")]
     pub type_field: String,
     pub text: Option<String>,
     pub date: i64,
 }
+
+/// Query parameters for fetching transactions.
+///
+/// All fields are optional; an empty query returns the bank's default window.
+/// Pagination uses an opaque cursor: pass `next_cursor` from the previous
+/// response to fetch the following (older) page.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionQuery {
+    /// Earliest booking date to include.
+    pub from_date: Option<NaiveDate>,
+    /// Latest booking date to include.
+    pub to_date: Option<NaiveDate>,
+    /// Maximum number of transactions per page.
+    pub row_limit: Option<u32>,
+    /// Cursor returned as `next_cursor` by the previous page.
+    pub cursor: Option<String>,
+}
+
+impl TransactionQuery {
+    /// Query for transactions on or after the given date.
+    pub fn since(from_date: NaiveDate) -> Self {
+        Self {
+            from_date: Some(from_date),
+            ..Self::default()
+        }
+    }
+
+    /// Restricts the query to transactions on or before the given date.
+    pub fn until(mut self, to_date: NaiveDate) -> Self {
+        self.to_date = Some(to_date);
+        self
+    }
+
+    /// Limits the number of transactions per page.
+    pub fn limit(mut self, row_limit: u32) -> Self {
+        self.row_limit = Some(row_limit);
+        self
+    }
+
+    /// Continues from a cursor returned by a previous page.
+    pub fn after(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    /// Number of transactions to skip, as encoded in the cursor.
+    pub fn offset(&self) -> usize {
+        self.cursor
+            .as_deref()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Returns true if the transaction date falls within the query window.
+    pub fn matches_date(&self, tx: &Transaction) -> bool {
+        let Some(date) = chrono::DateTime::from_timestamp_millis(tx.date).map(|d| d.date_naive()) else {
+            return false;
+        };
+        self.from_date.is_none_or(|from| date >= from) && self.to_date.is_none_or(|to| date <= to)
+    }
+
+    /// Cuts the page described by the cursor and row limit out of `transactions`.
+    ///
+    /// `transactions` must already be filtered to the date window and ordered
+    /// newest first, starting from the beginning of the window.
+    pub fn paginate(&self, transactions: Vec<Transaction>) -> TransactionResponse {
+        let offset = self.offset();
+        let mut page: Vec<Transaction> = transactions.into_iter().skip(offset).collect();
+
+        let next_cursor = match self.row_limit {
+            Some(limit) if page.len() > limit as usize => {
+                page.truncate(limit as usize);
+                Some((offset + limit as usize).to_string())
+            }
+            _ => None,
+        };
+
+        TransactionResponse {
+            transactions: page,
+            errors: vec![],
+            next_cursor,
+        }
+    }
+
+    /// Applies the full query to an in-memory list of transactions.
+    pub fn apply(&self, transactions: impl IntoIterator<Item = Transaction>) -> TransactionResponse {
+        let mut filtered: Vec<Transaction> = transactions
+            .into_iter()
+            .filter(|tx| self.matches_date(tx))
+            .collect();
+        filtered.sort_by_key(|tx| std::cmp::Reverse(tx.date));
+        self.paginate(filtered)
+    }
+}
@@ -0,0 +1,339 @@
+//! Fixed-point money type.
+//!
+//! Amounts are stored as an integer number of minor units (øre for NOK)
+//! together with their currency, so arithmetic is exact and rounding only
+//! happens where it is asked for explicitly.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// ISO 4217 currency code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency([u8; 3]);
+
+impl Currency {
+    /// Norwegian krone.
+    pub const NOK: Currency = Currency(*b"NOK");
+
+    /// Parses a three-letter currency code (case-insensitive).
+    pub fn parse(code: &str) -> Option<Self> {
+        let bytes: [u8; 3] = code.trim().as_bytes().try_into().ok()?;
+        if !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        Some(Currency(bytes.map(|b| b.to_ascii_uppercase())))
+    }
+
+    /// The currency code as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII letters are ever stored.
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::NOK
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Currency::parse(&code).ok_or_else(|| de::Error::custom(format!("invalid currency code: {}", code)))
+    }
+}
+
+/// An exact amount of money in minor units of a currency.
+///
+/// Serialized as a plain decimal number, which is how the bank API and the
+/// rule definitions represent amounts. The currency is not part of that
+/// representation; deserialized values are NOK until bound to the currency
+/// of the surrounding record with [`Money::with_currency`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Money {
+    ore: i64,
+    currency: Currency,
+}
+
+impl Money {
+    /// Creates an amount from minor units.
+    pub const fn new(ore: i64, currency: Currency) -> Self {
+        Self { ore, currency }
+    }
+
+    /// Creates a NOK amount from øre.
+    pub const fn nok(ore: i64) -> Self {
+        Self::new(ore, Currency::NOK)
+    }
+
+    /// Zero in the given currency.
+    pub const fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Converts a decimal amount, rounding half away from zero to whole øre.
+    pub fn from_decimal(value: f64, currency: Currency) -> Self {
+        Self::new((value * 100.0).round() as i64, currency)
+    }
+
+    /// Amount in minor units.
+    pub const fn ore(&self) -> i64 {
+        self.ore
+    }
+
+    /// Currency of the amount.
+    pub const fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    /// The same amount in another currency (no conversion is done).
+    pub const fn with_currency(self, currency: Currency) -> Self {
+        Self::new(self.ore, currency)
+    }
+
+    /// Decimal value, for display and wire formats only.
+    pub fn to_decimal(&self) -> f64 {
+        self.ore as f64 / 100.0
+    }
+
+    /// Absolute value of the amount.
+    pub const fn abs(self) -> Self {
+        Self::new(self.ore.abs(), self.currency)
+    }
+
+    /// Returns true if the amount is exactly zero.
+    pub const fn is_zero(&self) -> bool {
+        self.ore == 0
+    }
+
+    /// Returns true if the amount is greater than zero.
+    pub const fn is_positive(&self) -> bool {
+        self.ore > 0
+    }
+
+    /// Returns true if the amount is less than zero.
+    pub const fn is_negative(&self) -> bool {
+        self.ore < 0
+    }
+
+    /// Adds two amounts of the same currency.
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Self::new(self.ore.checked_add(other.ore)?, self.currency))
+    }
+
+    /// Subtracts an amount of the same currency.
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        if self.currency != other.currency {
+            return None;
+        }
+        Some(Self::new(self.ore.checked_sub(other.ore)?, self.currency))
+    }
+
+    /// Takes a percentage of the amount, rounding half away from zero to whole øre.
+    pub fn percentage(self, percent: f64) -> Self {
+        let ore = (self.ore as f64 * percent / 100.0).round() as i64;
+        Self::new(ore, self.currency)
+    }
+}
+
+impl PartialOrd for Money {
+    /// Amounts in different currencies are not comparable.
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        (self.currency == other.currency).then(|| self.ore.cmp(&other.ore))
+    }
+}
+
+impl std::ops::Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::new(-self.ore, self.currency)
+    }
+}
+
+impl fmt::Display for Money {
+    /// Formats the amount with two decimals and no currency, e.g. `-14.90`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.ore < 0 { "-" } else { "" };
+        let abs = self.ore.unsigned_abs();
+        write!(f, "{}{}.{:02}", sign, abs / 100, abs % 100)
+    }
+}
+
+impl fmt::Debug for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self, self.currency)
+    }
+}
+
+/// Error parsing a decimal amount.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid amount: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoneyError {}
+
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    /// Parses a NOK decimal amount with at most two decimals, e.g. `"-149.5"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMoneyError(s.to_string());
+        let trimmed = s.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if whole.is_empty() || fraction.len() > 2 {
+            return Err(err());
+        }
+        if !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(err());
+        }
+
+        let whole: i64 = whole.parse().map_err(|_| err())?;
+        let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| err())?;
+        let ore = whole
+            .checked_mul(100)
+            .and_then(|o| o.checked_add(fraction))
+            .ok_or_else(err)?;
+
+        Ok(Money::nok(if negative { -ore } else { ore }))
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(self.to_decimal())
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    /// Accepts a JSON number or a decimal string; numbers are rounded to whole øre.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct MoneyVisitor;
+
+        impl Visitor<'_> for MoneyVisitor {
+            type Value = Money;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a decimal amount")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+                if !v.is_finite() {
+                    return Err(E::custom("amount must be finite"));
+                }
+                Ok(Money::from_decimal(v, Currency::NOK))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+                v.checked_mul(100)
+                    .map(Money::nok)
+                    .ok_or_else(|| E::custom("amount out of range"))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+                i64::try_from(v)
+                    .map_err(|_| E::custom("amount out of range"))
+                    .and_then(|v| self.visit_i64(v))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentage_is_exact() {
+        let amount = Money::nok(14900);
+        assert_eq!(amount.percentage(10.0), Money::nok(1490));
+        assert_eq!(amount.percentage(10.0).to_string(), "14.90");
+    }
+
+    #[test]
+    fn test_percentage_rounds_half_away_from_zero() {
+        assert_eq!(Money::nok(5).percentage(50.0), Money::nok(3));
+        assert_eq!(Money::nok(-5).percentage(50.0), Money::nok(-3));
+        assert_eq!(Money::nok(333).percentage(10.0), Money::nok(33));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Money::nok(-50).to_string(), "-0.50");
+        assert_eq!(Money::nok(100).to_string(), "1.00");
+        assert_eq!(Money::nok(123456).to_string(), "1234.56");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("149".parse::<Money>(), Ok(Money::nok(14900)));
+        assert_eq!("-0.5".parse::<Money>(), Ok(Money::nok(-50)));
+        assert_eq!("99.99".parse::<Money>(), Ok(Money::nok(9999)));
+        assert!("1.234".parse::<Money>().is_err());
+        assert!("abc".parse::<Money>().is_err());
+        assert!(".5".parse::<Money>().is_err());
+    }
+
+    #[test]
+    fn test_serde_roundtrip() {
+        let amount: Money = serde_json::from_str("15000.50").unwrap();
+        assert_eq!(amount, Money::nok(1500050));
+        assert_eq!(serde_json::to_string(&amount).unwrap(), "15000.5");
+
+        let amount: Money = serde_json::from_str("-149").unwrap();
+        assert_eq!(amount, Money::nok(-14900));
+
+        let amount: Money = serde_json::from_str("\"0.10\"").unwrap();
+        assert_eq!(amount, Money::nok(10));
+    }
+
+    #[test]
+    fn test_currencies_do_not_mix() {
+        let nok = Money::nok(100);
+        let eur = Money::new(100, Currency::parse("eur").unwrap());
+        assert_eq!(nok.partial_cmp(&eur), None);
+        assert_eq!(nok.checked_add(eur), None);
+        assert_eq!(nok.checked_add(nok), Some(Money::nok(200)));
+    }
+}
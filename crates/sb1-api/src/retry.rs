@@ -0,0 +1,146 @@
+//! Retry policy for idempotent API requests.
+
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::time::Duration;
+
+/// Retry policy for idempotent requests.
+///
+/// Failed attempts are retried with exponential backoff and full jitter. A
+/// `Retry-After` header on the response takes precedence over the computed
+/// delay. Only GET requests are retried; POSTs are never resent.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for each subsequent retry.
+    pub base_delay: Duration,
+    /// Upper bound for any single delay, including `Retry-After`.
+    pub max_delay: Duration,
+    /// Randomize delays between zero and the computed backoff.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that makes a single attempt.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// Returns true if another attempt is allowed after `attempt` (1-based).
+    pub fn can_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts
+    }
+
+    /// Backoff delay before the retry following `attempt` (1-based).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        if self.jitter {
+            delay.mul_f64(fastrand::f64())
+        } else {
+            delay
+        }
+    }
+
+    /// Delay before retrying a response, honouring `Retry-After` when present.
+    ///
+    /// Returns `None` if the server asks for a longer wait than `max_delay`.
+    pub fn delay_for(&self, attempt: u32, headers: &HeaderMap) -> Option<Duration> {
+        match retry_after(headers) {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+/// Returns true if a response with this status may succeed when retried.
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || matches!(
+            status,
+            StatusCode::INTERNAL_SERVER_ERROR
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+}
+
+/// Parses a `Retry-After` header given either as seconds or as an HTTP date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn no_jitter() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: false,
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        let policy = no_jitter();
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(5), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_jitter_stays_within_backoff() {
+        let policy = RetryPolicy { jitter: true, ..no_jitter() };
+        for _ in 0..100 {
+            assert!(policy.backoff(3) <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_header() {
+        let policy = no_jitter();
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("0"));
+        assert_eq!(policy.delay_for(1, &headers), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(policy.delay_for(1, &headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(policy.delay_for(1, &headers), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,143 @@
+//! Integration tests for token refresh in the file-based token provider.
+
+use sb1_api::config::{read_token_data_from, save_token_data_to, AppConfig};
+use sb1_api::models::TokenData;
+use sb1_api::{FileTokenProvider, TokenProvider};
+use std::path::PathBuf;
+use std::sync::Arc;
+use wiremock::matchers::{body_string_contains, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn test_config() -> AppConfig {
+    AppConfig {
+        client_id: "client".to_string(),
+        client_secret: "secret".to_string(),
+        financial_institution: "fid-test".to_string(),
+    }
+}
+
+fn token_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("sb1-api-auth-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.json", name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn token(access_token: &str, issued_at: Option<i64>) -> TokenData {
+    TokenData {
+        access_token: access_token.to_string(),
+        expires_in: 600,
+        refresh_token_expires_in: 3600,
+        refresh_token_absolute_expires_in: 86400,
+        token_type: "Bearer".to_string(),
+        refresh_token: "refresh-1".to_string(),
+        issued_at,
+    }
+}
+
+const REFRESH_RESPONSE: &str = r#"{
+    "access_token": "fresh-token",
+    "expires_in": 600,
+    "refresh_token_expires_in": 3600,
+    "refresh_token_absolute_expires_in": 86400,
+    "token_type": "Bearer",
+    "refresh_token": "refresh-2"
+}"#;
+
+#[tokio::test]
+async fn test_valid_token_is_not_refreshed() {
+    let server = MockServer::start().await;
+    let token_file = token_path("valid");
+    let now = chrono::Utc::now().timestamp();
+    save_token_data_to(&token_file, &token("cached-token", Some(now))).unwrap();
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(REFRESH_RESPONSE))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let provider =
+        FileTokenProvider::with_endpoint(test_config(), token_file, format!("{}/oauth/token", server.uri())).unwrap();
+
+    assert_eq!(provider.get_access_token().await.unwrap(), "cached-token");
+}
+
+#[tokio::test]
+async fn test_expiring_token_is_refreshed_once_and_persisted() {
+    let server = MockServer::start().await;
+    let token_file = token_path("expiring");
+    let issued = chrono::Utc::now().timestamp() - 590;
+    save_token_data_to(&token_file, &token("old-token", Some(issued))).unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/oauth/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .and(body_string_contains("refresh_token=refresh-1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(REFRESH_RESPONSE))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = Arc::new(
+        FileTokenProvider::with_endpoint(test_config(), token_file.clone(), format!("{}/oauth/token", server.uri()))
+            .unwrap(),
+    );
+
+    let handles: Vec<_> = (0..5)
+        .map(|_| {
+            let provider = provider.clone();
+            tokio::spawn(async move { provider.get_access_token().await })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.await.unwrap().unwrap(), "fresh-token");
+    }
+
+    let stored = read_token_data_from(&token_file).unwrap().expect("token should be stored");
+    assert_eq!(stored.access_token, "fresh-token");
+    assert_eq!(stored.refresh_token, "refresh-2");
+    assert!(stored.issued_at.is_some());
+}
+
+#[tokio::test]
+async fn test_legacy_token_without_issue_time_is_refreshed() {
+    let server = MockServer::start().await;
+    let token_file = token_path("legacy");
+    save_token_data_to(&token_file, &token("legacy-token", None)).unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/oauth/token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(REFRESH_RESPONSE))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider =
+        FileTokenProvider::with_endpoint(test_config(), token_file, format!("{}/oauth/token", server.uri())).unwrap();
+
+    assert_eq!(provider.get_access_token().await.unwrap(), "fresh-token");
+}
+
+#[tokio::test]
+async fn test_expired_refresh_token_requires_reauthorization() {
+    let server = MockServer::start().await;
+    let token_file = token_path("refresh-expired");
+    let issued = chrono::Utc::now().timestamp() - 7200;
+    save_token_data_to(&token_file, &token("old-token", Some(issued))).unwrap();
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(REFRESH_RESPONSE))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let provider =
+        FileTokenProvider::with_endpoint(test_config(), token_file, format!("{}/oauth/token", server.uri())).unwrap();
+
+    assert!(matches!(
+        provider.get_access_token().await,
+        Err(sb1_api::ApiError::Auth(_))
+    ));
+}
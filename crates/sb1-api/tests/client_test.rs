@@ -1,7 +1,10 @@
 //! Integration tests for the SpareBank 1 API client using wiremock.
 
-use sb1_api::{BankApiClient, MockTokenProvider, SpareBank1Client};
+use sb1_api::models::TransactionQuery;
+use sb1_api::{BankApiClient, MockTokenProvider, Money, RetryPolicy, SpareBank1Client};
+use chrono::NaiveDate;
 use std::sync::Arc;
+use std::time::Duration;
 use wiremock::matchers::{header, method, path, query_param};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -75,7 +78,7 @@ async fn test_get_accounts_success() {
     let accounts = result.unwrap();
     assert_eq!(accounts.accounts.len(), 1);
     assert_eq!(accounts.accounts[0].name, "Checking");
-    assert_eq!(accounts.accounts[0].balance, 5000.00);
+    assert_eq!(accounts.accounts[0].balance, Money::nok(500000));
 }
 
 #[tokio::test]
@@ -130,13 +133,13 @@ async fn test_get_transactions_success() {
         .mount(&mock_server)
         .await;
 
-    let result = client.get_transactions("acc-1").await;
+    let result = client.get_transactions("acc-1", &TransactionQuery::default()).await;
     assert!(result.is_ok());
 
     let transactions = result.unwrap();
     assert_eq!(transactions.transactions.len(), 1);
     assert_eq!(transactions.transactions[0].id, "tx-1");
-    assert_eq!(transactions.transactions[0].amount, -100.00);
+    assert_eq!(transactions.transactions[0].amount, Money::nok(-10000));
 }
 
 #[tokio::test]
@@ -250,5 +253,192 @@ async fn test_unauthorized_request() {
         .await;
 
     let result = client.get_accounts().await;
-    assert!(result.is_err());
+    assert_eq!(result.unwrap_err().status(), Some(401));
+}
+
+fn fast_retry_policy() -> RetryPolicy {
+    RetryPolicy {
+        max_attempts: 3,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(50),
+        jitter: false,
+    }
+}
+
+const EMPTY_ACCOUNTS: &str = r#"{"accounts": [], "errors": []}"#;
+
+#[tokio::test]
+async fn test_get_retries_server_errors() {
+    let (mock_server, client) = setup_client().await;
+    let client = client.with_retry_policy(fast_retry_policy());
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_ACCOUNTS))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let result = client.get_accounts().await;
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn test_get_gives_up_after_max_attempts() {
+    let (mock_server, client) = setup_client().await;
+    let client = client.with_retry_policy(fast_retry_policy());
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(3)
+        .mount(&mock_server)
+        .await;
+
+    let err = client.get_accounts().await.unwrap_err();
+    assert_eq!(err.status(), Some(502));
+    assert!(err.is_transient());
+}
+
+#[tokio::test]
+async fn test_get_honours_retry_after() {
+    let (mock_server, client) = setup_client().await;
+    let client = client.with_retry_policy(fast_retry_policy());
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_ACCOUNTS))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    assert!(client.get_accounts().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_get_does_not_wait_for_long_retry_after() {
+    let (mock_server, client) = setup_client().await;
+    let client = client.with_retry_policy(fast_retry_policy());
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let err = client.get_accounts().await.unwrap_err();
+    assert_eq!(err.status(), Some(429));
+}
+
+#[tokio::test]
+async fn test_post_is_not_retried_on_server_error() {
+    let (mock_server, client) = setup_client().await;
+    let client = client.with_retry_policy(fast_retry_policy());
+
+    Mock::given(method("POST"))
+        .and(path("/personal/banking/transfer/debit"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let transfer = sb1_api::models::CreateTransferDTO {
+        amount: "100.00".to_string(),
+        due_date: None,
+        message: None,
+        to_account: "98765432101".to_string(),
+        from_account: "12345678901".to_string(),
+        currency_code: None,
+    };
+
+    let err = client.create_transfer(transfer).await.unwrap_err();
+    assert_eq!(err.status(), Some(503));
+}
+
+#[tokio::test]
+async fn test_unauthorized_retries_with_refreshed_token() {
+    let mock_server = MockServer::start().await;
+    let token_provider = Arc::new(MockTokenProvider::with_refresh("stale-token", "fresh-token"));
+    let client = SpareBank1Client::with_base_url(token_provider, mock_server.uri());
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .and(header("Authorization", "Bearer stale-token"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/accounts"))
+        .and(header("Authorization", "Bearer fresh-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(EMPTY_ACCOUNTS))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    assert!(client.get_accounts().await.is_ok());
+}
+
+fn transaction_json(id: &str, date: i64) -> String {
+    format!(
+        r#"{{
+            "id": "{id}", "nonUniqueId": "{id}", "description": null, "cleanedDescription": null,
+            "accountNumber": {{"value": "1", "formatted": "1", "unformatted": "1"}},
+            "amount": -10.0, "date": {date}, "interestDate": null, "typeCode": "VISA",
+            "typeText": "Card", "currencyCode": "NOK", "canShowDetails": true, "source": "VISA",
+            "isConfidential": false, "bookingStatus": "BOOKED", "accountName": "Checking",
+            "accountKey": "acc-1", "accountCurrency": "NOK", "isFromCurrencyAccount": false,
+            "classificationInput": {{"id": "{id}", "amount": -10.0, "type": "EXPENSE", "text": null, "date": {date}}},
+            "remoteAccountNumber": null, "remoteAccountName": null, "kidOrMessage": null
+        }}"#
+    )
+}
+
+#[tokio::test]
+async fn test_get_transactions_with_query() {
+    let (mock_server, client) = setup_client().await;
+
+    let day = 86_400_000;
+    let transactions: Vec<String> = (0..3)
+        .map(|i| transaction_json(&format!("tx-{}", i), 1707753600000 - i * day))
+        .collect();
+    let response_body = format!(r#"{{"transactions": [{}], "errors": []}}"#, transactions.join(","));
+
+    Mock::given(method("GET"))
+        .and(path("/personal/banking/transactions"))
+        .and(query_param("accountKey", "acc-1"))
+        .and(query_param("fromDate", "2024-02-01"))
+        .and(query_param("toDate", "2024-02-29"))
+        .and(query_param("rowLimit", "3"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(response_body))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let query = TransactionQuery::since(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap())
+        .until(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+        .limit(2);
+
+    let page = client.get_transactions("acc-1", &query).await.unwrap();
+    assert_eq!(page.transactions.len(), 2);
+    assert_eq!(page.transactions[0].id, "tx-0");
+    assert_eq!(page.next_cursor.as_deref(), Some("2"));
 }
@@ -1,6 +1,7 @@
 //! Model serialization/deserialization tests.
 
 use sb1_api::models::*;
+use sb1_api::{Currency, Money};
 
 const ACCOUNT_JSON: &str = r#"{
     "key": "acc-123",
@@ -114,8 +115,8 @@ fn test_deserialize_account() {
     assert_eq!(account.key, "acc-123");
     assert_eq!(account.account_number, "12345678901");
     assert_eq!(account.name, "My Checking Account");
-    assert_eq!(account.balance, 15000.50);
-    assert_eq!(account.type_field, "ACCOUNT");
+    assert_eq!(account.balance, Money::nok(1500050));
+    assert_eq!(account.type_field, AccountType::Account);
     assert!(account.account_properties.is_transfer_from_enabled);
     assert!(account.account_properties.is_default_payment_account);
 
@@ -131,12 +132,21 @@ fn test_deserialize_transaction() {
     assert_eq!(tx.id, "tx-12345");
     assert_eq!(tx.description, Some("NETFLIX.COM".to_string()));
     assert_eq!(tx.cleaned_description, Some("Netflix Subscription".to_string()));
-    assert_eq!(tx.amount, -149.00);
-    assert_eq!(tx.type_code, "VISA");
-    assert_eq!(tx.booking_status, "BOOKED");
+    assert_eq!(tx.amount, Money::nok(-14900));
+    assert_eq!(tx.type_code, TransactionType::Visa);
+    assert_eq!(tx.booking_status, BookingStatus::Booked);
     assert_eq!(tx.account_number.formatted, "1234.56.78901");
 }
 
+#[test]
+fn test_transaction_amount_uses_currency_code() {
+    let json = TRANSACTION_JSON.replace(r#""currencyCode": "NOK""#, r#""currencyCode": "EUR""#);
+    let tx: Transaction = serde_json::from_str(&json).expect("Failed to parse transaction");
+
+    assert_eq!(tx.amount.currency(), Currency::parse("EUR").unwrap());
+    assert_eq!(tx.amount.ore(), -14900);
+}
+
 #[test]
 fn test_deserialize_transfer_response_success() {
     let response: TransferResponse =
@@ -1,18 +1,28 @@
 import type {
 	Account,
 	AccountData,
+	AccumulatorEntry,
 	AuditEntry,
+	BacktestReport,
+	BacktestRequest,
+	BackfillResponse,
 	CreateDemoTransactionRequest,
 	CreateDemoTransactionResponse,
 	CreateRuleRequest,
 	DemoAccount,
 	DemoStatus,
+	ProposalStatus,
 	Rule,
 	RuleExecution,
+	RuleLogEntry,
 	ServerStatus,
 	SystemStatus,
+	TransactionQuery,
 	TransactionResponse,
-	UpdateRuleRequest
+	TransferLimits,
+	TransferProposal,
+	UpdateRuleRequest,
+	ValidationResponse
 } from './types';
 
 const BASE_URL = '/api';
@@ -50,8 +60,13 @@ class ApiClient {
 		return this.request(`/accounts/${encodeURIComponent(key)}`);
 	}
 
-	async getTransactions(accountKey: string): Promise<TransactionResponse> {
-		return this.request(`/accounts/${encodeURIComponent(accountKey)}/transactions`);
+	async getTransactions(accountKey: string, query?: TransactionQuery): Promise<TransactionResponse> {
+		const params = new URLSearchParams();
+		for (const [key, value] of Object.entries(query ?? {})) {
+			if (value !== undefined) params.set(key, String(value));
+		}
+		const qs = params.toString() ? `?${params}` : '';
+		return this.request(`/accounts/${encodeURIComponent(accountKey)}/transactions${qs}`);
 	}
 
 	// Rules
@@ -70,6 +85,20 @@ class ApiClient {
 		});
 	}
 
+	async validateRule(rule: CreateRuleRequest): Promise<ValidationResponse> {
+		return this.request('/rules/validate', {
+			method: 'POST',
+			body: JSON.stringify(rule)
+		});
+	}
+
+	async backtestDraftRule(rule: CreateRuleRequest, request: BacktestRequest): Promise<BacktestReport> {
+		return this.request('/rules/backtest', {
+			method: 'POST',
+			body: JSON.stringify({ rule, ...request })
+		});
+	}
+
 	async updateRule(id: string, rule: UpdateRuleRequest): Promise<Rule> {
 		return this.request(`/rules/${id}`, {
 			method: 'PUT',
@@ -95,6 +124,32 @@ class ApiClient {
 		});
 	}
 
+	async backfillRule(id: string, since: string): Promise<BackfillResponse> {
+		return this.request(`/rules/${id}/backfill`, {
+			method: 'POST',
+			body: JSON.stringify({ since })
+		});
+	}
+
+	async backtestRule(id: string, request: BacktestRequest): Promise<BacktestReport> {
+		return this.request(`/rules/${id}/backtest`, {
+			method: 'POST',
+			body: JSON.stringify(request)
+		});
+	}
+
+	async getRuleLog(ruleId: string, limit = 100): Promise<RuleLogEntry[]> {
+		return this.request(`/rules/${ruleId}/log?limit=${limit}`);
+	}
+
+	async getTransactionLog(ruleId: string, transactionId: string): Promise<RuleLogEntry[]> {
+		return this.request(`/rules/${ruleId}/log/${encodeURIComponent(transactionId)}`);
+	}
+
+	async getAccumulated(ruleId: string): Promise<AccumulatorEntry[]> {
+		return this.request(`/rules/${ruleId}/accumulated`);
+	}
+
 	async getRuleExecutions(ruleId: string): Promise<RuleExecution[]> {
 		return this.request(`/rules/${ruleId}/executions`);
 	}
@@ -109,6 +164,39 @@ class ApiClient {
 		return this.request(`/executions/${id}`);
 	}
 
+	async resendExecution(id: string): Promise<RuleExecution> {
+		return this.request(`/executions/${id}/resend`, {
+			method: 'POST'
+		});
+	}
+
+	// Proposals
+	async getProposals(status?: ProposalStatus, limit?: number): Promise<TransferProposal[]> {
+		const params = new URLSearchParams();
+		if (status) params.set('status', status);
+		if (limit) params.set('limit', limit.toString());
+		const query = params.toString();
+		return this.request(`/proposals${query ? `?${query}` : ''}`);
+	}
+
+	async getProposal(id: string): Promise<TransferProposal> {
+		return this.request(`/proposals/${id}`);
+	}
+
+	async approveProposal(id: string, decidedBy: string): Promise<TransferProposal> {
+		return this.request(`/proposals/${id}/approve`, {
+			method: 'POST',
+			body: JSON.stringify({ decided_by: decidedBy })
+		});
+	}
+
+	async rejectProposal(id: string, decidedBy: string): Promise<TransferProposal> {
+		return this.request(`/proposals/${id}/reject`, {
+			method: 'POST',
+			body: JSON.stringify({ decided_by: decidedBy })
+		});
+	}
+
 	// Audit
 	async getAuditLog(limit?: number, eventType?: string): Promise<AuditEntry[]> {
 		const params = new URLSearchParams();
@@ -145,6 +233,29 @@ class ApiClient {
 		});
 	}
 
+	async engageEmergencyStop(): Promise<void> {
+		return this.request('/system/emergency-stop', {
+			method: 'POST'
+		});
+	}
+
+	async releaseEmergencyStop(): Promise<void> {
+		return this.request('/system/emergency-stop', {
+			method: 'DELETE'
+		});
+	}
+
+	async getTransferLimits(): Promise<TransferLimits> {
+		return this.request('/system/limits');
+	}
+
+	async updateTransferLimits(limits: TransferLimits): Promise<TransferLimits> {
+		return this.request('/system/limits', {
+			method: 'PUT',
+			body: JSON.stringify(limits)
+		});
+	}
+
 	// Server status (includes demo_mode)
 	async getServerStatus(): Promise<ServerStatus> {
 		return this.request('/status');
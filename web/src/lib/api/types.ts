@@ -61,6 +61,14 @@ export interface AccountProperties {
 export interface TransactionResponse {
 	transactions: Transaction[];
 	errors: unknown[];
+	nextCursor?: string;
+}
+
+export interface TransactionQuery {
+	fromDate?: string;
+	toDate?: string;
+	rowLimit?: number;
+	cursor?: string;
 }
 
 export interface Transaction {
@@ -104,22 +112,83 @@ export interface ClassificationInput {
 }
 
 // Rule types
+export type FireOn = 'pending' | 'booked' | 'first_seen';
+
 export interface Rule {
 	id: string;
 	name: string;
 	description?: string;
 	enabled: boolean;
+	trigger: RuleTrigger;
 	trigger_account_key: string;
+	fire_on: FireOn;
+	include_self_transfers: boolean;
+	limits: RuleLimits;
+	// Rules on an account run highest priority first; a match on a
+	// stop_processing rule skips the rest, and only the first match in a
+	// group acts on a transaction
+	priority: number;
+	stop_processing: boolean;
+	group?: string;
+	// Transfers wait as proposals until approved
+	requires_approval: boolean;
 	conditions: Condition[];
 	actions: Action[];
 	created_at: number;
 	updated_at: number;
+	activated_at: number;
+	next_run_at?: number;
+}
+
+// What makes a rule run; cron expressions are in Norwegian time
+export type RuleTrigger =
+	| { type: 'transaction' }
+	| { type: 'schedule'; cron: string; catch_up?: boolean };
+
+export interface RuleLimits {
+	max_transfers_per_day?: number;
+	max_amount_per_day?: number;
+	max_amount_per_month?: number;
+	cooldown_secs?: number;
+}
+
+// Why a rule did or did not fire on a transaction: 'skipped',
+// 'limited:<kind>' or 'executed:<statuses>'
+export interface RuleLogEntry {
+	id: string;
+	rule_id: string;
+	transaction_id: string;
+	transaction_fingerprint: string;
+	action_taken: string;
+	processed_at: number;
+	condition_trace?: ConditionTrace[];
+}
+
+// How a condition evaluated, with the values it compared
+export interface ConditionTrace {
+	condition: string;
+	matched: boolean;
+	actual?: string;
+	expected?: string;
+	children?: ConditionTrace[];
+}
+
+export interface BackfillResponse {
+	transactions_evaluated: number;
 }
 
 export interface CreateRuleRequest {
 	name: string;
 	description?: string;
+	trigger?: RuleTrigger;
 	trigger_account_key: string;
+	fire_on?: FireOn;
+	include_self_transfers?: boolean;
+	limits?: RuleLimits;
+	priority?: number;
+	stop_processing?: boolean;
+	group?: string;
+	requires_approval?: boolean;
 	conditions: Condition[];
 	actions: Action[];
 }
@@ -127,11 +196,70 @@ export interface CreateRuleRequest {
 export interface UpdateRuleRequest {
 	name?: string;
 	description?: string;
+	trigger?: RuleTrigger;
 	trigger_account_key?: string;
+	fire_on?: FireOn;
+	include_self_transfers?: boolean;
+	limits?: RuleLimits;
+	priority?: number;
+	stop_processing?: boolean;
+	// An empty string removes the rule from its group
+	group?: string;
+	requires_approval?: boolean;
 	conditions?: Condition[];
 	actions?: Action[];
 }
 
+// A problem with one field of a rule, e.g. field 'conditions[0].pattern'
+export interface ValidationError {
+	field: string;
+	message: string;
+}
+
+export interface ValidationResponse {
+	valid: boolean;
+	errors: ValidationError[];
+}
+
+// Backtests read stored transactions by default, or fetch them from the bank
+export type BacktestSource = 'stored' | 'bank';
+
+export interface BacktestRequest {
+	from: string;
+	to?: string;
+	source?: BacktestSource;
+}
+
+export interface BacktestAccount {
+	key: string;
+	name: string;
+	account_number: string;
+}
+
+export interface BacktestTransfer {
+	action_index: number;
+	from_account?: BacktestAccount;
+	to_account?: BacktestAccount;
+	amount: number;
+	outcome: 'transfer' | 'accumulate' | 'skipped' | 'unresolved';
+}
+
+export interface BacktestMatch {
+	transaction_id: string;
+	date: number;
+	description?: string;
+	amount: number;
+	limited?: string;
+	transfers: BacktestTransfer[];
+}
+
+export interface BacktestReport {
+	transactions_evaluated: number;
+	matches: BacktestMatch[];
+	transfer_count: number;
+	total_amount: number;
+}
+
 // Condition types (discriminated union)
 export type Condition =
 	| { type: 'description_matches'; pattern: string; case_insensitive?: boolean }
@@ -140,7 +268,28 @@ export type Condition =
 	| { type: 'amount_between'; min: number; max: number }
 	| { type: 'amount_equals'; value: number; tolerance?: number }
 	| { type: 'transaction_type'; type_code: string }
+	| { type: 'transaction_source'; source: string }
+	| { type: 'currency'; currency: string }
+	| { type: 'remote_account_number'; number: string }
+	| { type: 'remote_name_matches'; pattern: string; case_insensitive?: boolean }
+	| { type: 'kid_or_message_matches'; pattern: string; case_insensitive?: boolean }
+	| { type: 'kid_or_message_equals'; value: string }
+	| {
+			type: 'aggregate';
+			// An amount for 'sum' and 'average', a whole number for 'count'
+			function: 'sum' | 'count' | 'average';
+			window: AggregateWindow;
+			filter?: Condition;
+			comparison: 'greater_than' | 'less_than';
+			value: number;
+	  }
 	| { type: 'is_settled' }
+	| { type: 'weekday'; days: Weekday[]; timezone?: string }
+	| { type: 'day_of_month_between'; from: number; to: number; timezone?: string }
+	| { type: 'time_of_day_between'; from: string; to: string; timezone?: string }
+	| { type: 'near_month_end'; days: number; timezone?: string }
+	| { type: 'account_balance_below'; account: AccountRef; value: number; balance?: BalanceKind }
+	| { type: 'account_balance_above'; account: AccountRef; value: number; balance?: BalanceKind }
 	| { type: 'and'; conditions: Condition[] }
 	| { type: 'or'; conditions: Condition[] }
 	| { type: 'not'; condition: Condition };
@@ -152,14 +301,42 @@ export type Action = {
 	to_account: AccountRef;
 	amount: AmountSpec;
 	message?: string;
+	accumulate?: Accumulate;
 };
 
+// Collect amounts and transfer them together at a threshold or on a schedule
+export interface Accumulate {
+	threshold?: number;
+	flush_schedule?: string;
+}
+
+export interface AccumulatorEntry {
+	id: number;
+	rule_id: string;
+	action_index: number;
+	source_id: string;
+	amount: number;
+	created_at: number;
+	flush_execution_id?: string;
+}
+
 // Account reference types
 export type AccountRef =
 	| { type: 'by_key'; key: string }
 	| { type: 'by_number'; number: string }
 	| { type: 'trigger_account' };
 
+// Window of stored transactions an aggregate condition covers
+export type AggregateWindow =
+	| { type: 'rolling'; days: number }
+	| { type: 'calendar'; period: 'day' | 'week' | 'month' };
+
+// Weekday for date conditions, evaluated in Europe/Oslo unless a timezone is given
+export type Weekday = 'Mon' | 'Tue' | 'Wed' | 'Thu' | 'Fri' | 'Sat' | 'Sun';
+
+// Which balance of an account a condition or amount reads (default 'available')
+export type BalanceKind = 'booked' | 'available';
+
 // Amount specification types
 export type AmountSpec =
 	| { type: 'fixed'; value: number }
@@ -167,20 +344,55 @@ export type AmountSpec =
 	| { type: 'transaction_amount_abs' }
 	| { type: 'percentage'; of_transaction: number }
 	| { type: 'min'; specs: AmountSpec[] }
-	| { type: 'max'; specs: AmountSpec[] };
+	| { type: 'max'; specs: AmountSpec[] }
+	| { type: 'round_up'; to: number }
+	| { type: 'account_balance'; account: AccountRef; balance?: BalanceKind }
+	| { type: 'excess_above'; account: AccountRef; threshold: number }
+	| { type: 'top_up_to'; account: AccountRef; target: number }
+	| { type: 'clamp'; spec: AmountSpec; min?: number; max?: number };
 
 // Execution types
 export interface RuleExecution {
 	id: string;
 	rule_id: string;
-	transaction_id: string;
+	transaction_id?: string;
+	scheduled_for?: number;
+	idempotency_key: string;
 	transfer_payment_id?: string;
 	amount: number;
 	from_account: string;
 	to_account: string;
-	status: string;
+	status: 'pending' | 'success' | 'failed' | 'unknown' | 'blocked' | 'retrying' | 'dead_letter';
 	error_message?: string;
 	executed_at: number;
+	debit_transaction_id?: string;
+	credit_transaction_id?: string;
+	message?: string;
+	attempts: number;
+	next_retry_at?: number;
+}
+
+// A transfer waiting for approval, or the decision made on it
+export type ProposalStatus = 'pending' | 'approved' | 'rejected' | 'expired';
+
+export interface TransferProposal {
+	id: string;
+	rule_id: string;
+	transaction_id?: string;
+	scheduled_for?: number;
+	action_index: number;
+	idempotency_key: string;
+	amount: number;
+	from_account: string;
+	to_account: string;
+	message?: string;
+	status: ProposalStatus;
+	created_at: number;
+	expires_at: number;
+	decided_at?: number;
+	decided_by?: string;
+	// Status of the transfer sent on approval
+	execution_status?: string;
 }
 
 // Audit types
@@ -200,12 +412,20 @@ export interface AuditEntry {
 export interface SystemStatus {
 	status: string;
 	scheduler_enabled: boolean;
+	emergency_stop: boolean;
 	last_poll?: number;
 	total_rules: number;
 	enabled_rules: number;
 	total_executions: number;
 }
 
+export interface TransferLimits {
+	max_per_transfer: number | null;
+	max_per_day: number | null;
+	max_per_month: number | null;
+	max_transfers_per_poll: number | null;
+}
+
 // Server status (from /api/status)
 export interface ServerStatus {
 	status: string;